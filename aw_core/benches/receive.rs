@@ -0,0 +1,124 @@
+//! Receiving bursts of packets like the world and user lists the universe
+//! sends when a client logs in.
+use aw_core::{
+    AWPacket, AWPacketGroup, AWProtocol, Decompressor, DeserializeLimits, PacketType, VarID,
+};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::channel;
+use std::thread;
+
+const BURST_PACKETS: usize = 500;
+
+fn user_list_entry(i: usize) -> AWPacket {
+    let mut packet = AWPacket::new(PacketType::UserList);
+    packet.add_string(VarID::UserListName, format!("Citizen number {i}"));
+    packet.add_string(VarID::UserListWorldName, "AW".to_string());
+    packet.add_uint(VarID::UserListID, i as u32);
+    packet.add_uint(VarID::UserListCitizenID, i as u32);
+    packet.add_uint(VarID::UserListPrivilegeID, 0);
+    packet.add_uint(VarID::UserListAddress, 0x7F000001);
+    packet.add_byte(VarID::UserListState, 1);
+    packet
+}
+
+/// Split a burst into groups the way the universe sends them.
+fn burst_groups() -> Vec<AWPacketGroup> {
+    let mut groups = Vec::new();
+    let mut group = AWPacketGroup::new();
+
+    for i in 0..BURST_PACKETS {
+        if let Err(packet) = group.push(user_list_entry(i)) {
+            groups.push(group);
+            group = AWPacketGroup::new();
+            group.push(packet).expect("a single packet fits in a group");
+        }
+    }
+    groups.push(group);
+
+    groups
+}
+
+fn compressed_burst() -> Vec<Vec<u8>> {
+    burst_groups()
+        .iter()
+        .map(|group| {
+            let mut serialized = Vec::new();
+            for packet in &group.packets {
+                serialized.extend(packet.serialize().unwrap());
+            }
+            AWPacket::compress_if_needed(&serialized).unwrap()
+        })
+        .collect()
+}
+
+fn decompress(c: &mut Criterion) {
+    let burst = compressed_burst();
+    let limits = DeserializeLimits::default();
+
+    let mut group = c.benchmark_group("decompress");
+    group.throughput(Throughput::Elements(BURST_PACKETS as u64));
+
+    group.bench_function("reused scratch buffer", |b| {
+        let mut decompressor = Decompressor::new();
+        b.iter(|| {
+            for compressed in &burst {
+                let data = decompressor
+                    .decompress(compressed, limits.max_decompressed_size)
+                    .unwrap();
+                AWPacketGroup::deserialize(data, &limits).unwrap();
+            }
+        })
+    });
+
+    group.bench_function("new buffer per packet", |b| {
+        b.iter(|| {
+            for compressed in &burst {
+                let data = AWPacket::decompress(compressed).unwrap();
+                AWPacketGroup::deserialize(&data, &limits).unwrap();
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn receive(c: &mut Criterion) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (go_tx, go_rx) = channel::<bool>();
+
+    let sender = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut proto = AWProtocol::new(stream).unwrap();
+        let groups = burst_groups();
+        while let Ok(true) = go_rx.recv() {
+            for group in &groups {
+                proto.send(&mut group.packets.clone(), true).unwrap();
+            }
+        }
+    });
+
+    let mut proto = AWProtocol::new(TcpStream::connect(addr).unwrap()).unwrap();
+
+    let mut group = c.benchmark_group("receive");
+    group.throughput(Throughput::Elements(BURST_PACKETS as u64));
+    group.bench_function("compressed user list burst", |b| {
+        b.iter_batched(
+            || go_tx.send(true).unwrap(),
+            |_| {
+                for _ in 0..BURST_PACKETS {
+                    proto.recv_next_packet().unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+
+    go_tx.send(false).unwrap();
+    sender.join().unwrap();
+}
+
+criterion_group!(benches, decompress, receive);
+criterion_main!(benches);
@@ -1,3 +1,6 @@
+use std::fmt::Write;
+use std::{env, fs, path::Path};
+
 fn main() {
     #[cfg(feature = "stream_cipher_rc4")]
     let stream_cipher_rc4 = true;
@@ -16,4 +19,65 @@ fn main() {
     if !stream_cipher_rc4 && !stream_cipher_aes {
         panic!("One of 'stream_cipher_rc4' or 'stream_cipher_aes' must be enabled");
     }
+
+    generate_dissector();
+}
+
+/// Write the Wireshark dissector to `OUT_DIR`, with its lookup tables built
+/// from the protocol enums.
+fn generate_dissector() {
+    let template_path = "wireshark/aw.lua";
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={template_path}");
+
+    let tables = [
+        ("packet_types", "src/net/packet.rs", "PacketType"),
+        ("var_ids", "src/net/packet_var.rs", "VarID"),
+        ("data_types", "src/net/packet_var.rs", "DataType"),
+        ("reason_codes", "src/reason_code.rs", "ReasonCode"),
+    ];
+
+    let mut generated = String::new();
+    for (table, path, enum_name) in tables {
+        writeln!(generated, "local {table} = {{").unwrap();
+        for (variant, value) in enum_variants(path, enum_name) {
+            writeln!(generated, "    [{value}] = \"{variant}\",").unwrap();
+        }
+        writeln!(generated, "}}\n").unwrap();
+    }
+
+    let template = fs::read_to_string(template_path)
+        .unwrap_or_else(|why| panic!("Could not read {template_path}: {why}"));
+    let dissector = template.replace("-- @GENERATED_TABLES@\n", &generated);
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("aw.lua"), dissector)
+        .unwrap_or_else(|why| panic!("Could not write the dissector: {why}"));
+}
+
+/// Read the `Name = value` variants of `enum_name` from a source file.
+fn enum_variants(path: &str, enum_name: &str) -> Vec<(String, i64)> {
+    println!("cargo:rerun-if-changed={path}");
+
+    let source =
+        fs::read_to_string(path).unwrap_or_else(|why| panic!("Could not read {path}: {why}"));
+    let start = source
+        .find(&format!("enum {enum_name} {{"))
+        .unwrap_or_else(|| panic!("enum {enum_name} is not in {path}"));
+    let body = &source[start..];
+    let body = &body[body.find('{').unwrap() + 1..body.find('}').unwrap()];
+
+    body.lines()
+        .filter_map(|line| {
+            let line = line.split("//").next().unwrap_or_default();
+            let (variant, value) = line.trim().trim_end_matches(',').split_once('=')?;
+            let value = value.trim().parse().unwrap_or_else(|_| {
+                panic!(
+                    "{enum_name}::{} does not have a literal value",
+                    variant.trim()
+                )
+            });
+            Some((variant.trim().to_string(), value))
+        })
+        .collect()
 }
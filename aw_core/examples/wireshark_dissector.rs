@@ -0,0 +1,7 @@
+//! Print the Wireshark dissector for the AW protocol.
+//!
+//! `cargo run -p aw_core --example wireshark_dissector > aw.lua`
+
+fn main() {
+    print!("{}", aw_core::wireshark::DISSECTOR);
+}
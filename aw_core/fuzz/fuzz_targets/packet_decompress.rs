@@ -0,0 +1,19 @@
+#![no_main]
+
+use aw_core::{AWPacket, AWPacketGroup, DeserializeError, DeserializeLimits};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let limits = DeserializeLimits::default();
+
+    match AWPacket::decompress_with_limit(data, limits.max_decompressed_size) {
+        Ok(decompressed) => {
+            assert!(decompressed.len() <= limits.max_decompressed_size);
+            let _ = AWPacketGroup::deserialize(&decompressed, &limits);
+        }
+        Err(DeserializeError::Length)
+        | Err(DeserializeError::Decompression)
+        | Err(DeserializeError::DecompressedTooLarge) => {}
+        Err(why) => panic!("unexpected error from decompress: {why}"),
+    }
+});
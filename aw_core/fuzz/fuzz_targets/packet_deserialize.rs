@@ -0,0 +1,20 @@
+#![no_main]
+
+use aw_core::{AWPacket, DeserializeError};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(serialized_len) = AWPacket::deserialize_check(data) {
+        if let Ok((packet, consumed)) = AWPacket::deserialize(data) {
+            assert_eq!(consumed, serialized_len);
+
+            // Anything that was accepted must survive a round trip
+            if let Ok(serialized) = packet.serialize() {
+                let (again, _) = AWPacket::deserialize(&serialized).unwrap();
+                assert_eq!(again.get_type(), packet.get_type());
+            }
+        }
+    } else if let Err(DeserializeError::Compressed(_)) = AWPacket::deserialize_check(data) {
+        let _ = AWPacket::decompress(data);
+    }
+});
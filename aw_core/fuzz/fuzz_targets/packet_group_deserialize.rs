@@ -0,0 +1,8 @@
+#![no_main]
+
+use aw_core::{AWPacketGroup, DeserializeLimits};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = AWPacketGroup::deserialize(data, &DeserializeLimits::default());
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use aw_core::AWPacketVar;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((var, consumed)) = AWPacketVar::deserialize(data) {
+        assert!(consumed as usize <= data.len());
+        let _ = var.serialize();
+    }
+});
@@ -13,23 +13,34 @@ pub enum RSAKey {
     Private,
 }
 
+/// Size of the keys generated by [`AWCryptRSA::new`], which is what clients
+/// use for their own keys during the handshake.
+pub const DEFAULT_RSA_BITS: u16 = 512;
+
+/// Largest key size supported by the rsaref key format.
+pub const MAX_RSA_BITS: u16 = 1024;
+
 impl AWCryptRSA {
     pub fn new() -> Self {
+        Self::generate(DEFAULT_RSA_BITS).expect("Failed to generate RSA keys")
+    }
+
+    /// Generate a new key pair with a modulus of `bits` bits.
+    pub fn generate(bits: u16) -> Result<Self, RSAError> {
         let random_struct = RandomStruct::new();
 
         let proto_key = RSAProtoKey {
-            bits: 512,
+            bits: bits.into(),
             use_fermat4: true,
         };
 
-        let (pub_key, priv_key) =
-            generate_pem_keys(&proto_key).expect("Failed to generate RSA keys");
+        let (pub_key, priv_key) = generate_pem_keys(&proto_key).map_err(|_| RSAError::Key)?;
 
-        Self {
+        Ok(Self {
             random_struct,
             public_key: Some(pub_key),
             private_key: Some(priv_key),
-        }
+        })
     }
 
     pub fn set_private_key(&mut self, private_key: RSAPrivateKey) {
@@ -1,11 +1,147 @@
+//! Conversion between Rust strings and the bytes of string variables.
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Byte written in place of a character that the encoding cannot represent.
+pub const REPLACEMENT_BYTE: u8 = b'?';
+
+/// Characters 0x80 to 0x9F in Windows-1252. The rest are the same as Latin-1.
+/// Unassigned bytes decode to the replacement character.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}', '\u{017D}', '\u{FFFD}',
+    '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{FFFD}', '\u{017E}', '\u{0178}',
+];
+
+/// How strings are turned into bytes on the wire.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StringEncoding {
+    /// ISO 8859-1, which is what older browsers send
+    #[default]
+    Latin1,
+    /// Latin-1 with printable characters in place of most C1 controls, which
+    /// is what Windows clients actually use
+    Windows1252,
+    Utf8,
+}
+
+impl StringEncoding {
+    /// Encode a string. Characters the encoding cannot represent become
+    /// [`REPLACEMENT_BYTE`].
+    pub fn encode(self, s: &str) -> Vec<u8> {
+        match self {
+            Self::Latin1 => s
+                .chars()
+                .map(|c| u8::try_from(c).unwrap_or(REPLACEMENT_BYTE))
+                .collect(),
+            Self::Windows1252 => s.chars().map(encode_windows_1252).collect(),
+            Self::Utf8 => s.as_bytes().to_vec(),
+        }
+    }
+
+    /// Decode bytes, stripping off any null terminators at the end. Bytes
+    /// that are not valid in the encoding become U+FFFD.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        let text: String = match self {
+            Self::Latin1 => bytes.iter().map(|&b| char::from(b)).collect(),
+            Self::Windows1252 => bytes.iter().map(|&b| decode_windows_1252(b)).collect(),
+            Self::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        };
+        text.trim_end_matches('\0').to_string()
+    }
+
+    /// Length of a string once encoded, without a null terminator.
+    pub fn encoded_len(self, s: &str) -> usize {
+        match self {
+            Self::Latin1 | Self::Windows1252 => s.chars().count(),
+            Self::Utf8 => s.len(),
+        }
+    }
+}
+
+fn encode_windows_1252(c: char) -> u8 {
+    if let Some(index) = WINDOWS_1252_HIGH
+        .iter()
+        .position(|&high| high == c && high != char::REPLACEMENT_CHARACTER)
+    {
+        return 0x80 + index as u8;
+    }
+
+    match u8::try_from(c) {
+        // These bytes mean something else in Windows-1252
+        Ok(0x80..=0x9F) | Err(_) => REPLACEMENT_BYTE,
+        Ok(b) => b,
+    }
+}
+
+fn decode_windows_1252(b: u8) -> char {
+    match b {
+        0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(b - 0x80)],
+        _ => char::from(b),
+    }
+}
+
+/// Decode text from the database. Text is stored as UTF-8, but rows written
+/// by older universe servers may hold Latin-1 instead.
+pub fn decode_stored_text(bytes: &[u8]) -> String {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => Cow::Owned(StringEncoding::Latin1.decode(bytes)),
+    };
+    text.trim_end_matches('\0').to_string()
+}
+
 pub fn latin1_to_string(s: &[u8]) -> String {
-    s.iter()
-        .map(|&c| c as char)
-        .collect::<String>()
-        .trim_end_matches('\0') // Strip off any null terminator
-        .to_string()
+    StringEncoding::Latin1.decode(s)
 }
 
 pub fn string_to_latin1(s: &str) -> Vec<u8> {
-    s.chars().map(|c| c as u8).collect()
+    StringEncoding::Latin1.encode(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossy_conversions_use_replacement_characters() {
+        let text = "Zoë paid 5€ for ☃";
+
+        assert_eq!(StringEncoding::Latin1.encode(text), b"Zo\xEB paid 5? for ?");
+        assert_eq!(
+            StringEncoding::Windows1252.encode(text),
+            b"Zo\xEB paid 5\x80 for ?"
+        );
+        assert_eq!(
+            StringEncoding::Utf8.decode(StringEncoding::Utf8.encode(text).as_slice()),
+            text
+        );
+
+        assert_eq!(
+            StringEncoding::Windows1252.decode(b"5\x80\x81\0\0"),
+            "5€\u{FFFD}"
+        );
+        assert_eq!(StringEncoding::Latin1.decode(b"a\0b\0"), "a\0b");
+        assert_eq!(StringEncoding::Utf8.decode(b"a\xFFb"), "a\u{FFFD}b");
+        assert_eq!(StringEncoding::Latin1.decode(b"Zo\xEB\0"), "Zoë");
+
+        for encoding in [
+            StringEncoding::Latin1,
+            StringEncoding::Windows1252,
+            StringEncoding::Utf8,
+        ] {
+            assert_eq!(
+                encoding.encoded_len(text),
+                encoding.encode(text).len(),
+                "{encoding:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn stored_text_falls_back_to_latin1() {
+        assert_eq!(decode_stored_text("Zoë".as_bytes()), "Zoë");
+        assert_eq!(decode_stored_text(b"Zo\xEB"), "Zoë");
+    }
 }
@@ -1,3 +1,6 @@
+// Lets code generated by aw_derive refer to ::aw_core from inside this crate.
+extern crate self as aw_core;
+
 mod crypt_rsa;
 pub use crypt_rsa::*;
 
@@ -17,6 +20,10 @@ mod net;
 pub use net::*;
 
 mod reason_code;
-pub use reason_code::ReasonCode;
+pub use reason_code::{Language, ReasonCode, ReasonCodeCategory};
+
+pub mod date;
 
 pub mod encoding;
+
+pub mod wireshark;
@@ -1,9 +1,62 @@
-use crate::{AWPacket, AWPacketGroup, AWProtocol, ProtocolMessage};
+use crate::{
+    encoding::StringEncoding, AWPacket, AWPacketGroup, AWProtocol, HandshakeAction,
+    ProtocolMessage, TrafficCounter, TrafficMeter, TrafficStats,
+};
 use std::{
-    net::SocketAddr,
-    sync::mpsc::{Receiver, Sender},
+    net::{Shutdown, SocketAddr, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{Receiver, Sender},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
+/// Bytes a connection may have waiting to be sent before the peer is
+/// considered too slow and disconnected.
+pub const DEFAULT_SEND_QUEUE_LIMIT: usize = 1 << 20;
+
+/// Packets waiting to be sent on a connection, shared between the connection
+/// and its protocol thread.
+#[derive(Debug, Default)]
+pub struct SendQueue {
+    packets: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+impl SendQueue {
+    /// Number of packets waiting to be sent.
+    pub fn packets(&self) -> usize {
+        self.packets.load(Ordering::Relaxed)
+    }
+
+    /// Serialized size of the packets waiting to be sent, before compression.
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Count packets as queued, returning the new number of queued bytes.
+    pub(crate) fn push(&self, packets: &[AWPacket]) -> usize {
+        let bytes = Self::size_of(packets);
+        self.packets.fetch_add(packets.len(), Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed) + bytes
+    }
+
+    /// Count packets as no longer queued.
+    pub(crate) fn pop(&self, packets: &[AWPacket]) {
+        let bytes = Self::size_of(packets);
+        self.packets.fetch_sub(packets.len(), Ordering::Relaxed);
+        self.bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    fn size_of(packets: &[AWPacket]) -> usize {
+        packets
+            .iter()
+            .map(|packet| packet.serialize_len().unwrap_or_default())
+            .sum()
+    }
+}
+
 #[derive(Debug)]
 pub struct AWConnection {
     outbound: Sender<ProtocolMessage>,
@@ -11,11 +64,24 @@ pub struct AWConnection {
     a4_send_key: Vec<u8>,
     disconnected: bool,
     addr: SocketAddr,
+    send_queue: Arc<SendQueue>,
+    send_queue_limit: usize,
+    /// Set when the peer fell too far behind on reading what was sent
+    too_slow: AtomicBool,
+    /// Used to interrupt the protocol thread when it is stuck writing to a slow peer
+    stream: Option<TcpStream>,
+    traffic: Arc<TrafficMeter>,
+    connected_at: Instant,
+    encoding: StringEncoding,
 }
 
 impl AWConnection {
     pub fn new(protocol: AWProtocol, addr: SocketAddr) -> Self {
         let a4_send_key = protocol.get_send_key();
+        let send_queue = protocol.send_queue();
+        let stream = protocol.try_clone_stream().ok();
+        let traffic = protocol.traffic();
+        let encoding = protocol.encoding();
 
         let (outbound, inbound) = protocol.start_process_loop();
 
@@ -25,6 +91,13 @@ impl AWConnection {
             a4_send_key,
             disconnected: false,
             addr,
+            send_queue,
+            send_queue_limit: DEFAULT_SEND_QUEUE_LIMIT,
+            too_slow: AtomicBool::new(false),
+            stream,
+            traffic,
+            connected_at: Instant::now(),
+            encoding,
         }
     }
 
@@ -32,14 +105,71 @@ impl AWConnection {
         self.addr
     }
 
+    /// How long ago the connection was established.
+    pub fn age(&self) -> Duration {
+        self.connected_at.elapsed()
+    }
+
+    /// Traffic sent and received on this connection so far, and what is
+    /// still waiting to be sent.
+    pub fn traffic_stats(&self) -> TrafficStats {
+        let mut stats = self.traffic.snapshot();
+        stats.queued = TrafficCounter {
+            packets: self.send_queue.packets() as u64,
+            bytes: self.send_queue.bytes() as u64,
+        };
+        stats
+    }
+
+    /// Set how many bytes may wait to be sent before the peer is disconnected.
+    pub fn set_send_queue_limit(&mut self, bytes: usize) {
+        self.send_queue_limit = bytes;
+    }
+
+    /// Packets that have been sent on this connection but not yet written to the socket.
+    pub fn send_queue(&self) -> &SendQueue {
+        &self.send_queue
+    }
+
     pub fn send(&self, packet: AWPacket) {
-        self.outbound.send(ProtocolMessage::Packet(packet)).ok();
+        if self.enqueue(std::slice::from_ref(&packet)) {
+            self.outbound.send(ProtocolMessage::Packet(packet)).ok();
+        }
     }
 
     pub fn send_group(&self, packets: AWPacketGroup) {
-        self.outbound
-            .send(ProtocolMessage::PacketGroup(packets.packets))
-            .ok();
+        if self.enqueue(&packets.packets) {
+            self.outbound
+                .send(ProtocolMessage::PacketGroup(packets.packets))
+                .ok();
+        }
+    }
+
+    /// Count packets against the send queue limit, and disconnect the peer if
+    /// they exceed it. Returns whether the packets should still be sent.
+    fn enqueue(&self, packets: &[AWPacket]) -> bool {
+        if self.too_slow.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        let queued = self.send_queue.push(packets);
+        if queued <= self.send_queue_limit {
+            return true;
+        }
+
+        self.send_queue.pop(packets);
+        if !self.too_slow.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "Disconnecting {} because {queued} bytes are waiting to be sent to it",
+                self.addr
+            );
+            self.outbound.send(ProtocolMessage::Disconnect).ok();
+            if let Some(stream) = &self.stream {
+                stream.shutdown(Shutdown::Both).ok();
+            }
+        }
+
+        false
     }
 
     pub fn set_recv_key(&self, key: &[u8]) {
@@ -48,10 +178,31 @@ impl AWConnection {
             .ok();
     }
 
+    /// Carry out what a handshake needs done, in order.
+    pub fn apply_handshake(&self, actions: Vec<HandshakeAction>) {
+        for action in actions {
+            match action {
+                HandshakeAction::Send(packet) => self.send(packet),
+                HandshakeAction::EnableEncryption => self.encrypt_data(true),
+                HandshakeAction::SetRecvKey(key) => self.set_recv_key(&key),
+            }
+        }
+    }
+
     pub fn get_send_key(&self) -> Vec<u8> {
         self.a4_send_key.clone()
     }
 
+    /// Set how string variables are encoded from now on, in both directions.
+    pub fn set_encoding(&mut self, encoding: StringEncoding) {
+        self.encoding = encoding;
+        self.outbound.send(ProtocolMessage::Encoding(encoding)).ok();
+    }
+
+    pub fn encoding(&self) -> StringEncoding {
+        self.encoding
+    }
+
     pub fn encrypt_data(&self, should: bool) {
         self.outbound.send(ProtocolMessage::Encrypt(should)).ok();
     }
@@ -70,7 +221,7 @@ impl AWConnection {
     }
 
     pub fn is_disconnected(&self) -> bool {
-        self.disconnected
+        self.disconnected || self.too_slow.load(Ordering::Relaxed)
     }
 }
 
@@ -79,3 +230,69 @@ impl Drop for AWConnection {
         self.disconnect();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AWPacketVar, PacketType};
+    use std::net::TcpListener;
+
+    fn packet_with_text(text: &str) -> AWPacket {
+        let mut packet = AWPacket::new(PacketType::Address);
+        packet.add_var(AWPacketVar::string(1u16, text.to_string()));
+        packet
+    }
+
+    fn connect() -> (AWConnection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).unwrap();
+        let (server, peer) = listener.accept().unwrap();
+        let connection = AWConnection::new(AWProtocol::new(server).unwrap(), peer);
+        (connection, client)
+    }
+
+    #[test]
+    fn send_queue_counts_bytes() {
+        let queue = SendQueue::default();
+        let small = packet_with_text("a");
+        let large = packet_with_text("a longer string of text");
+        let small_len = small.serialize_len().unwrap();
+        let large_len = large.serialize_len().unwrap();
+
+        assert_eq!(queue.push(std::slice::from_ref(&small)), small_len);
+        assert_eq!(
+            queue.push(&[large.clone(), small.clone()]),
+            large_len + 2 * small_len
+        );
+        assert_eq!(queue.packets(), 3);
+
+        queue.pop(&[small.clone(), large]);
+        assert_eq!(queue.packets(), 1);
+        assert_eq!(queue.bytes(), small_len);
+
+        queue.pop(&[small]);
+        assert_eq!(queue.packets(), 0);
+        assert_eq!(queue.bytes(), 0);
+    }
+
+    #[test]
+    fn overflowing_send_queue_drops_peer() {
+        let (mut connection, _client) = connect();
+        let packet = packet_with_text("more than the limit");
+        let packet_len = packet.serialize_len().unwrap();
+        connection.set_send_queue_limit(packet_len - 1);
+
+        assert!(!connection.enqueue(std::slice::from_ref(&packet)));
+        assert!(connection.is_disconnected());
+        // The packet that went over the limit is not left counted
+        assert_eq!(connection.send_queue().packets(), 0);
+        assert_eq!(connection.send_queue().bytes(), 0);
+
+        // Nothing more is queued once the peer has been dropped
+        connection.set_send_queue_limit(DEFAULT_SEND_QUEUE_LIMIT);
+        connection.send(packet_with_text("a"));
+        assert_eq!(connection.send_queue().bytes(), 0);
+        assert_eq!(connection.traffic_stats().queued.packets, 0);
+    }
+}
@@ -0,0 +1,374 @@
+//! Key exchange at the start of a connection.
+//!
+//! The client asks for the server's public RSA key and answers with its own
+//! stream key encrypted with it, followed by its own public RSA key. The
+//! server then answers with its stream key encrypted with the client's key.
+//! Each side encrypts what it sends as soon as it has sent its stream key.
+//!
+//! Neither side does any I/O here. Packets are fed in with `handle` and the
+//! resulting [`HandshakeAction`]s are applied to a connection, e.g. with
+//! [`AWConnection::apply_handshake`](crate::AWConnection::apply_handshake).
+use crate::{AWCryptRSA, AWPacket, PacketType, PacketTypeResult, VarID};
+use std::fmt;
+
+/// Something a connection has to do for the handshake to make progress.
+#[derive(Clone, PartialEq)]
+pub enum HandshakeAction {
+    /// Send a packet to the peer
+    Send(AWPacket),
+    /// Encrypt everything sent from now on
+    EnableEncryption,
+    /// Decrypt everything received from now on with the peer's stream key
+    SetRecvKey(Vec<u8>),
+}
+
+/// Leaves out the packets and keys, which carry the stream keys.
+impl fmt::Debug for HandshakeAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Send(packet) => f.debug_tuple("Send").field(&packet.get_type()).finish(),
+            Self::EnableEncryption => write!(f, "EnableEncryption"),
+            Self::SetRecvKey(_) => write!(f, "SetRecvKey(..)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HandshakeError {
+    /// The packet is not part of the handshake or arrived out of order
+    UnexpectedPacket(PacketTypeResult),
+    /// The packet does not contain an encryption key
+    MissingKey,
+    /// The peer's public RSA key could not be decoded
+    InvalidPublicKey,
+    /// Our public RSA key could not be encoded
+    EncodeFailed,
+    /// Our stream key could not be encrypted with the peer's public key
+    EncryptFailed,
+    /// The peer's stream key could not be decrypted with our private key
+    DecryptFailed,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedPacket(packet_type) => {
+                write!(f, "Unexpected {packet_type:?} packet during handshake")
+            }
+            Self::MissingKey => write!(f, "Handshake packet has no encryption key"),
+            Self::InvalidPublicKey => write!(f, "Could not decode the peer's public key"),
+            Self::EncodeFailed => write!(f, "Could not encode our public key"),
+            Self::EncryptFailed => write!(f, "Could not encrypt our stream key"),
+            Self::DecryptFailed => write!(f, "Could not decrypt the peer's stream key"),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+/// Whether a packet is one that only the handshake deals with.
+pub fn is_handshake_packet(packet: &AWPacket) -> bool {
+    matches!(
+        packet.get_type(),
+        PacketTypeResult::PacketType(
+            PacketType::PublicKeyRequest
+                | PacketType::PublicKeyResponse
+                | PacketType::StreamKeyResponse
+        )
+    )
+}
+
+fn encryption_key(packet: &AWPacket) -> Result<Vec<u8>, HandshakeError> {
+    packet
+        .get_data(VarID::EncryptionKey)
+        .ok_or(HandshakeError::MissingKey)
+}
+
+/// Encrypt our stream key with the peer's public RSA key.
+fn encrypt_stream_key(peer_public_key: &[u8], send_key: &[u8]) -> Result<Vec<u8>, HandshakeError> {
+    let mut peer_rsa = AWCryptRSA::default();
+    peer_rsa.randomize();
+    peer_rsa
+        .decode_public_key(peer_public_key)
+        .map_err(|_| HandshakeError::InvalidPublicKey)?;
+    peer_rsa
+        .encrypt_public(send_key)
+        .map_err(|_| HandshakeError::EncryptFailed)
+}
+
+fn public_key_packet(rsa: &AWCryptRSA) -> Result<AWPacket, HandshakeError> {
+    let key = rsa
+        .encode_public_key()
+        .ok_or(HandshakeError::EncodeFailed)?;
+    let mut packet = AWPacket::new(PacketType::PublicKeyResponse);
+    packet.add_data(VarID::EncryptionKey, key);
+    Ok(packet)
+}
+
+fn stream_key_packet(encrypted_key: Vec<u8>) -> AWPacket {
+    let mut packet = AWPacket::new(PacketType::StreamKeyResponse);
+    packet.add_data(VarID::EncryptionKey, encrypted_key);
+    packet
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientState {
+    Idle,
+    AwaitingPublicKey,
+    AwaitingStreamKey,
+    Complete,
+}
+
+/// The side of the handshake that opens the connection.
+#[derive(Debug)]
+pub struct ClientHandshake {
+    rsa: AWCryptRSA,
+    send_key: Vec<u8>,
+    state: ClientState,
+}
+
+impl ClientHandshake {
+    /// Start a handshake for a connection whose stream key is `send_key`,
+    /// using a newly generated RSA key pair.
+    pub fn new(send_key: Vec<u8>) -> Self {
+        Self::with_rsa(AWCryptRSA::new(), send_key)
+    }
+
+    /// Start a handshake using an existing RSA key pair.
+    pub fn with_rsa(rsa: AWCryptRSA, send_key: Vec<u8>) -> Self {
+        Self {
+            rsa,
+            send_key,
+            state: ClientState::Idle,
+        }
+    }
+
+    /// Ask the server for its public key.
+    pub fn start(&mut self) -> Vec<HandshakeAction> {
+        self.state = ClientState::AwaitingPublicKey;
+        vec![HandshakeAction::Send(AWPacket::new(
+            PacketType::PublicKeyRequest,
+        ))]
+    }
+
+    /// Handle a packet from the server.
+    pub fn handle(&mut self, packet: &AWPacket) -> Result<Vec<HandshakeAction>, HandshakeError> {
+        let packet_type = packet.get_type();
+
+        match (self.state, packet_type) {
+            (
+                ClientState::AwaitingPublicKey,
+                PacketTypeResult::PacketType(PacketType::PublicKeyResponse),
+            ) => {
+                let encrypted_key = encrypt_stream_key(&encryption_key(packet)?, &self.send_key)?;
+
+                // The client's header differs from the server's, or else the
+                // server refuses the login later on.
+                let mut public_key = public_key_packet(&self.rsa)?;
+                public_key.set_header_1(2);
+
+                self.state = ClientState::AwaitingStreamKey;
+                Ok(vec![
+                    HandshakeAction::Send(stream_key_packet(encrypted_key)),
+                    HandshakeAction::EnableEncryption,
+                    HandshakeAction::Send(public_key),
+                ])
+            }
+            (
+                ClientState::AwaitingStreamKey,
+                PacketTypeResult::PacketType(PacketType::StreamKeyResponse),
+            ) => {
+                let recv_key = self
+                    .rsa
+                    .decrypt_private(&encryption_key(packet)?)
+                    .map_err(|_| HandshakeError::DecryptFailed)?;
+
+                self.state = ClientState::Complete;
+                Ok(vec![HandshakeAction::SetRecvKey(recv_key)])
+            }
+            _ => Err(HandshakeError::UnexpectedPacket(packet_type)),
+        }
+    }
+
+    /// Whether both stream keys have been exchanged.
+    pub fn is_complete(&self) -> bool {
+        self.state == ClientState::Complete
+    }
+}
+
+/// The side of the handshake that accepts the connection.
+#[derive(Debug)]
+pub struct ServerHandshake {
+    rsa: AWCryptRSA,
+    send_key: Vec<u8>,
+    sent_stream_key: bool,
+    received_stream_key: bool,
+}
+
+impl ServerHandshake {
+    /// Prepare a handshake for a connection whose stream key is `send_key`.
+    /// A new RSA key pair is generated for every connection, since clients
+    /// before AW 7.0 use very weak RSA encryption.
+    pub fn new(send_key: Vec<u8>) -> Self {
+        Self::with_rsa(AWCryptRSA::new(), send_key)
+    }
+
+    /// Prepare a handshake using an existing RSA key pair.
+    pub fn with_rsa(rsa: AWCryptRSA, send_key: Vec<u8>) -> Self {
+        Self {
+            rsa,
+            send_key,
+            sent_stream_key: false,
+            received_stream_key: false,
+        }
+    }
+
+    /// Handle a packet from the client.
+    pub fn handle(&mut self, packet: &AWPacket) -> Result<Vec<HandshakeAction>, HandshakeError> {
+        let packet_type = packet.get_type();
+
+        match packet_type {
+            PacketTypeResult::PacketType(PacketType::PublicKeyRequest) => {
+                Ok(vec![HandshakeAction::Send(public_key_packet(&self.rsa)?)])
+            }
+            PacketTypeResult::PacketType(PacketType::PublicKeyResponse)
+                if !self.sent_stream_key =>
+            {
+                let encrypted_key = encrypt_stream_key(&encryption_key(packet)?, &self.send_key)?;
+
+                self.sent_stream_key = true;
+                Ok(vec![
+                    HandshakeAction::Send(stream_key_packet(encrypted_key)),
+                    HandshakeAction::EnableEncryption,
+                ])
+            }
+            PacketTypeResult::PacketType(PacketType::StreamKeyResponse)
+                if !self.received_stream_key =>
+            {
+                let recv_key = self
+                    .rsa
+                    .decrypt_private(&encryption_key(packet)?)
+                    .map_err(|_| HandshakeError::DecryptFailed)?;
+
+                self.received_stream_key = true;
+                Ok(vec![HandshakeAction::SetRecvKey(recv_key)])
+            }
+            _ => Err(HandshakeError::UnexpectedPacket(packet_type)),
+        }
+    }
+
+    /// Whether the client has our stream key and we have theirs.
+    pub fn is_complete(&self) -> bool {
+        self.sent_stream_key && self.received_stream_key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pass every packet one side sends to the other, and collect the rest.
+    fn deliver(
+        actions: Vec<HandshakeAction>,
+        mut handle: impl FnMut(&AWPacket) -> Result<Vec<HandshakeAction>, HandshakeError>,
+        other: &mut Vec<HandshakeAction>,
+        mine: &mut Vec<HandshakeAction>,
+    ) {
+        for action in actions {
+            if let HandshakeAction::Send(packet) = &action {
+                other.extend(handle(packet).unwrap());
+            }
+            mine.push(action);
+        }
+    }
+
+    fn recv_key(actions: &[HandshakeAction]) -> Option<&[u8]> {
+        actions.iter().find_map(|action| match action {
+            HandshakeAction::SetRecvKey(key) => Some(key.as_slice()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn client_and_server_exchange_keys() {
+        let client_key = vec![1u8; 16];
+        let server_key = vec![2u8; 16];
+        let mut client = ClientHandshake::new(client_key.clone());
+        let mut server = ServerHandshake::new(server_key.clone());
+
+        let mut client_actions = Vec::new();
+        let mut server_actions = Vec::new();
+
+        let mut to_server = client.start();
+        while !to_server.is_empty() {
+            let mut to_client = Vec::new();
+            deliver(
+                to_server,
+                |packet| server.handle(packet),
+                &mut to_client,
+                &mut client_actions,
+            );
+
+            to_server = Vec::new();
+            deliver(
+                to_client,
+                |packet| client.handle(packet),
+                &mut to_server,
+                &mut server_actions,
+            );
+        }
+
+        assert!(client.is_complete());
+        assert!(server.is_complete());
+        assert_eq!(recv_key(&client_actions), Some(server_key.as_slice()));
+        assert_eq!(recv_key(&server_actions), Some(client_key.as_slice()));
+
+        // Encryption starts right after each side sends its stream key
+        for actions in [&client_actions, &server_actions] {
+            let stream_key = actions
+                .iter()
+                .position(|action| match action {
+                    HandshakeAction::Send(packet) => {
+                        packet.get_type()
+                            == PacketTypeResult::PacketType(PacketType::StreamKeyResponse)
+                    }
+                    _ => false,
+                })
+                .unwrap();
+            assert_eq!(
+                actions.get(stream_key + 1),
+                Some(&HandshakeAction::EnableEncryption)
+            );
+        }
+    }
+
+    #[test]
+    fn client_rejects_out_of_order_packets() {
+        let mut client = ClientHandshake::new(vec![1u8; 16]);
+        client.start();
+
+        let packet = AWPacket::new(PacketType::StreamKeyResponse);
+        assert_eq!(
+            client.handle(&packet),
+            Err(HandshakeError::UnexpectedPacket(packet.get_type()))
+        );
+
+        let packet = AWPacket::new(PacketType::PublicKeyResponse);
+        assert_eq!(client.handle(&packet), Err(HandshakeError::MissingKey));
+    }
+
+    #[test]
+    fn debug_leaves_out_keys() {
+        let key = vec![0xAB; 16];
+        let mut packet = AWPacket::new(PacketType::StreamKeyResponse);
+        packet.add_data(VarID::EncryptionKey, key.clone());
+
+        let actions = vec![
+            HandshakeAction::Send(packet),
+            HandshakeAction::SetRecvKey(key),
+        ];
+        let debug = format!("{actions:?}");
+        assert!(debug.contains("StreamKeyResponse"));
+        assert!(!debug.contains("171"));
+    }
+}
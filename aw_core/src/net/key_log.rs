@@ -0,0 +1,52 @@
+//! Log of stream cipher keys, for decrypting captured traffic.
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Name of the stream cipher this build uses, as written to the key log.
+#[cfg(feature = "stream_cipher_aes")]
+pub const KEY_LOG_CIPHER: &str = "AES";
+#[cfg(feature = "stream_cipher_rc4")]
+pub const KEY_LOG_CIPHER: &str = "A4";
+
+/// A file that stream keys are appended to, shared between connections.
+///
+/// Each key is one line of the form
+/// `AW_STREAM_KEY <source> <destination> <cipher> <key as hex>`, meaning that
+/// data sent from `source` to `destination` is encrypted with that key. The
+/// Wireshark dissector in [`crate::wireshark`] reads this format.
+#[derive(Debug, Clone)]
+pub struct KeyLog {
+    file: Arc<Mutex<File>>,
+}
+
+impl KeyLog {
+    /// Open a key log, appending to it if it already exists.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Record the key used for data sent from `source` to `destination`.
+    pub fn log_key(&self, source: SocketAddr, destination: SocketAddr, key: &[u8]) {
+        let mut line = format!("AW_STREAM_KEY {source} {destination} {KEY_LOG_CIPHER} ");
+        for byte in key {
+            write!(line, "{byte:02x}").ok();
+        }
+        line.push('\n');
+
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+
+        if let Err(why) = file.write_all(line.as_bytes()) {
+            log::error!("Could not write to key log: {why}");
+        }
+    }
+}
@@ -7,5 +7,19 @@ pub use packet::*;
 mod packet_var;
 pub use packet_var::*;
 
+mod key_log;
+pub use key_log::*;
+
 mod connection;
 pub use connection::*;
+
+mod handshake;
+pub use handshake::*;
+
+mod stats;
+pub use stats::*;
+
+mod typed_packet;
+pub use typed_packet::*;
+
+pub mod schema;
@@ -1,12 +1,12 @@
 //! Packet (de)serialization for AW
-use crate::{net::packet_var::AWPacketVar, PacketData};
+use crate::{encoding::StringEncoding, net::packet_var::AWPacketVar, PacketData};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
-use flate2::Compression;
+use flate2::{Compression, Decompress, FlushDecompress, Status};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
-use std::io::{Cursor, Read, Write};
+use std::fmt;
+use std::io::{Cursor, Write};
 
 /// Packet which can be sent over an AWProtocol.
 #[derive(Debug, PartialEq, Clone)]
@@ -98,6 +98,8 @@ impl AWPacket {
         for var in &self.vars {
             match &var.data {
                 PacketData::Int(x) if var.id == var_id => return Some(*x as u32),
+                // Only packets built locally hold Uint; received ones hold Int
+                PacketData::Uint(x) if var.id == var_id => return Some(*x),
                 _ => {}
             }
         }
@@ -137,6 +139,17 @@ impl AWPacket {
         None
     }
 
+    /// Reinterpret every string variable of a packet that was decoded as
+    /// `from` but should have been decoded as `to`. This is only lossless if
+    /// `from` can encode every character it decodes, like Latin-1.
+    pub fn transcode_strings(&mut self, from: StringEncoding, to: StringEncoding) {
+        for var in &mut self.vars {
+            if let PacketData::String(x) = &mut var.data {
+                *x = to.decode(&from.encode(x));
+            }
+        }
+    }
+
     pub fn add_data(&mut self, id: impl Into<u16>, value: Vec<u8>) {
         self.add_var(AWPacketVar::data(id.into(), value));
     }
@@ -153,12 +166,18 @@ impl AWPacket {
         None
     }
 
-    /// The expected length of the packet after serialization.
-    fn serialize_len(&self) -> Result<usize, String> {
+    /// The expected length of the packet after serialization with strings in Latin-1.
+    pub(crate) fn serialize_len(&self) -> Result<usize, String> {
+        self.serialize_len_encoded(StringEncoding::default())
+    }
+
+    /// The expected length of the packet after serialization with strings in
+    /// the given encoding.
+    pub(crate) fn serialize_len_encoded(&self, encoding: StringEncoding) -> Result<usize, String> {
         let mut size = TagHeader::length();
 
         for var in &self.vars {
-            let var_serialized_len = var.serialize_len().ok_or(
+            let var_serialized_len = var.serialize_len_encoded(encoding).ok_or(
                 "serialize_len calculation failed because a var was too large".to_string(),
             )?;
 
@@ -171,9 +190,14 @@ impl AWPacket {
         Ok(size)
     }
 
-    /// Encode the given packet.
+    /// Encode the given packet with strings in Latin-1.
     pub fn serialize(&self) -> Result<Vec<u8>, String> {
-        let serialize_len = self.serialize_len()?;
+        self.serialize_encoded(StringEncoding::default())
+    }
+
+    /// Encode the given packet with strings in the given encoding.
+    pub fn serialize_encoded(&self, encoding: StringEncoding) -> Result<Vec<u8>, String> {
+        let serialize_len = self.serialize_len_encoded(encoding)?;
 
         if serialize_len > u16::MAX.into() {
             return Err(format!("Serializing packet too large: {serialize_len}"));
@@ -192,7 +216,7 @@ impl AWPacket {
 
         result.extend(header.serialize());
         for var in &self.vars {
-            result.extend(var.serialize()?);
+            result.extend(var.serialize_encoded(encoding)?);
         }
 
         Ok(result)
@@ -240,64 +264,85 @@ impl AWPacket {
     }
 
     /// Decompress a compressed packet and return its decompressed serialized bytes.
-    pub fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
-        let compressed_data = data
-            .get(TagHeader::length()..)
-            .ok_or("Data not long enough to do any decompression".to_string())?;
+    pub fn decompress(data: &[u8]) -> Result<Vec<u8>, DeserializeError> {
+        Self::decompress_with_limit(data, DeserializeLimits::default().max_decompressed_size)
+    }
+
+    /// Decompress a compressed packet, refusing to produce more than `max_size` bytes.
+    pub fn decompress_with_limit(
+        data: &[u8],
+        max_size: usize,
+    ) -> Result<Vec<u8>, DeserializeError> {
+        Decompressor::new()
+            .decompress(data, max_size)
+            .map(<[u8]>::to_vec)
+    }
+
+    /// Decode a packet with the default [`DeserializeLimits`] and return an
+    /// instance and the number of bytes it took up if successful.
+    pub fn deserialize(data: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Self::deserialize_with_limits(data, &DeserializeLimits::default())
+    }
+
+    /// Decode a packet with strings in Latin-1 and return an instance and the
+    /// number of bytes it took up if successful.
+    pub fn deserialize_with_limits(
+        data: &[u8],
+        limits: &DeserializeLimits,
+    ) -> Result<(Self, usize), DeserializeError> {
+        Self::deserialize_encoded(data, limits, StringEncoding::default())
+    }
+
+    /// Decode a packet with strings in the given encoding and return an
+    /// instance and the number of bytes it took up if successful.
+    pub fn deserialize_encoded(
+        data: &[u8],
+        limits: &DeserializeLimits,
+        encoding: StringEncoding,
+    ) -> Result<(Self, usize), DeserializeError> {
+        let (header, consumed) = TagHeader::deserialize(data)?;
+        let serialized_length = usize::from(header.serialized_length);
 
-        let mut decoder = ZlibDecoder::new(compressed_data);
-        let mut decompressed_bytes = Vec::<u8>::new();
-        match decoder.read_to_end(&mut decompressed_bytes) {
-            Ok(_) => Ok(decompressed_bytes),
-            Err(_) => Err("Failed to decode compressed data".to_string()),
+        if serialized_length < TagHeader::length() {
+            return Err(DeserializeError::InvalidHeader);
         }
-    }
 
-    /// Decode a packet and return an instance if successful.
-    pub fn deserialize(mut data: &[u8]) -> Result<(Self, usize), String> {
-        let mut total_consumed: usize = 0;
-        let (header, consumed) = TagHeader::deserialize(data)?;
-        let consumed: usize = consumed.try_into().map_err(|why| {
-            format!("TagHeader::deserialize consumed too many bytes: {consumed} - {why:?}")
-        })?;
-
-        data = data
-            .get(consumed..)
-            .ok_or("Not enough data to deserialize".to_string())?;
+        if header.var_count > limits.max_var_count {
+            return Err(DeserializeError::TooManyVars(header.var_count));
+        }
 
-        total_consumed = total_consumed
-            .checked_add(consumed)
-            .ok_or("Consumed too much data while deserializing".to_string())?;
+        // Variables may not run on into whatever follows this packet
+        let mut data = data
+            .get(..serialized_length)
+            .ok_or(DeserializeError::Length)?;
+        let mut total_consumed = consumed;
+        data = data.get(consumed..).ok_or(DeserializeError::Length)?;
 
-        let mut vars = Vec::<AWPacketVar>::with_capacity(header.var_count as usize);
+        let mut vars = Vec::<AWPacketVar>::with_capacity(header.var_count.into());
 
         for _ in 0..header.var_count {
-            let (var, consumed) = AWPacketVar::deserialize(data)?;
-            let consumed: usize = consumed.try_into().map_err(|why| {
-                format!("AWPacketVar::deserialize consumed too many bytes: {consumed} - {why:?}")
-            })?;
-            data = data
-                .get(consumed..)
-                .ok_or("Not enough data to deserialize".to_string())?;
-
+            let (var, consumed) = AWPacketVar::deserialize_encoded(data, limits, encoding)
+                .map_err(|why| match why {
+                    DeserializeError::Length => DeserializeError::Truncated,
+                    why => why,
+                })?;
+            let consumed = usize::try_from(consumed).map_err(|_| DeserializeError::Truncated)?;
+            data = data.get(consumed..).ok_or(DeserializeError::Truncated)?;
             total_consumed = total_consumed
                 .checked_add(consumed)
-                .ok_or("Consumed too much data while deserializing".to_string())?;
+                .ok_or(DeserializeError::Truncated)?;
 
             vars.push(var);
         }
 
-        if total_consumed != header.serialized_length.into() {
-            return Err(format!(
-                "Consumed {total_consumed} bytes instead of {}",
-                header.serialized_length
-            ));
+        if total_consumed != serialized_length {
+            return Err(DeserializeError::LengthMismatch {
+                expected: serialized_length,
+                actual: total_consumed,
+            });
         }
 
-        let opcode = match PacketType::from_i16(header.opcode) {
-            Some(packet_type) => PacketTypeResult::PacketType(packet_type),
-            None => PacketTypeResult::Unknown(header.opcode),
-        };
+        let opcode = PacketTypeResult::from(header.opcode);
 
         Ok((
             Self {
@@ -312,9 +357,9 @@ impl AWPacket {
 
     /// Examine serialized header to see what the state of this packet is.
     pub fn deserialize_check(src: &[u8]) -> Result<usize, DeserializeError> {
-        let (header, _) = TagHeader::deserialize(src).map_err(|_| DeserializeError::Length)?;
+        let (header, _) = TagHeader::deserialize(src)?;
 
-        if !header.is_valid() {
+        if !header.is_valid() || usize::from(header.serialized_length) < TagHeader::length() {
             return Err(DeserializeError::InvalidHeader);
         }
 
@@ -334,19 +379,102 @@ impl AWPacket {
     }
 }
 
+/// Decompresses packets into a buffer that is reused from one packet to the next.
+pub struct Decompressor {
+    inflate: Decompress,
+    scratch: Vec<u8>,
+}
+
+impl Decompressor {
+    pub fn new() -> Self {
+        Self {
+            inflate: Decompress::new(true),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Decompress a compressed packet, refusing to produce more than
+    /// `max_size` bytes. The result is valid until the next call.
+    pub fn decompress(&mut self, data: &[u8], max_size: usize) -> Result<&[u8], DeserializeError> {
+        let compressed_data = data
+            .get(TagHeader::length()..)
+            .ok_or(DeserializeError::Length)?;
+
+        self.inflate.reset(true);
+        self.scratch.clear();
+
+        loop {
+            // Leave room for one byte past the limit to tell whether there was more
+            if self.scratch.len() == self.scratch.capacity() {
+                let room = max_size
+                    .saturating_add(1)
+                    .saturating_sub(self.scratch.len())
+                    .min(0x4000);
+                if room == 0 {
+                    return Err(DeserializeError::DecompressedTooLarge);
+                }
+                self.scratch.reserve(room);
+            }
+
+            let total_in = self.inflate.total_in();
+            let consumed =
+                usize::try_from(total_in).map_err(|_| DeserializeError::Decompression)?;
+            let input = compressed_data
+                .get(consumed..)
+                .ok_or(DeserializeError::Decompression)?;
+            let produced_before = self.scratch.len();
+
+            let status = self
+                .inflate
+                .decompress_vec(input, &mut self.scratch, FlushDecompress::None)
+                .map_err(|_| DeserializeError::Decompression)?;
+
+            if self.scratch.len() > max_size {
+                return Err(DeserializeError::DecompressedTooLarge);
+            }
+
+            match status {
+                Status::StreamEnd => return Ok(&self.scratch),
+                // The stream ended early if nothing more could be done
+                _ if self.inflate.total_in() == total_in
+                    && self.scratch.len() == produced_before
+                    && self.scratch.len() < self.scratch.capacity() =>
+                {
+                    return Err(DeserializeError::Decompression);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for Decompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct AWPacketGroup {
     pub packets: Vec<AWPacket>,
+    /// Encoding of string variables, which affects how many packets fit
+    pub encoding: StringEncoding,
 }
 
 impl AWPacketGroup {
     pub fn new() -> Self {
+        Self::with_encoding(StringEncoding::default())
+    }
+
+    pub fn with_encoding(encoding: StringEncoding) -> Self {
         Self {
             packets: Vec::new(),
+            encoding,
         }
     }
+
     pub fn push(&mut self, packet: AWPacket) -> Result<usize, AWPacket> {
-        let packet_serialized_len = match packet.serialize_len() {
+        let packet_serialized_len = match packet.serialize_len_encoded(self.encoding) {
             Ok(len) => len,
             Err(_) => return Err(packet),
         };
@@ -369,10 +497,46 @@ impl AWPacketGroup {
         Ok(total_len)
     }
 
+    /// Decode the packets that were compressed together, which must all be
+    /// present in `data`, with strings in Latin-1.
+    pub fn deserialize(data: &[u8], limits: &DeserializeLimits) -> Result<Self, DeserializeError> {
+        Self::deserialize_encoded(data, limits, StringEncoding::default())
+    }
+
+    /// Decode the packets that were compressed together, which must all be
+    /// present in `data`, with strings in the given encoding.
+    pub fn deserialize_encoded(
+        mut data: &[u8],
+        limits: &DeserializeLimits,
+        encoding: StringEncoding,
+    ) -> Result<Self, DeserializeError> {
+        let mut group = Self::with_encoding(encoding);
+
+        while !data.is_empty() {
+            let serialized_len = AWPacket::deserialize_check(data).map_err(|why| match why {
+                DeserializeError::Length => DeserializeError::Truncated,
+                DeserializeError::Compressed(_) => DeserializeError::NestedCompression,
+                why => why,
+            })?;
+
+            let (packet, consumed) = AWPacket::deserialize_encoded(
+                data.get(..serialized_len)
+                    .ok_or(DeserializeError::Truncated)?,
+                limits,
+                encoding,
+            )?;
+
+            group.packets.push(packet);
+            data = data.get(consumed..).ok_or(DeserializeError::Truncated)?;
+        }
+
+        Ok(group)
+    }
+
     pub fn serialize_len(&self) -> Result<usize, String> {
         let mut total = 0usize;
         for p in &self.packets {
-            let packet_serialized_len = p.serialize_len()?;
+            let packet_serialized_len = p.serialize_len_encoded(self.encoding)?;
 
             total = match total.checked_add(packet_serialized_len) {
                 Some(len) => len,
@@ -429,28 +593,28 @@ impl TagHeader {
         result
     }
 
-    pub fn deserialize(data: &[u8]) -> Result<(Self, u64), String> {
+    pub fn deserialize(data: &[u8]) -> Result<(Self, usize), DeserializeError> {
         if data.len() < TagHeader::length() {
-            return Err("Not enough data to deserialize TagHeader.".to_string());
+            return Err(DeserializeError::Length);
         }
 
         let mut reader = Cursor::new(data);
 
         let serialized_length = reader
             .read_u16::<BigEndian>()
-            .map_err(|_| "Could not read serialized_length.")?;
+            .map_err(|_| DeserializeError::Length)?;
         let header_0 = reader
             .read_u16::<BigEndian>()
-            .map_err(|_| "Could not read header_0.")?;
+            .map_err(|_| DeserializeError::Length)?;
         let opcode = reader
             .read_i16::<BigEndian>()
-            .map_err(|_| "Could not read opcode.")?;
+            .map_err(|_| DeserializeError::Length)?;
         let header_1 = reader
             .read_u16::<BigEndian>()
-            .map_err(|_| "Could not read header_1.")?;
+            .map_err(|_| DeserializeError::Length)?;
         let var_count = reader
             .read_u16::<BigEndian>()
-            .map_err(|_| "Could not read var_count.")?;
+            .map_err(|_| DeserializeError::Length)?;
 
         Ok((
             Self {
@@ -460,7 +624,7 @@ impl TagHeader {
                 header_1,
                 var_count,
             },
-            reader.position(),
+            TagHeader::length(),
         ))
     }
 
@@ -480,11 +644,76 @@ impl TagHeader {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DeserializeError {
+    /// Not enough data yet; more may arrive later
     Length,
+    /// The header does not describe a valid packet
     InvalidHeader,
+    /// The packet is compressed and has this serialized length
     Compressed(usize),
+    /// A variable runs past the end of its packet
+    Truncated,
+    /// The variables do not add up to the length in the packet header
+    LengthMismatch { expected: usize, actual: usize },
+    /// A variable has a data type that does not exist
+    InvalidDataType(u16),
+    /// A packet has more variables than allowed
+    TooManyVars(u16),
+    /// A string variable is longer than allowed
+    StringTooLong(usize),
+    /// Compressed data is not valid zlib
+    Decompression,
+    /// Compressed data decompresses to more than allowed
+    DecompressedTooLarge,
+    /// Decompressed data contains another compressed packet
+    NestedCompression,
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length => write!(f, "Not enough data to deserialize"),
+            Self::InvalidHeader => write!(f, "Invalid packet header"),
+            Self::Compressed(len) => write!(f, "Packet of {len} bytes is compressed"),
+            Self::Truncated => write!(f, "Variable runs past the end of the packet"),
+            Self::LengthMismatch { expected, actual } => {
+                write!(f, "Consumed {actual} bytes instead of {expected}")
+            }
+            Self::InvalidDataType(data_type) => write!(f, "Invalid data type {data_type}"),
+            Self::TooManyVars(count) => write!(f, "Too many variables: {count}"),
+            Self::StringTooLong(len) => write!(f, "String variable too long: {len} bytes"),
+            Self::Decompression => write!(f, "Failed to decode compressed data"),
+            Self::DecompressedTooLarge => write!(f, "Compressed data decompresses too large"),
+            Self::NestedCompression => write!(f, "Compressed packet inside compressed data"),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+/// Limits on what a peer may send, checked while deserializing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializeLimits {
+    /// Largest size that one compressed packet may decompress to
+    pub max_decompressed_size: usize,
+    /// Most variables in one packet
+    pub max_var_count: u16,
+    /// Longest string variable in bytes, including its terminator. The
+    /// packet format itself allows up to 0xFFF bytes.
+    pub max_string_length: usize,
+}
+
+impl Default for DeserializeLimits {
+    fn default() -> Self {
+        Self {
+            max_decompressed_size: 0x10000,
+            max_var_count: 1024,
+            // Legitimate strings are at most a few hundred characters, which
+            // fits in this even when every character takes several bytes
+            max_string_length: 0x800,
+        }
+    }
 }
 
 #[derive(FromPrimitive, Clone, Copy, Debug, PartialEq)]
@@ -661,6 +890,15 @@ impl From<PacketTypeResult> for i16 {
     }
 }
 
+impl From<i16> for PacketTypeResult {
+    fn from(value: i16) -> Self {
+        match PacketType::from_i16(value) {
+            Some(packet_type) => PacketTypeResult::PacketType(packet_type),
+            None => PacketTypeResult::Unknown(value),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -674,4 +912,107 @@ mod tests {
         let (deserialized, _) = AWPacket::deserialize(&serialized).unwrap();
         assert!(packet == deserialized);
     }
+
+    #[test]
+    pub fn test_serialize_encoded() {
+        let mut packet = AWPacket::new(PacketType::Address);
+        packet.add_string(1u16, "Zoë ☃".to_string());
+
+        let serialized = packet.serialize_encoded(StringEncoding::Utf8).unwrap();
+        assert_eq!(
+            packet.serialize_len_encoded(StringEncoding::Utf8),
+            Ok(serialized.len())
+        );
+        let limits = DeserializeLimits::default();
+        let (deserialized, _) =
+            AWPacket::deserialize_encoded(&serialized, &limits, StringEncoding::Utf8).unwrap();
+        assert_eq!(packet, deserialized);
+
+        // Read as Latin-1 by mistake, then fixed up
+        let (mut misread, _) = AWPacket::deserialize(&serialized).unwrap();
+        assert_ne!(packet, misread);
+        misread.transcode_strings(StringEncoding::Latin1, StringEncoding::Utf8);
+        assert_eq!(packet, misread);
+
+        // Characters outside Latin-1 are replaced rather than mangled
+        let (latin1, _) = AWPacket::deserialize(&packet.serialize().unwrap()).unwrap();
+        assert_eq!(latin1.get_string(1u16).as_deref(), Some("Zoë ?"));
+    }
+
+    #[test]
+    pub fn test_deserialize_limits() {
+        let mut packet = AWPacket::new(PacketType::Address);
+        packet.add_string(1u16, "Hello".to_string());
+        packet.add_byte(2u16, 1);
+        let serialized = packet.serialize().unwrap();
+
+        let limits = DeserializeLimits {
+            max_var_count: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            AWPacket::deserialize_with_limits(&serialized, &limits),
+            Err(DeserializeError::TooManyVars(2))
+        );
+
+        let limits = DeserializeLimits {
+            max_string_length: 5,
+            ..Default::default()
+        };
+        assert_eq!(
+            AWPacket::deserialize_with_limits(&serialized, &limits),
+            Err(DeserializeError::StringTooLong(6))
+        );
+
+        let mut packet = AWPacket::new(PacketType::Address);
+        packet.add_string(1u16, "x".repeat(0x800));
+        assert_eq!(
+            AWPacket::deserialize(&packet.serialize().unwrap()),
+            Err(DeserializeError::StringTooLong(0x801))
+        );
+
+        // Claim one variable more than there is room for
+        let mut truncated = serialized.clone();
+        truncated[9] = 3;
+        assert_eq!(
+            AWPacket::deserialize(&truncated),
+            Err(DeserializeError::Truncated)
+        );
+    }
+
+    #[test]
+    pub fn test_compressed_group() {
+        let mut group = AWPacketGroup::new();
+        for i in 0..20 {
+            let mut packet = AWPacket::new(PacketType::Address);
+            packet.add_string(1u16, format!("Packet number {i}"));
+            group.push(packet).unwrap();
+        }
+
+        let mut serialized = Vec::new();
+        for packet in &group.packets {
+            serialized.extend(packet.serialize().unwrap());
+        }
+        let compressed = AWPacket::compress_if_needed(&serialized).unwrap();
+        assert_eq!(
+            AWPacket::deserialize_check(&compressed),
+            Err(DeserializeError::Compressed(compressed.len()))
+        );
+
+        let decompressed = AWPacket::decompress(&compressed).unwrap();
+        let limits = DeserializeLimits::default();
+        assert_eq!(
+            AWPacketGroup::deserialize(&decompressed, &limits),
+            Ok(group)
+        );
+
+        assert_eq!(
+            AWPacket::decompress_with_limit(&compressed, decompressed.len() - 1),
+            Err(DeserializeError::DecompressedTooLarge)
+        );
+        assert_eq!(
+            AWPacketGroup::deserialize(&compressed, &limits),
+            Err(DeserializeError::NestedCompression)
+        );
+    }
 }
@@ -1,6 +1,7 @@
 //! Packet variable (de)serialization for AW
 
-use crate::encoding::{latin1_to_string, string_to_latin1};
+use crate::encoding::StringEncoding;
+use crate::net::packet::{DeserializeError, DeserializeLimits};
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
@@ -42,13 +43,13 @@ impl PacketData {
         }
     }
 
-    fn get_data_size(&self) -> Option<usize> {
+    fn get_data_size(&self, encoding: StringEncoding) -> Option<usize> {
         Some(match self {
             Self::Byte(_) => 1,
             Self::Int(_) => 4,
             Self::Uint(_) => 4,
             Self::Float(_) => 4,
-            Self::String(string) => string_to_latin1(string).len().checked_add(1)?,
+            Self::String(string) => encoding.encoded_len(string).checked_add(1)?,
             Self::Data(buf) => buf.len(),
             Self::Unknown(buf) => buf.len(),
         })
@@ -249,6 +250,11 @@ pub enum VarID {
     CitizenPrivacy = 301,
     TrialUser = 302,
     MyZone = 349,
+
+    // Not part of the original protocol
+    /// Challenge a world server must decrypt with the private half of the key
+    /// registered for a world before it may start the world
+    WorldServerChallenge = 1000,
 }
 
 impl From<VarID> for u16 {
@@ -301,17 +307,23 @@ impl AWPacketVar {
         self.data.get_data_type()
     }
 
-    fn get_data_size(&self) -> Option<usize> {
-        self.data.get_data_size()
+    fn get_data_size(&self, encoding: StringEncoding) -> Option<usize> {
+        self.data.get_data_size(encoding)
     }
 
+    /// Encode the variable with strings in Latin-1.
     pub fn serialize(&self) -> Result<Vec<u8>, String> {
+        self.serialize_encoded(StringEncoding::default())
+    }
+
+    /// Encode the variable with strings in the given encoding.
+    pub fn serialize_encoded(&self, encoding: StringEncoding) -> Result<Vec<u8>, String> {
         let mut result = Vec::<u8>::with_capacity(16);
 
         let var_id = self.get_var_id();
 
         let size = self
-            .get_data_size()
+            .get_data_size(encoding)
             .ok_or("Data size invalid".to_string())?;
 
         if size > 0xFFF {
@@ -344,7 +356,7 @@ impl AWPacketVar {
                 result.write_f32::<LittleEndian>(*x).unwrap();
             }
             PacketData::String(x) => {
-                result.write_all(&string_to_latin1(x)).unwrap();
+                result.write_all(&encoding.encode(x)).unwrap();
                 result.write_all(&[0u8]).unwrap();
             }
             PacketData::Data(x) => {
@@ -358,74 +370,85 @@ impl AWPacketVar {
         Ok(result)
     }
 
-    pub fn deserialize(data: &[u8]) -> Result<(Self, u64), String> {
+    /// Decode a variable with the default [`DeserializeLimits`], returning it and
+    /// the number of bytes it took up.
+    pub fn deserialize(data: &[u8]) -> Result<(Self, u64), DeserializeError> {
+        Self::deserialize_with_limits(data, &DeserializeLimits::default())
+    }
+
+    /// Decode a variable with strings in Latin-1, returning it and the number
+    /// of bytes it took up.
+    pub fn deserialize_with_limits(
+        data: &[u8],
+        limits: &DeserializeLimits,
+    ) -> Result<(Self, u64), DeserializeError> {
+        Self::deserialize_encoded(data, limits, StringEncoding::default())
+    }
+
+    /// Decode a variable with strings in the given encoding, returning it and
+    /// the number of bytes it took up.
+    pub fn deserialize_encoded(
+        data: &[u8],
+        limits: &DeserializeLimits,
+        encoding: StringEncoding,
+    ) -> Result<(Self, u64), DeserializeError> {
         let mut reader = Cursor::new(data);
 
         // Header is big endian
         let var_id_num = reader
             .read_u16::<BigEndian>()
-            .map_err(|_| "Could not deserialize var_id_num")?;
+            .map_err(|_| DeserializeError::Length)?;
         let data_type_and_size = reader
             .read_u16::<BigEndian>()
-            .map_err(|_| "Could not deserialize data_type_and_size")?;
+            .map_err(|_| DeserializeError::Length)?;
 
         // Extract size and data type from packed value
-        let size = data_type_and_size & 0xFFF;
+        let size = usize::from(data_type_and_size & 0xFFF);
         let data_type_num = (data_type_and_size & 0xF000) >> 12;
 
         let data_type: DataType = DataType::from_u16(data_type_num)
-            .ok_or_else(|| format!("Received invalid data type {data_type_num}"))?;
+            .ok_or(DeserializeError::InvalidDataType(data_type_num))?;
 
         // Little endian
         let result = match data_type {
             DataType::Byte => {
-                let x = reader
-                    .read_u8()
-                    .map_err(|_| "Could not deserialize Byte data")?;
+                let x = reader.read_u8().map_err(|_| DeserializeError::Length)?;
                 Self::byte(var_id_num, x)
             }
             DataType::Int => {
                 let x = reader
                     .read_i32::<LittleEndian>()
-                    .map_err(|_| "Could not deserialize Int data")?;
+                    .map_err(|_| DeserializeError::Length)?;
                 Self::int(var_id_num, x)
             }
             DataType::Float => {
                 let x = reader
                     .read_f32::<LittleEndian>()
-                    .map_err(|_| "Could not deserialize Float data")?;
+                    .map_err(|_| DeserializeError::Length)?;
                 Self::float(var_id_num, x)
             }
             DataType::String => {
-                let mut buf = vec![0u8; size as usize];
-                reader
-                    .read_exact(&mut buf)
-                    .map_err(|_| "Could not deserialize String data")?;
-                Self::string(var_id_num, latin1_to_string(&buf))
-            }
-            DataType::Data => {
-                let mut buf = vec![0u8; size as usize];
-                reader
-                    .read_exact(&mut buf)
-                    .map_err(|_| "Could not deserialize Data data")?;
-                Self::data(var_id_num, buf)
-            }
-            DataType::Unknown => {
-                let mut buf = vec![0u8; size as usize];
-                reader
-                    .read_exact(&mut buf)
-                    .map_err(|_| "Could not deserialize Unknown data")?;
-                AWPacketVar::unknown(var_id_num, buf)
+                if size > limits.max_string_length {
+                    return Err(DeserializeError::StringTooLong(size));
+                }
+                let buf = read_buf(&mut reader, size)?;
+                Self::string(var_id_num, encoding.decode(&buf))
             }
+            DataType::Data => Self::data(var_id_num, read_buf(&mut reader, size)?),
+            DataType::Unknown => AWPacketVar::unknown(var_id_num, read_buf(&mut reader, size)?),
         };
 
         Ok((result, reader.position()))
     }
 
     pub fn serialize_len(&self) -> Option<usize> {
+        self.serialize_len_encoded(StringEncoding::default())
+    }
+
+    pub fn serialize_len_encoded(&self, encoding: StringEncoding) -> Option<usize> {
         let var_id_size: usize = 2;
         let data_type_and_size_size: usize = 2;
-        let data_size: usize = self.get_data_size()?;
+        let data_size: usize = self.get_data_size(encoding)?;
 
         var_id_size
             .checked_add(data_type_and_size_size)?
@@ -433,6 +456,14 @@ impl AWPacketVar {
     }
 }
 
+fn read_buf(reader: &mut Cursor<&[u8]>, size: usize) -> Result<Vec<u8>, DeserializeError> {
+    let mut buf = vec![0u8; size];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| DeserializeError::Length)?;
+    Ok(buf)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,10 +1,20 @@
 //! Networking protocol implementation
-use crate::net::packet::{AWPacket, DeserializeError, PacketType};
+use crate::encoding::StringEncoding;
+use crate::net::connection::SendQueue;
+use crate::net::key_log::KeyLog;
+use crate::net::packet::{
+    AWPacket, AWPacketGroup, Decompressor, DeserializeError, DeserializeLimits, PacketType,
+};
+use crate::net::schema::Direction;
+use crate::net::stats::TrafficMeter;
 use crate::{AWCryptStream, StreamCipherError};
 use crate::{PacketTypeResult, ReasonCode};
+use bytes::{Buf, BytesMut};
+use std::collections::VecDeque;
 use std::io::{self, Read, Write};
 use std::net::{SocketAddr, TcpStream};
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
@@ -13,10 +23,16 @@ type StreamCipherType = crate::AWCryptAES;
 #[cfg(feature = "stream_cipher_rc4")]
 type StreamCipherType = crate::AWCryptA4;
 
+/// Most bytes read from the socket at once.
+const RECV_CHUNK_SIZE: usize = 0x8000;
+
 /// State of an instance of the AW protocol.
 pub struct AWProtocol {
     stream: TcpStream,
-    data: Vec<u8>,
+    data: BytesMut,
+    /// Where bytes are read from the socket before being added to `data`,
+    /// allocated once so that reads do not have to clear new memory
+    read_buffer: Box<[u8]>,
     send_cipher: StreamCipherType,
     should_encrypt: bool,
     recv_cipher: Option<StreamCipherType>,
@@ -26,6 +42,21 @@ pub struct AWProtocol {
     other_inbound_packets: Option<Receiver<ProtocolMessage>>,
     other_outbound_packets: Option<Sender<ProtocolMessage>>,
     last_packet_type: Option<PacketType>,
+    /// Direction of received packets, when checking packets against the schema
+    schema_check: Option<Direction>,
+    key_log: Option<KeyLog>,
+    limits: DeserializeLimits,
+    /// Packets that arrived compressed together and have not been handled yet
+    pending: VecDeque<AWPacket>,
+    decompressor: Decompressor,
+    send_queue: Arc<SendQueue>,
+    traffic: Arc<TrafficMeter>,
+    /// Encoding of string variables in both directions
+    encoding: StringEncoding,
+    /// Type of packet after which the encoding is decided again
+    encoding_chosen_by: Option<PacketType>,
+    /// Set while received packets are held back until the encoding is decided
+    awaiting_encoding: bool,
 }
 
 impl AWProtocol {
@@ -36,12 +67,23 @@ impl AWProtocol {
 
         Ok(Self {
             stream,
-            data: Vec::new(),
+            data: BytesMut::new(),
+            read_buffer: vec![0u8; RECV_CHUNK_SIZE].into_boxed_slice(),
             send_cipher: StreamCipherType::new()?,
             should_encrypt: false,
             recv_cipher: None,
             dead: false,
             last_packet_type: None,
+            schema_check: None,
+            key_log: None,
+            limits: DeserializeLimits::default(),
+            pending: VecDeque::new(),
+            decompressor: Decompressor::new(),
+            send_queue: Arc::new(SendQueue::default()),
+            traffic: Arc::new(TrafficMeter::default()),
+            encoding: StringEncoding::default(),
+            encoding_chosen_by: None,
+            awaiting_encoding: false,
             inbound_packets: inbound_packets_tx,
             outbound_packets: outbound_packets_rx,
             other_inbound_packets: Some(inbound_packets_rx),
@@ -54,9 +96,25 @@ impl AWProtocol {
         self.stream.peer_addr()
     }
 
+    /// Duplicate the handle to the underlying TCP stream.
+    pub fn try_clone_stream(&self) -> io::Result<TcpStream> {
+        self.stream.try_clone()
+    }
+
+    /// Counts of packets queued on the outbound channel but not yet sent.
+    pub fn send_queue(&self) -> Arc<SendQueue> {
+        self.send_queue.clone()
+    }
+
+    /// Counters of the traffic sent and received on this connection.
+    pub fn traffic(&self) -> Arc<TrafficMeter> {
+        self.traffic.clone()
+    }
+
     /// Set the key to receive data (i.e. the key the other end of the connection is using).
     pub fn set_recv_key(&mut self, key: &[u8]) -> Result<(), StreamCipherError> {
         self.recv_cipher = Some(StreamCipherType::from_key(key)?);
+        self.log_recv_key(key);
         Ok(())
     }
 
@@ -70,21 +128,90 @@ impl AWProtocol {
         self.should_encrypt = should;
     }
 
+    /// How string variables are encoded in both directions.
+    pub fn encoding(&self) -> StringEncoding {
+        self.encoding
+    }
+
+    /// Set how string variables are encoded in both directions.
+    pub fn set_encoding(&mut self, encoding: StringEncoding) {
+        // Packets that arrived compressed together were all decoded already
+        if encoding != self.encoding {
+            for packet in &mut self.pending {
+                packet.transcode_strings(self.encoding, encoding);
+            }
+        }
+        self.encoding = encoding;
+        self.awaiting_encoding = false;
+    }
+
+    /// Stop reading after receiving a packet of this type until the encoding
+    /// has been set again, even if it stays the same, so that later packets
+    /// are decoded with the encoding that packet asked for.
+    pub fn wait_for_encoding_after(&mut self, packet_type: PacketType) {
+        self.encoding_chosen_by = Some(packet_type);
+    }
+
+    /// Log a warning for every sent or received packet that does not match the
+    /// protocol schema. `inbound` is the direction of packets received on this
+    /// connection, e.g. `ClientToServer` for a server.
+    pub fn warn_on_schema_violations(&mut self, inbound: Direction) {
+        self.schema_check = Some(inbound);
+    }
+
+    /// Set the limits on what the peer may send. A peer that exceeds them is
+    /// disconnected.
+    pub fn set_limits(&mut self, limits: DeserializeLimits) {
+        self.limits = limits;
+    }
+
+    /// Write the stream keys of this connection to `key_log`, so that captured
+    /// traffic can be decrypted. The send key is written immediately and the
+    /// receive key once it is known.
+    pub fn set_key_log(&mut self, key_log: KeyLog) {
+        if let (Ok(local), Ok(peer)) = (self.stream.local_addr(), self.stream.peer_addr()) {
+            key_log.log_key(local, peer, &self.get_send_key());
+        }
+        self.key_log = Some(key_log);
+    }
+
+    fn log_recv_key(&self, key: &[u8]) {
+        let Some(key_log) = &self.key_log else {
+            return;
+        };
+
+        if let (Ok(local), Ok(peer)) = (self.stream.local_addr(), self.stream.peer_addr()) {
+            key_log.log_key(peer, local, key);
+        }
+    }
+
+    fn check_schema(&self, packet: &AWPacket, direction: Direction) {
+        if let Err(violations) = packet.validate(direction) {
+            let violations = violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let peer = self.stream.peer_addr().ok();
+            log::warn!(
+                "{direction:?} {:?} packet does not match the schema ({violations}); peer {peer:?}",
+                packet.get_type()
+            );
+        }
+    }
+
     /// Remove n oldest bytes from the recv buffer.
     pub fn remove_from_buf(&mut self, n: usize) {
-        self.data = match self.data.get(n..) {
-            // There are bytes remaining; keep them
-            Some(slice) => slice.to_vec(),
-            // Nothing left, make a new empty buffer
-            None => Vec::<u8>::new(),
-        };
+        // The space is reused by later reads rather than freed
+        self.data.advance(n.min(self.data.len()));
     }
 
     /// Add bytes to the front of the recv buffer.
     pub fn insert_into_buf(&mut self, data: &[u8]) {
-        let mut data2 = data.to_vec();
-        std::mem::swap(&mut self.data, &mut data2);
-        self.data.extend(data2);
+        let mut buf = BytesMut::with_capacity(data.len() + self.data.len());
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(&self.data);
+        self.data = buf;
     }
 
     /// Send packets.
@@ -108,10 +235,21 @@ impl AWProtocol {
             }
         }
 
+        if let Some(inbound) = self.schema_check {
+            for packet in packets.iter() {
+                self.check_schema(packet, inbound.reverse());
+            }
+        }
+
         // Serialize one or more packets
         let mut serialized_bytes = Vec::<u8>::new();
+        let mut serialized_lens = Vec::<(&AWPacket, usize)>::with_capacity(packets.len());
         for packet in packets.iter() {
-            serialized_bytes.extend(packet.serialize().map_err(|_| ReasonCode::SendFailed)?);
+            let serialized = packet
+                .serialize_encoded(self.encoding)
+                .map_err(|_| ReasonCode::SendFailed)?;
+            serialized_lens.push((packet, serialized.len()));
+            serialized_bytes.extend(serialized);
         }
 
         // Try to compress the serialized packet
@@ -120,6 +258,7 @@ impl AWProtocol {
         } else {
             serialized_bytes
         };
+        let compressed_len = bytes_to_send.len();
 
         // If the other end of the connection has been given our encryption key, we need to encrypt.
         if self.should_encrypt {
@@ -134,51 +273,60 @@ impl AWProtocol {
             .write_all(&bytes_to_send)
             .map_err(|_| ReasonCode::SendFailed)?;
 
+        self.traffic
+            .record_sent(&serialized_lens, compressed_len, bytes_to_send.len());
+
         Ok(())
     }
 
     /// Receive incoming bytes, return success
     pub fn recv(&mut self) -> Result<usize, String> {
-        let mut buf = [0u8; 0x8000];
-        // let mut buf = [0u8; 0x1]; // Can use this to stress-test recv
-
-        let Ok(bytes_read) = self.stream.read(&mut buf) else {
-            return Err("Could not receive bytes.".to_string());
+        let bytes_read = match self.stream.read(&mut self.read_buffer) {
+            Ok(bytes_read) => bytes_read,
+            Err(_) => return Err("Could not receive bytes.".to_string()),
         };
 
         if bytes_read == 0 {
             return Err("Connection closed.".to_string());
         }
+        self.traffic.record_received_wire(bytes_read);
 
-        let Some(read_buffer) = buf.get_mut(..bytes_read) else {
+        let Some(received) = self.read_buffer.get_mut(..bytes_read) else {
             return Err(format!("Received a nonsense number of bytes: {bytes_read}"));
         };
 
         // Decrypt incoming bytes if we have a key.
         if let Some(cipher) = &mut self.recv_cipher {
-            if let Err(why) = cipher.decrypt_in_place(read_buffer) {
+            if let Err(why) = cipher.decrypt_in_place(received) {
                 self.kill();
                 return Err(format!("Failed to decrypt_in_place: {why:?}"));
             }
         }
 
-        self.data.extend(read_buffer.iter());
+        self.data.extend_from_slice(received);
 
         Ok(bytes_read)
     }
 
     fn decompress_packet(&mut self, serialized_len: usize) -> Result<(), String> {
-        // Decompress it and replace the front of the recv buf with the decompressed packet.
+        // Decompress it and queue the packets it contained.
         let Some(compressed_data) = self.data.get(..serialized_len) else {
             return Err(format!(
                 "Could not decompress packet because the length is invalid: {serialized_len}"
             ));
         };
 
-        let decompressed = AWPacket::decompress(compressed_data)?;
+        let group = self
+            .decompressor
+            .decompress(compressed_data, self.limits.max_decompressed_size)
+            .and_then(|decompressed| {
+                AWPacketGroup::deserialize_encoded(decompressed, &self.limits, self.encoding)
+            })
+            .map_err(|why| self.malformed(why))?;
 
+        self.traffic.record_received_compressed(serialized_len);
         self.remove_from_buf(serialized_len);
-        self.insert_into_buf(&decompressed);
+        self.pending.extend(group.packets);
         Ok(())
     }
 
@@ -189,35 +337,43 @@ impl AWProtocol {
             ));
         };
 
-        match AWPacket::deserialize(data) {
-            Ok((packet, consumed_bytes)) => {
-                // Successfully deserialized a packet, now remove the data from the recv buf.
-                self.remove_from_buf(consumed_bytes);
-                return Ok(Some(packet));
-            }
-            Err(why) => {
-                // Failed to deserialize packet
-                log::debug!("Failed to deserialize packet: {why:?}");
-                self.recv()?;
-            }
-        }
-        Ok(None)
+        let (packet, consumed_bytes) =
+            AWPacket::deserialize_encoded(data, &self.limits, self.encoding)
+                .map_err(|why| self.malformed(why))?;
+
+        // Successfully deserialized a packet, now remove the data from the recv buf.
+        self.traffic.record_received_compressed(consumed_bytes);
+        self.remove_from_buf(consumed_bytes);
+        Ok(Some(packet))
+    }
+
+    /// Describe why the peer's data could not be used, which ends the connection.
+    fn malformed(&self, why: DeserializeError) -> String {
+        let peer = self.stream.peer_addr().ok();
+        log::warn!("Disconnecting {peer:?} for sending a malformed packet: {why}");
+        why.to_string()
     }
 
     fn check_and_deserialize_packet(&mut self) -> Result<Option<AWPacket>, String> {
+        if let Some(packet) = self.pending.pop_front() {
+            return Ok(Some(packet));
+        }
+
         match AWPacket::deserialize_check(&self.data) {
             // Received a packet that appears well formed, attempt to deserialize
             Ok(serialized_len) => {
                 return self.deserialize_packet(serialized_len);
             }
             Err(err) => match err {
-                DeserializeError::Length | DeserializeError::InvalidHeader => {
+                // Wait for the rest of the packet
+                DeserializeError::Length => {
                     self.recv()?;
                 }
                 // Received a packet that is still compressed.
                 DeserializeError::Compressed(serialized_len) => {
                     self.decompress_packet(serialized_len)?;
                 }
+                why => return Err(self.malformed(why)),
             },
         }
         Ok(None)
@@ -239,8 +395,8 @@ impl AWProtocol {
 
     /// Returns whether there is anything to handle on a connection, including whether there has been an error.
     pub fn needs_action(&mut self) -> bool {
-        // If we already have bytes, they need to be handled
-        if !self.data.is_empty() {
+        // If we already have packets or bytes, they need to be handled
+        if !self.pending.is_empty() || !self.data.is_empty() {
             return true;
         }
 
@@ -273,6 +429,12 @@ impl AWProtocol {
                 }
             }
 
+            // Likewise for the encoding of the packets that come next.
+            while self.awaiting_encoding && !self.dead {
+                self.handle_messages();
+                thread::sleep(Duration::from_millis(1));
+            }
+
             self.handle_messages();
             thread::sleep(Duration::from_millis(1));
         }
@@ -288,39 +450,92 @@ impl AWProtocol {
         }
     }
 
+    /// Handle every message waiting on the outbound channel. Packets sent one
+    /// at a time are coalesced into groups, which are compressed together.
     fn handle_messages(&mut self) {
-        let message = match self.outbound_packets.try_recv() {
-            Ok(message) => message,
-            Err(_) => return,
-        };
-
-        match message {
-            ProtocolMessage::Packet(packet) => {
-                self.send_or_kill(&mut [packet], true);
-            }
-            ProtocolMessage::PacketGroup(mut packets) => {
-                self.send_or_kill(&mut packets, true);
-            }
-            ProtocolMessage::StreamKey(key) => {
-                match StreamCipherType::from_key(&key) {
-                    Ok(mut stream_cipher) => {
-                        // There may be data that has already been sent, so we need to decrypt it now.
-                        if let Err(why) = stream_cipher.decrypt_in_place(&mut self.data) {
-                            log::error!("Failed to decrypt_in_place: {why:?}");
-                            self.kill();
+        let mut group = AWPacketGroup::with_encoding(self.encoding);
+
+        while let Ok(message) = self.outbound_packets.try_recv() {
+            match message {
+                ProtocolMessage::Packet(packet) => {
+                    self.send_queue.pop(std::slice::from_ref(&packet));
+
+                    if !Self::can_coalesce(&packet) {
+                        self.flush_group(&mut group);
+                        self.send_or_kill(&mut [packet], true);
+                    } else if let Err(packet) = group.push(packet) {
+                        self.flush_group(&mut group);
+                        if let Err(packet) = group.push(packet) {
+                            self.send_or_kill(&mut [packet], true);
                         }
-
-                        self.recv_cipher = Some(stream_cipher);
                     }
-                    Err(_) => self.kill(),
+                }
+                ProtocolMessage::PacketGroup(mut packets) => {
+                    self.send_queue.pop(&packets);
+                    self.flush_group(&mut group);
+                    self.send_or_kill(&mut packets, true);
+                }
+                // The rest may change how later packets are sent, so
+                // everything before them goes out first.
+                ProtocolMessage::StreamKey(key) => {
+                    self.flush_group(&mut group);
+                    self.set_stream_key(&key);
+                }
+                ProtocolMessage::Encrypt(should) => {
+                    self.flush_group(&mut group);
+                    self.encrypt_data(should);
+                }
+                ProtocolMessage::Encoding(encoding) => {
+                    self.flush_group(&mut group);
+                    self.set_encoding(encoding);
+                    group.encoding = encoding;
+                }
+                ProtocolMessage::Disconnect => {
+                    self.flush_group(&mut group);
+                    self.kill();
                 }
             }
-            ProtocolMessage::Encrypt(should) => {
-                self.encrypt_data(should);
+
+            if self.dead {
+                return;
             }
-            ProtocolMessage::Disconnect => {
-                self.kill();
+        }
+
+        self.flush_group(&mut group);
+    }
+
+    /// Whether a packet may be sent in a group with others. Key exchange
+    /// packets go alone, since encryption starts right after them.
+    fn can_coalesce(packet: &AWPacket) -> bool {
+        !matches!(
+            packet.get_type(),
+            PacketTypeResult::PacketType(
+                PacketType::PublicKeyResponse | PacketType::StreamKeyResponse
+            )
+        )
+    }
+
+    fn flush_group(&mut self, group: &mut AWPacketGroup) {
+        if !group.packets.is_empty() {
+            let mut packets = std::mem::take(&mut group.packets);
+            self.send_or_kill(&mut packets, true);
+        }
+    }
+
+    /// Start decrypting received data with the peer's stream key.
+    fn set_stream_key(&mut self, key: &[u8]) {
+        match StreamCipherType::from_key(key) {
+            Ok(mut stream_cipher) => {
+                // There may be data that has already been sent, so we need to decrypt it now.
+                if let Err(why) = stream_cipher.decrypt_in_place(&mut self.data) {
+                    log::error!("Failed to decrypt_in_place: {why:?}");
+                    self.kill();
+                }
+
+                self.recv_cipher = Some(stream_cipher);
+                self.log_recv_key(key);
             }
+            Err(_) => self.kill(),
         }
     }
 
@@ -346,8 +561,22 @@ impl AWProtocol {
 
         if let PacketTypeResult::PacketType(packet_type) = packet.get_type() {
             self.last_packet_type = Some(packet_type);
+            if self.encoding_chosen_by == Some(packet_type) {
+                self.awaiting_encoding = true;
+            }
         }
 
+        if let Some(inbound) = self.schema_check {
+            self.check_schema(&packet, inbound);
+        }
+
+        self.traffic.record_received(
+            &packet,
+            packet
+                .serialize_len_encoded(self.encoding)
+                .unwrap_or_default(),
+        );
+
         let send_result = self.inbound_packets.send(ProtocolMessage::Packet(packet));
 
         if send_result.is_err() {
@@ -380,6 +609,7 @@ pub enum ProtocolMessage {
     Disconnect,
     StreamKey(Vec<u8>),
     Encrypt(bool),
+    Encoding(StringEncoding),
 }
 
 #[cfg(test)]
@@ -433,4 +663,71 @@ mod tests {
         // The deserialized packet should be the same as the packet originally sent.
         assert!(packet == packet_2);
     }
+
+    #[test]
+    fn coalesces_packets_into_one_frame() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sender =
+            AWProtocol::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap()).unwrap();
+        let mut receiver = AWProtocol::new(listener.accept().unwrap().0).unwrap();
+
+        let packets = (0..3)
+            .map(|i| {
+                let mut packet = AWPacket::new(PacketType::AvatarAdd);
+                packet.add_var(AWPacketVar::int(1u16, i));
+                packet.add_var(AWPacketVar::string(2u16, "Hello, World! ".repeat(8)));
+                packet
+            })
+            .collect::<Vec<_>>();
+
+        let outbound = sender.other_outbound_packets.take().unwrap();
+        sender.send_queue.push(&packets);
+        for packet in &packets {
+            outbound
+                .send(ProtocolMessage::Packet(packet.clone()))
+                .unwrap();
+        }
+        sender.handle_messages();
+
+        assert_eq!(sender.send_queue.packets(), 0);
+        assert_eq!(sender.send_queue.bytes(), 0);
+        let sent = sender.traffic.snapshot().sent;
+        assert_eq!(sent.packets.packets, 3);
+        assert!(sent.compressed_bytes < sent.packets.bytes);
+
+        // The other two packets came in the same compressed frame as the first
+        assert_eq!(receiver.recv_next_packet().as_ref(), Some(&packets[0]));
+        assert_eq!(receiver.pending.len(), 2);
+        assert_eq!(receiver.recv_next_packet().as_ref(), Some(&packets[1]));
+        assert_eq!(receiver.recv_next_packet().as_ref(), Some(&packets[2]));
+    }
+
+    #[test]
+    fn packets_after_login_use_the_new_encoding() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sender =
+            AWProtocol::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap()).unwrap();
+        let mut receiver = AWProtocol::new(listener.accept().unwrap().0).unwrap();
+        receiver.wait_for_encoding_after(PacketType::Login);
+        let inbound = receiver.other_inbound_packets.take().unwrap();
+
+        let text = "Zoë ".repeat(50);
+        let login = AWPacket::new(PacketType::Login);
+        let mut address = AWPacket::new(PacketType::Address);
+        address.add_var(AWPacketVar::string(1u16, text.clone()));
+        sender.set_encoding(StringEncoding::Utf8);
+        sender.send(&mut [login, address], true).unwrap();
+
+        receiver.handle_inbound_packets();
+        assert!(receiver.awaiting_encoding);
+        assert!(matches!(inbound.try_recv(), Ok(ProtocolMessage::Packet(_))));
+
+        receiver.set_encoding(StringEncoding::Utf8);
+        assert!(!receiver.awaiting_encoding);
+        receiver.handle_inbound_packets();
+        match inbound.try_recv() {
+            Ok(ProtocolMessage::Packet(packet)) => assert_eq!(packet.get_string(1u16), Some(text)),
+            _ => panic!("Expected the address packet"),
+        }
+    }
 }
@@ -0,0 +1,586 @@
+//! Which variables each packet is expected to carry.
+//!
+//! The table is built from what the universe and SDK read and write, so it
+//! only describes packets whose contents are understood. Packets without an
+//! entry are not checked.
+
+use std::fmt;
+
+use num_traits::FromPrimitive;
+
+use crate::{AWPacket, PacketData, PacketType, PacketTypeResult, VarID};
+use Direction::{ClientToServer, ServerToClient};
+use VarType::{Byte, Data, Int, String as Str};
+
+/// Which end of a connection sends a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Sent by a browser, bot or world server to the universe or a world
+    ClientToServer,
+    /// Sent by the universe or a world to its clients
+    ServerToClient,
+}
+
+impl Direction {
+    pub fn reverse(self) -> Self {
+        match self {
+            Self::ClientToServer => Self::ServerToClient,
+            Self::ServerToClient => Self::ClientToServer,
+        }
+    }
+}
+
+/// Wire type of a packet variable. Signed and unsigned integers share `Int`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarType {
+    Byte,
+    Int,
+    Float,
+    String,
+    Data,
+}
+
+impl VarType {
+    /// The type of a variable's data, or `None` for data of an unknown type.
+    pub fn of(data: &PacketData) -> Option<Self> {
+        match data {
+            PacketData::Byte(_) => Some(Self::Byte),
+            PacketData::Int(_) | PacketData::Uint(_) => Some(Self::Int),
+            PacketData::Float(_) => Some(Self::Float),
+            PacketData::String(_) => Some(Self::String),
+            PacketData::Data(_) => Some(Self::Data),
+            PacketData::Unknown(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VarSchema {
+    pub var: VarID,
+    pub var_type: VarType,
+    pub required: bool,
+}
+
+const fn req(var: VarID, var_type: VarType) -> VarSchema {
+    VarSchema {
+        var,
+        var_type,
+        required: true,
+    }
+}
+
+const fn opt(var: VarID, var_type: VarType) -> VarSchema {
+    VarSchema {
+        var,
+        var_type,
+        required: false,
+    }
+}
+
+#[derive(Debug)]
+pub struct PacketSchema {
+    pub packet_type: PacketType,
+    pub direction: Direction,
+    pub vars: &'static [VarSchema],
+}
+
+impl PacketSchema {
+    pub fn var(&self, var_id: u16) -> Option<&VarSchema> {
+        self.vars.iter().find(|v| u16::from(v.var) == var_id)
+    }
+}
+
+/// A way in which a packet does not match its schema.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaViolation {
+    Missing(VarID),
+    WrongType {
+        var: VarID,
+        expected: VarType,
+        actual: Option<VarType>,
+    },
+    /// A variable the schema does not list, by its raw ID
+    Unexpected(u16),
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(var) => write!(f, "missing {var:?}"),
+            Self::WrongType {
+                var,
+                expected,
+                actual: Some(actual),
+            } => write!(f, "{var:?} is {actual:?}, expected {expected:?}"),
+            Self::WrongType {
+                var,
+                expected,
+                actual: None,
+            } => write!(f, "{var:?} has an unknown type, expected {expected:?}"),
+            Self::Unexpected(id) => match VarID::from_u16(*id) {
+                Some(var) => write!(f, "unexpected {var:?}"),
+                None => write!(f, "unexpected variable {id}"),
+            },
+        }
+    }
+}
+
+/// The schema of a packet type sent in the given direction, if it is known.
+pub fn packet_schema(
+    packet_type: PacketType,
+    direction: Direction,
+) -> Option<&'static PacketSchema> {
+    PROTOCOL_SCHEMA
+        .iter()
+        .find(|s| s.packet_type == packet_type && s.direction == direction)
+}
+
+impl AWPacket {
+    /// Check the packet against the protocol schema. Packets of a type without
+    /// a schema for `direction` always pass.
+    pub fn validate(&self, direction: Direction) -> Result<(), Vec<SchemaViolation>> {
+        let PacketTypeResult::PacketType(packet_type) = self.get_type() else {
+            return Ok(());
+        };
+
+        let Some(schema) = packet_schema(packet_type, direction) else {
+            return Ok(());
+        };
+
+        let mut violations = Vec::new();
+
+        for expected in schema.vars {
+            match self.get_var(expected.var) {
+                Some(var) => {
+                    let actual = VarType::of(&var.data);
+                    if actual != Some(expected.var_type) {
+                        violations.push(SchemaViolation::WrongType {
+                            var: expected.var,
+                            expected: expected.var_type,
+                            actual,
+                        });
+                    }
+                }
+                None if expected.required => {
+                    violations.push(SchemaViolation::Missing(expected.var))
+                }
+                None => {}
+            }
+        }
+
+        for var in self.get_vars() {
+            if schema.var(var.get_var_id()).is_none() {
+                violations.push(SchemaViolation::Unexpected(var.get_var_id()));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+/// Every packet whose contents are known.
+pub static PROTOCOL_SCHEMA: &[PacketSchema] = &[
+    // Connection setup
+    PacketSchema {
+        packet_type: PacketType::PublicKeyRequest,
+        direction: ClientToServer,
+        vars: &[],
+    },
+    PacketSchema {
+        packet_type: PacketType::PublicKeyResponse,
+        direction: ClientToServer,
+        vars: &[req(VarID::EncryptionKey, Data)],
+    },
+    PacketSchema {
+        packet_type: PacketType::PublicKeyResponse,
+        direction: ServerToClient,
+        vars: &[req(VarID::EncryptionKey, Data)],
+    },
+    PacketSchema {
+        packet_type: PacketType::StreamKeyResponse,
+        direction: ClientToServer,
+        vars: &[req(VarID::EncryptionKey, Data)],
+    },
+    PacketSchema {
+        packet_type: PacketType::StreamKeyResponse,
+        direction: ServerToClient,
+        vars: &[req(VarID::EncryptionKey, Data)],
+    },
+    PacketSchema {
+        packet_type: PacketType::Heartbeat,
+        direction: ClientToServer,
+        vars: &[],
+    },
+    PacketSchema {
+        packet_type: PacketType::Heartbeat,
+        direction: ServerToClient,
+        vars: &[],
+    },
+    PacketSchema {
+        packet_type: PacketType::Login,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::UserType, Int),
+            req(VarID::LoginUsername, Str),
+            req(VarID::BrowserBuild, Int),
+            opt(VarID::BrowserVersion, Int),
+            opt(VarID::LoginID, Int),
+            opt(VarID::Password, Str),
+            opt(VarID::PrivilegeUserID, Int),
+            opt(VarID::PrivilegePassword, Str),
+            opt(VarID::Application, Str),
+            opt(VarID::Email, Str),
+            opt(VarID::VolumeSerial, Int),
+        ],
+    },
+    // Citizens
+    PacketSchema {
+        packet_type: PacketType::CitizenLookupByName,
+        direction: ClientToServer,
+        vars: &[req(VarID::CitizenName, Str)],
+    },
+    PacketSchema {
+        packet_type: PacketType::CitizenLookupByNumber,
+        direction: ClientToServer,
+        vars: &[req(VarID::CitizenNumber, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::CitizenNext,
+        direction: ClientToServer,
+        vars: &[req(VarID::CitizenNumber, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::CitizenPrev,
+        direction: ClientToServer,
+        vars: &[req(VarID::CitizenNumber, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::CitizenDelete,
+        direction: ClientToServer,
+        vars: &[req(VarID::CitizenNumber, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::CitizenChangeResult,
+        direction: ServerToClient,
+        vars: &[req(VarID::ReasonCode, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::Immigrate,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::CitizenName, Str),
+            req(VarID::CitizenPassword, Str),
+            req(VarID::CitizenEmail, Str),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::ImmigrateResponse,
+        direction: ServerToClient,
+        vars: &[req(VarID::ReasonCode, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::CAVGet,
+        direction: ClientToServer,
+        vars: &[req(VarID::CAVCitizen, Int)],
+    },
+    // Contacts
+    PacketSchema {
+        packet_type: PacketType::ContactAdd,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::ContactListName, Str),
+            req(VarID::ContactListOptions, Int),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::ContactChange,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::ContactListCitizenID, Int),
+            req(VarID::ContactListOptions, Int),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::ContactDelete,
+        direction: ClientToServer,
+        vars: &[req(VarID::ContactListCitizenID, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::ContactConfirm,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::ContactListCitizenID, Int),
+            req(VarID::ContactListOptions, Int),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::ContactList,
+        direction: ClientToServer,
+        vars: &[req(VarID::ContactListCitizenID, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::SetAFK,
+        direction: ClientToServer,
+        vars: &[req(VarID::AFKStatus, Int)],
+    },
+    // Telegrams and botgrams
+    PacketSchema {
+        packet_type: PacketType::TelegramSend,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::TelegramTo, Str),
+            req(VarID::TelegramMessage, Str),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::Botgram,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::BotgramCitizenNumber, Int),
+            req(VarID::BotgramMessage, Str),
+            opt(VarID::BotgramType, Int),
+        ],
+    },
+    // Joining
+    PacketSchema {
+        packet_type: PacketType::JoinRequest,
+        direction: ClientToServer,
+        vars: &[req(VarID::CitizenNumber, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::JoinReply,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::CitizenNumber, Int),
+            req(VarID::ReasonCode, Int),
+            opt(VarID::WorldName, Str),
+            opt(VarID::PositionNorth, Int),
+            opt(VarID::PositionHeight, Int),
+            opt(VarID::PositionWest, Int),
+            opt(VarID::PositionRotation, Int),
+        ],
+    },
+    // Lists
+    PacketSchema {
+        packet_type: PacketType::UserList,
+        direction: ClientToServer,
+        vars: &[opt(VarID::UserListContinuationID, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::WorldList,
+        direction: ClientToServer,
+        vars: &[opt(VarID::WorldList3DayUnknown, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::WorldLookup,
+        direction: ClientToServer,
+        vars: &[req(VarID::WorldName, Str)],
+    },
+    // Ejections
+    PacketSchema {
+        packet_type: PacketType::EjectAdd,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::EjectionAddress, Int),
+            req(VarID::EjectionExpiration, Int),
+            req(VarID::EjectionComment, Str),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::EjectDelete,
+        direction: ClientToServer,
+        vars: &[req(VarID::EjectionAddress, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::EjectLookup,
+        direction: ClientToServer,
+        vars: &[req(VarID::EjectionAddress, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::EjectNext,
+        direction: ClientToServer,
+        vars: &[req(VarID::EjectionAddress, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::EjectPrev,
+        direction: ClientToServer,
+        vars: &[req(VarID::EjectionAddress, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::EjectResult,
+        direction: ServerToClient,
+        vars: &[req(VarID::ReasonCode, Int)],
+    },
+    // Licenses
+    PacketSchema {
+        packet_type: PacketType::LicenseByName,
+        direction: ClientToServer,
+        vars: &[req(VarID::WorldName, Str)],
+    },
+    PacketSchema {
+        packet_type: PacketType::LicenseNext,
+        direction: ClientToServer,
+        vars: &[req(VarID::WorldName, Str)],
+    },
+    PacketSchema {
+        packet_type: PacketType::LicensePrev,
+        direction: ClientToServer,
+        vars: &[req(VarID::WorldName, Str)],
+    },
+    PacketSchema {
+        packet_type: PacketType::LicenseDelete,
+        direction: ClientToServer,
+        vars: &[req(VarID::WorldName, Str)],
+    },
+    PacketSchema {
+        packet_type: PacketType::LicenseAdd,
+        direction: ClientToServer,
+        vars: LICENSE_VARS,
+    },
+    PacketSchema {
+        packet_type: PacketType::LicenseChange,
+        direction: ClientToServer,
+        vars: LICENSE_VARS,
+    },
+    // World servers
+    PacketSchema {
+        packet_type: PacketType::WorldServerStart,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::BrowserVersion, Int),
+            req(VarID::WorldBuild, Int),
+            req(VarID::WorldPort, Int),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::WorldStart,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::WorldName, Str),
+            req(VarID::WorldLicensePassword, Str),
+            req(VarID::WorldRating, Byte),
+            req(VarID::WorldFreeEntry, Byte),
+            opt(VarID::WorldServerChallenge, Data),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::WorldStop,
+        direction: ClientToServer,
+        vars: &[req(VarID::WorldName, Str)],
+    },
+    PacketSchema {
+        packet_type: PacketType::WorldStop,
+        direction: ServerToClient,
+        vars: &[req(VarID::ReasonCode, Int)],
+    },
+    PacketSchema {
+        packet_type: PacketType::WorldStatsUpdate,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::WorldName, Str),
+            req(VarID::WorldRating, Byte),
+            req(VarID::WorldFreeEntry, Byte),
+            req(VarID::WorldUsers, Int),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::Identify,
+        direction: ClientToServer,
+        vars: &[
+            req(VarID::WorldName, Str),
+            req(VarID::WorldUserNonce, Data),
+            req(VarID::SessionID, Int),
+            req(VarID::IdentifyUserIP, Int),
+            req(VarID::PlayerPort, Int),
+        ],
+    },
+    // Sent by worlds to bots
+    PacketSchema {
+        packet_type: PacketType::Message,
+        direction: ServerToClient,
+        vars: &[
+            req(VarID::ChatMessage, Str),
+            req(VarID::ChatType, Int),
+            req(VarID::MyName, Str),
+            req(VarID::MySession, Int),
+        ],
+    },
+    PacketSchema {
+        packet_type: PacketType::AvatarDelete,
+        direction: ServerToClient,
+        vars: &[req(VarID::MySession, Int), req(VarID::MyName, Str)],
+    },
+    PacketSchema {
+        packet_type: PacketType::HudResult,
+        direction: ServerToClient,
+        vars: &[
+            req(VarID::HudElementId, Int),
+            req(VarID::HudElementSession, Int),
+        ],
+    },
+];
+
+const LICENSE_VARS: &[VarSchema] = &[
+    req(VarID::WorldName, Str),
+    opt(VarID::WorldLicensePassword, Str),
+    opt(VarID::WorldLicenseEmail, Str),
+    opt(VarID::WorldLicenseComment, Str),
+    opt(VarID::WorldLicenseExpiration, Int),
+    opt(VarID::WorldLicenseHidden, Int),
+    opt(VarID::WorldLicensePlugins, Int),
+    opt(VarID::WorldLicenseRange, Int),
+    opt(VarID::WorldLicenseTourists, Int),
+    opt(VarID::WorldLicenseUsers, Int),
+    opt(VarID::WorldLicenseVoip, Int),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_world_stop() {
+        let mut packet = AWPacket::new(PacketType::WorldStop);
+        assert_eq!(
+            packet.validate(ClientToServer),
+            Err(vec![SchemaViolation::Missing(VarID::WorldName)])
+        );
+
+        packet.add_uint(VarID::WorldName, 3);
+        packet.add_string(VarID::ChatMessage, "hi".to_string());
+        assert_eq!(
+            packet.validate(ClientToServer),
+            Err(vec![
+                SchemaViolation::WrongType {
+                    var: VarID::WorldName,
+                    expected: Str,
+                    actual: Some(Int),
+                },
+                SchemaViolation::Unexpected(VarID::ChatMessage.into()),
+            ])
+        );
+
+        let mut packet = AWPacket::new(PacketType::WorldStop);
+        packet.add_string(VarID::WorldName, "AW".to_string());
+        assert_eq!(packet.validate(ClientToServer), Ok(()));
+        assert!(packet.validate(ServerToClient).is_err());
+    }
+
+    #[test]
+    fn schema_has_no_duplicates() {
+        for (i, a) in PROTOCOL_SCHEMA.iter().enumerate() {
+            for b in &PROTOCOL_SCHEMA[i + 1..] {
+                assert!(
+                    a.packet_type != b.packet_type || a.direction != b.direction,
+                    "{:?} {:?} is listed twice",
+                    a.packet_type,
+                    a.direction
+                );
+            }
+        }
+    }
+}
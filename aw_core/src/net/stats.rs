@@ -0,0 +1,283 @@
+//! Traffic statistics for a connection
+use crate::{AWPacket, PacketTypeResult};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Number of packets and their serialized size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TrafficCounter {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+impl TrafficCounter {
+    fn add(&mut self, other: TrafficCounter) {
+        self.packets += other.packets;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Traffic in one direction of a connection.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DirectionStats {
+    /// Packets and their size before compression
+    pub packets: TrafficCounter,
+    /// Bytes after compression (or before decompression)
+    pub compressed_bytes: u64,
+    /// Bytes on the wire, after encryption (or before decryption)
+    pub wire_bytes: u64,
+    /// Packets and their size before compression per raw packet type,
+    /// including types this crate does not know about
+    pub by_type: BTreeMap<i16, TrafficCounter>,
+}
+
+impl DirectionStats {
+    /// Compressed size as a fraction of the uncompressed size, so smaller is better.
+    pub fn compression_ratio(&self) -> f64 {
+        if self.packets.bytes == 0 {
+            return 1.0;
+        }
+        self.compressed_bytes as f64 / self.packets.bytes as f64
+    }
+
+    /// Add the traffic of another connection to this one.
+    pub fn merge(&mut self, other: &DirectionStats) {
+        self.packets.add(other.packets);
+        self.compressed_bytes += other.compressed_bytes;
+        self.wire_bytes += other.wire_bytes;
+        for (packet_type, counter) in &other.by_type {
+            self.by_type.entry(*packet_type).or_default().add(*counter);
+        }
+    }
+
+    fn record_packet(&mut self, packet: &AWPacket, bytes: usize) {
+        let counter = TrafficCounter {
+            packets: 1,
+            bytes: bytes as u64,
+        };
+        self.packets.add(counter);
+        self.by_type
+            .entry(i16::from(packet.get_type()))
+            .or_default()
+            .add(counter);
+    }
+
+    /// The packet type with the most bytes, if any packets were counted.
+    pub fn busiest_type(&self) -> Option<(PacketTypeResult, TrafficCounter)> {
+        self.by_type
+            .iter()
+            .max_by_key(|(_, counter)| counter.bytes)
+            .map(|(packet_type, counter)| (PacketTypeResult::from(*packet_type), *counter))
+    }
+}
+
+impl fmt::Display for DirectionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} packets, {} bytes ({} compressed, ratio {:.2}), {} on the wire",
+            self.packets.packets,
+            self.packets.bytes,
+            self.compressed_bytes,
+            self.compression_ratio(),
+            self.wire_bytes
+        )?;
+
+        if let Some((packet_type, counter)) = self.busiest_type() {
+            write!(
+                f,
+                ", mostly {packet_type:?} ({} packets, {} bytes)",
+                counter.packets, counter.bytes
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Snapshot of the traffic of a connection.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TrafficStats {
+    pub sent: DirectionStats,
+    pub received: DirectionStats,
+    /// Time between the most recent heartbeat sent and the next one received
+    pub heartbeat_rtt: Option<Duration>,
+    /// Lowest heartbeat round trip time seen
+    pub min_heartbeat_rtt: Option<Duration>,
+    /// Packets waiting to be sent when the snapshot was taken
+    pub queued: TrafficCounter,
+}
+
+impl TrafficStats {
+    /// Add the traffic of another connection to this one. Round trip times
+    /// are not added up; the worst one is kept instead.
+    pub fn merge(&mut self, other: &TrafficStats) {
+        self.sent.merge(&other.sent);
+        self.received.merge(&other.received);
+        self.queued.add(other.queued);
+        self.heartbeat_rtt = self.heartbeat_rtt.max(other.heartbeat_rtt);
+        self.min_heartbeat_rtt = match (self.min_heartbeat_rtt, other.min_heartbeat_rtt) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+impl fmt::Display for TrafficStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sent {}; received {}", self.sent, self.received)?;
+        if self.queued.packets > 0 {
+            write!(
+                f,
+                "; {} packets ({} bytes) queued",
+                self.queued.packets, self.queued.bytes
+            )?;
+        }
+        if let Some(rtt) = self.heartbeat_rtt {
+            write!(f, "; heartbeat rtt {rtt:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Traffic counters shared between a connection and its protocol thread.
+#[derive(Debug, Default)]
+pub struct TrafficMeter {
+    stats: Mutex<TrafficStats>,
+    /// When the heartbeat that has not been answered yet was sent
+    heartbeat_sent: Mutex<Option<Instant>>,
+}
+
+impl TrafficMeter {
+    /// Copy of the counters as they are now.
+    pub fn snapshot(&self) -> TrafficStats {
+        match self.stats.lock() {
+            Ok(stats) => stats.clone(),
+            Err(_) => TrafficStats::default(),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut TrafficStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            f(&mut stats);
+        }
+    }
+
+    /// Count packets that were sent together, with their serialized sizes.
+    pub(crate) fn record_sent(
+        &self,
+        packets: &[(&AWPacket, usize)],
+        compressed_bytes: usize,
+        wire_bytes: usize,
+    ) {
+        self.update(|stats| {
+            for (packet, bytes) in packets {
+                stats.sent.record_packet(packet, *bytes);
+            }
+            stats.sent.compressed_bytes += compressed_bytes as u64;
+            stats.sent.wire_bytes += wire_bytes as u64;
+        });
+
+        let sent_heartbeat = packets.iter().any(|(packet, _)| is_heartbeat(packet));
+        if sent_heartbeat {
+            if let Ok(mut heartbeat_sent) = self.heartbeat_sent.lock() {
+                // Measure from the oldest unanswered heartbeat
+                heartbeat_sent.get_or_insert_with(Instant::now);
+            }
+        }
+    }
+
+    /// Count a received packet with its serialized size.
+    pub(crate) fn record_received(&self, packet: &AWPacket, bytes: usize) {
+        let rtt = if is_heartbeat(packet) {
+            self.heartbeat_sent
+                .lock()
+                .ok()
+                .and_then(|mut heartbeat_sent| heartbeat_sent.take())
+                .map(|sent| sent.elapsed())
+        } else {
+            None
+        };
+
+        self.update(|stats| {
+            stats.received.record_packet(packet, bytes);
+            if let Some(rtt) = rtt {
+                stats.heartbeat_rtt = Some(rtt);
+                stats.min_heartbeat_rtt =
+                    Some(stats.min_heartbeat_rtt.map_or(rtt, |min| min.min(rtt)));
+            }
+        });
+    }
+
+    /// Count bytes received before they are decompressed.
+    pub(crate) fn record_received_compressed(&self, bytes: usize) {
+        self.update(|stats| stats.received.compressed_bytes += bytes as u64);
+    }
+
+    /// Count bytes read from the socket.
+    pub(crate) fn record_received_wire(&self, bytes: usize) {
+        self.update(|stats| stats.received.wire_bytes += bytes as u64);
+    }
+}
+
+fn is_heartbeat(packet: &AWPacket) -> bool {
+    packet.get_type() == PacketTypeResult::PacketType(crate::PacketType::Heartbeat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PacketType;
+
+    #[test]
+    fn counts_and_heartbeat_rtt() {
+        let meter = TrafficMeter::default();
+        let heartbeat = AWPacket::new(PacketType::Heartbeat);
+        let address = AWPacket::new(PacketType::Address);
+
+        meter.record_sent(&[(&heartbeat, 12), (&address, 20)], 16, 16);
+        meter.record_received_wire(12);
+        meter.record_received_compressed(12);
+        meter.record_received(&heartbeat, 12);
+
+        let stats = meter.snapshot();
+        assert_eq!(
+            stats.sent.packets,
+            TrafficCounter {
+                packets: 2,
+                bytes: 32
+            }
+        );
+        assert_eq!(stats.sent.compression_ratio(), 0.5);
+        assert_eq!(
+            stats.sent.busiest_type(),
+            Some((
+                PacketTypeResult::PacketType(PacketType::Address),
+                TrafficCounter {
+                    packets: 1,
+                    bytes: 20
+                }
+            ))
+        );
+        assert_eq!(stats.received.wire_bytes, 12);
+        assert!(stats.heartbeat_rtt.is_some());
+
+        // A heartbeat that was not answered is not measured
+        meter.record_received(&heartbeat, 12);
+        let mut total = meter.snapshot();
+        assert_eq!(total.heartbeat_rtt, stats.heartbeat_rtt);
+
+        total.queued = TrafficCounter {
+            packets: 1,
+            bytes: 8,
+        };
+        total.merge(&stats);
+        assert_eq!(total.received.packets.packets, 3);
+        assert_eq!(total.queued.bytes, 8);
+        assert_eq!(total.sent.by_type.len(), 2);
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+
+use crate::{AWPacket, PacketType, VarID};
+
+pub use aw_derive::TypedPacket;
+
+/// A value that can be stored in a single packet variable.
+pub trait PacketField: Sized {
+    /// Read the variable, or `None` if it is absent or cannot represent `Self`.
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self>;
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID);
+}
+
+/// A set of packet variables read and written together, usually derived with
+/// `#[derive(TypedPacket)]`.
+pub trait PacketFields: Sized {
+    fn decode(packet: &AWPacket) -> Result<Self, TypedPacketError>;
+    fn encode_into(&self, packet: &mut AWPacket);
+}
+
+/// Packet variables that make up a whole packet of one type.
+pub trait TypedPacket: PacketFields {
+    const PACKET_TYPE: PacketType;
+
+    fn encode(&self) -> AWPacket {
+        let mut packet = AWPacket::new(Self::PACKET_TYPE);
+        self.encode_into(&mut packet);
+        packet
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypedPacketError {
+    /// A required variable is not in the packet
+    Missing(VarID),
+    /// A variable is present but has the wrong type or an out of range value
+    Invalid(VarID),
+}
+
+impl TypedPacketError {
+    /// The error for a variable that could not be read from `packet`.
+    pub fn for_var(packet: &AWPacket, var: VarID) -> Self {
+        match packet.get_var(var) {
+            Some(_) => Self::Invalid(var),
+            None => Self::Missing(var),
+        }
+    }
+}
+
+impl fmt::Display for TypedPacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(var) => write!(f, "missing packet variable {var:?}"),
+            Self::Invalid(var) => write!(f, "invalid packet variable {var:?}"),
+        }
+    }
+}
+
+impl std::error::Error for TypedPacketError {}
+
+impl PacketField for u8 {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_byte(var)
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_byte(var, *self);
+    }
+}
+
+impl PacketField for bool {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_byte(var).map(|x| x != 0)
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_byte(var, u8::from(*self));
+    }
+}
+
+impl PacketField for i32 {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_int(var)
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_int(var, *self);
+    }
+}
+
+impl PacketField for u32 {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_uint(var)
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_uint(var, *self);
+    }
+}
+
+/// Sent as a 32-bit integer, which must fit in 16 bits when received.
+impl PacketField for u16 {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_uint(var).and_then(|x| u16::try_from(x).ok())
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_uint(var, u32::from(*self));
+    }
+}
+
+impl PacketField for f32 {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_float(var)
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_float(var, *self);
+    }
+}
+
+impl PacketField for String {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_string(var)
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_string(var, self.clone());
+    }
+}
+
+impl PacketField for Vec<u8> {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_data(var)
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_data(var, self.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(TypedPacket, Debug, PartialEq)]
+    #[aw(packet = PacketType::WorldStart)]
+    struct WorldStart {
+        #[aw(var = VarID::WorldName)]
+        name: String,
+        #[aw(var = VarID::SessionID)]
+        session: u16,
+        #[aw(var = VarID::WorldFreeEntry, default)]
+        free_entry: bool,
+        #[aw(var = VarID::WorldUserNonce)]
+        nonce: Option<Vec<u8>>,
+    }
+
+    #[derive(TypedPacket, Debug, PartialEq)]
+    struct Wrapper {
+        #[aw(var = VarID::ReasonCode)]
+        rc: i32,
+        #[aw(flatten)]
+        world: WorldStart,
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = Wrapper {
+            rc: 7,
+            world: WorldStart {
+                name: "AW".to_string(),
+                session: 12,
+                free_entry: true,
+                nonce: Some(vec![1, 2, 3]),
+            },
+        };
+
+        let mut packet = original.world.encode();
+        assert_eq!(
+            packet.get_type(),
+            crate::PacketTypeResult::PacketType(PacketType::WorldStart)
+        );
+        original.encode_into(&mut packet);
+        assert_eq!(Wrapper::decode(&packet), Ok(original));
+    }
+
+    #[test]
+    fn optional_and_required_fields() {
+        let mut packet = AWPacket::new(PacketType::WorldStart);
+        assert_eq!(
+            WorldStart::decode(&packet),
+            Err(TypedPacketError::Missing(VarID::WorldName))
+        );
+
+        packet.add_string(VarID::WorldName, "AW".to_string());
+        packet.add_uint(VarID::SessionID, 70000);
+        assert_eq!(
+            WorldStart::decode(&packet),
+            Err(TypedPacketError::Invalid(VarID::SessionID))
+        );
+
+        let mut packet = AWPacket::new(PacketType::WorldStart);
+        packet.add_string(VarID::WorldName, "AW".to_string());
+        packet.add_uint(VarID::SessionID, 5);
+        assert_eq!(
+            WorldStart::decode(&packet),
+            Ok(WorldStart {
+                name: "AW".to_string(),
+                session: 5,
+                free_entry: false,
+                nonce: None,
+            })
+        );
+    }
+}
@@ -1,4 +1,7 @@
 use num_enum::TryFromPrimitive;
+use std::fmt;
+
+mod en;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, TryFromPrimitive)]
 #[repr(i32)]
@@ -207,6 +210,288 @@ impl ReasonCode {
     pub fn is_ok(&self) -> bool {
         !self.is_err()
     }
+
+    /// Human-readable description of the code in English.
+    pub fn description(&self) -> &'static str {
+        self.message(Language::English)
+    }
+
+    /// Human-readable description of the code in the given language.
+    pub fn message(&self, language: Language) -> &'static str {
+        match language {
+            Language::English => en::message(*self),
+        }
+    }
+
+    /// What part of the system a reason code is about.
+    pub fn category(&self) -> ReasonCodeCategory {
+        match self {
+            Self::Success | Self::ServerOutOfMemory => ReasonCodeCategory::General,
+            Self::CitizenshipExpired
+            | Self::NoSuchCitizen
+            | Self::InvalidPassword
+            | Self::SdkMustUpgrade
+            | Self::NotLoggedIn
+            | Self::Unauthorized
+            | Self::IdentityAlreadyInUse
+            | Self::NoSuchActingCitizen
+            | Self::ActingPasswordInvalid
+            | Self::UniverseFull
+            | Self::MustUpgrade
+            | Self::BotLimitExceeded
+            | Self::NoSuchEjection
+            | Self::NoSuchSession
+            | Self::EjectionExpired
+            | Self::ActingCitizenExpired
+            | Self::CitizenDisabled
+            | Self::BetaRequired
+            | Self::ActingCitizenDisabled
+            | Self::TouristAllowed
+            | Self::NoTourists
+            | Self::PasswordTooLong
+            | Self::PasswordTooShort
+            | Self::PasswordWrong
+            | Self::PrivilegePasswordIsTooShort
+            | Self::PrivilegePasswordIsTooLong
+            | Self::Imposter
+            | Self::Ejected
+            | Self::NotWelcome => ReasonCodeCategory::Auth,
+            Self::UnableToMailBackNumber
+            | Self::ImmigrationNotAllowed
+            | Self::CitizenDoesNotExpire
+            | Self::NumberAlreadyUsed
+            | Self::NumberOutOfRange
+            | Self::NoSuchCav
+            | Self::NoCavTemplate
+            | Self::EmailChangeNotAllowed
+            | Self::NameChangeNotAllowed => ReasonCodeCategory::Citizen,
+            Self::InvalidEmail
+            | Self::EmailContainsInvalidChar
+            | Self::EmailEndsWithBlank
+            | Self::EmailMissingDot
+            | Self::EmailMissingAt
+            | Self::EmailStartsWithBlank
+            | Self::EmailTooLong
+            | Self::EmailTooShort
+            | Self::NameAlreadyUsed
+            | Self::NameContainsNonalphanumericChar
+            | Self::NameContainsInvalidBlank
+            | Self::NameDoesntExist
+            | Self::NameEndsWithBlank
+            | Self::NameTooLong
+            | Self::NameTooShort
+            | Self::NameUnused
+            | Self::EmailAlreadyUsed
+            | Self::EmailNotAllowed => ReasonCodeCategory::NameValidation,
+            Self::BillingTimeout
+            | Self::BillingRecvFailed
+            | Self::BillingResponseInvalid
+            | Self::BillingRejected
+            | Self::BillingBlocked => ReasonCodeCategory::Billing,
+            Self::LandLimitExceeded
+            | Self::LicensePasswordContainsSpace
+            | Self::LicensePasswordTooLong
+            | Self::LicensePasswordTooShort
+            | Self::LicenseRangeTooLarge
+            | Self::LicenseRangeTooSmall
+            | Self::LicenseUsersTooLarge
+            | Self::LicenseUsersTooSmall
+            | Self::LicenseContainsInvalidChar
+            | Self::LicenseWorldTooShort
+            | Self::LicenseWorldTooLong
+            | Self::NoSuchLicense
+            | Self::TooManyWorlds
+            | Self::WorldExpired
+            | Self::LicenseStartsWithNumber
+            | Self::InvalidUserCount => ReasonCodeCategory::License,
+            Self::InvalidWorld
+            | Self::ServerOutdated
+            | Self::WorldAlreadyStarted
+            | Self::NotWorldOwner
+            | Self::NoSuchWorld
+            | Self::WorldAlreadyExists
+            | Self::UnableToReportLocation
+            | Self::AlreadyStarted
+            | Self::WorldRunning
+            | Self::WorldNotSet
+            | Self::WorldDisabled
+            | Self::PrivateWorld
+            | Self::NoPort
+            | Self::UnableToChangeAttribute
+            | Self::JoinRefused
+            | Self::InvalidWorldName
+            | Self::WorldNameTooLong
+            | Self::WorldFull
+            | Self::OldWorld
+            | Self::WorldNotRunning
+            | Self::WorldInstanceAlreadyExists
+            | Self::WorldInstanceInvalid
+            | Self::WorldRedirect => ReasonCodeCategory::World,
+            Self::NoSuchCell
+            | Self::UnableToUpdateTerrain
+            | Self::NotChangeOwner
+            | Self::CantFindOldElement
+            | Self::CantChangeOwner
+            | Self::CantBuildHere
+            | Self::Encroaches
+            | Self::ObjectTypeInvalid
+            | Self::TooManyBytes
+            | Self::UnableToStore
+            | Self::UnregisteredObject
+            | Self::ElementAlreadyExists
+            | Self::RestrictedCommand
+            | Self::NoBuildRights
+            | Self::OutOfBounds
+            | Self::RestrictedObject
+            | Self::RestrictedArea => ReasonCodeCategory::Building,
+            Self::MessageLengthBad
+            | Self::UnableToSendTelegram
+            | Self::UnableToGetTelegram
+            | Self::UnableToSetContact
+            | Self::TelegramBlocked
+            | Self::TelegramTooLong
+            | Self::BotgramNotYet
+            | Self::TelegramBlockedByPlugin
+            | Self::MessageTooLong
+            | Self::ContactAddBlocked => ReasonCodeCategory::Communication,
+            Self::UnableToChangeCitizen
+            | Self::NoRegistry
+            | Self::CantOpenRegistry
+            | Self::UnableToDeleteName
+            | Self::UnableToGetCitizen
+            | Self::UnableToInsertCitizen
+            | Self::UnableToInsertName
+            | Self::UnableToPutCitizenCount
+            | Self::UnableToDeleteCitizen
+            | Self::UnableToChangeLicense
+            | Self::UnableToUpdateCav
+            | Self::UnableToDeleteCav
+            | Self::UnableToGetContacts
+            | Self::DatabaseError
+            | Self::NoDatabase => ReasonCodeCategory::Database,
+            Self::Timeout
+            | Self::UnableToContactUniverse
+            | Self::UnableToContactWorld
+            | Self::SendFailed
+            | Self::ReceiveFailed
+            | Self::StreamEmpty
+            | Self::StreamMessageTooLong
+            | Self::TooManyResets
+            | Self::UnableToCreateSocket
+            | Self::UnableToConnect
+            | Self::UnableToSetNonblocking
+            | Self::CantOpenStream
+            | Self::CantWriteStream
+            | Self::CantCloseStream
+            | Self::NoConnection
+            | Self::UnableToInitializeNetwork
+            | Self::IncorrectMessageLength
+            | Self::OutBufferFull
+            | Self::UnableToRegisterResolve
+            | Self::VersionMismatch
+            | Self::InBufferFull
+            | Self::ProtocolError
+            | Self::UnableToBind
+            | Self::UnableToListen
+            | Self::UnableToAccept
+            | Self::ConnectionLost
+            | Self::NoStream
+            | Self::OldUniverse
+            | Self::CantResolveUniverseHost
+            | Self::ZBufError
+            | Self::ZMemError
+            | Self::ZDataError => ReasonCodeCategory::Network,
+            Self::InvalidRequest
+            | Self::OutOfMemory
+            | Self::NotYet
+            | Self::NullPointer
+            | Self::NotInitialized
+            | Self::NoInstance
+            | Self::InvalidCallback
+            | Self::InvalidAttribute
+            | Self::TypeMismatch
+            | Self::StringTooLong
+            | Self::ReadOnly
+            | Self::InvalidInstance
+            | Self::QueryInProgress
+            | Self::NotAvailable
+            | Self::InvalidArgument
+            | Self::PluginNotAvailable => ReasonCodeCategory::Sdk,
+        }
+    }
+}
+
+impl fmt::Display for ReasonCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (reason code {})", self.description(), *self as i32)
+    }
+}
+
+/// What part of the system a [`ReasonCode`] is about.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ReasonCodeCategory {
+    /// Success, or a problem with the server as a whole
+    General,
+    /// Logging in, passwords, permissions and ejections
+    Auth,
+    /// Citizen accounts and their settings
+    Citizen,
+    /// Checks on names and email addresses
+    NameValidation,
+    Billing,
+    License,
+    /// Starting, finding and entering worlds
+    World,
+    /// Objects, cells and terrain in a world
+    Building,
+    /// Telegrams, botgrams, contacts and chat
+    Communication,
+    /// Reading or writing stored data
+    Database,
+    /// Connections and the data sent over them
+    Network,
+    /// Misuse of the SDK
+    Sdk,
+}
+
+impl fmt::Display for ReasonCodeCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::General => "general",
+            Self::Auth => "authentication",
+            Self::Citizen => "citizen",
+            Self::NameValidation => "name validation",
+            Self::Billing => "billing",
+            Self::License => "license",
+            Self::World => "world",
+            Self::Building => "building",
+            Self::Communication => "communication",
+            Self::Database => "database",
+            Self::Network => "network",
+            Self::Sdk => "SDK",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Language of the messages describing reason codes.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+#[non_exhaustive]
+pub enum Language {
+    #[default]
+    English,
+}
+
+impl Language {
+    /// Find the language for an IETF language tag such as `en-US`, if there
+    /// are messages for it.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next()?;
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Self::English),
+            _ => None,
+        }
+    }
 }
 
 impl From<ReasonCode> for i32 {
@@ -220,3 +505,39 @@ impl From<ReasonCode> for u32 {
         val as u32
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptions_and_categories() {
+        assert_eq!(
+            ReasonCode::NoSuchCitizen.to_string(),
+            "No such citizen (reason code 3)"
+        );
+        assert_eq!(
+            ReasonCode::NoSuchCitizen.category(),
+            ReasonCodeCategory::Auth
+        );
+        assert_eq!(
+            ReasonCode::BillingBlocked.category(),
+            ReasonCodeCategory::Billing
+        );
+        assert_eq!(
+            ReasonCode::NameTooLong.category(),
+            ReasonCodeCategory::NameValidation
+        );
+        assert_eq!(
+            ReasonCode::DatabaseError.category(),
+            ReasonCodeCategory::Database
+        );
+
+        assert_eq!(Language::from_tag("en-US"), Some(Language::English));
+        assert_eq!(Language::from_tag("xx"), None);
+        assert_eq!(
+            ReasonCode::WorldFull.message(Language::English),
+            ReasonCode::WorldFull.description()
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! English messages for every [`ReasonCode`].
+use super::ReasonCode;
+
+pub(super) fn message(code: ReasonCode) -> &'static str {
+    match code {
+        ReasonCode::Success => "Success",
+        ReasonCode::CitizenshipExpired => "Citizenship has expired",
+        ReasonCode::LandLimitExceeded => "World would exceed the land limit of its license",
+        ReasonCode::NoSuchCitizen => "No such citizen",
+        ReasonCode::MessageLengthBad => "Message length is invalid",
+        ReasonCode::LicensePasswordContainsSpace => "License password contains a space",
+        ReasonCode::LicensePasswordTooLong => "License password is too long",
+        ReasonCode::LicensePasswordTooShort => "License password is too short",
+        ReasonCode::LicenseRangeTooLarge => "License range is too large",
+        ReasonCode::LicenseRangeTooSmall => "License range is too small",
+        ReasonCode::LicenseUsersTooLarge => "License user limit is too large",
+        ReasonCode::LicenseUsersTooSmall => "License user limit is too small",
+        ReasonCode::LicenseContainsInvalidChar => "License name contains an invalid character",
+        ReasonCode::InvalidPassword => "Invalid password",
+        ReasonCode::UnableToMailBackNumber => "Unable to mail back the citizen number",
+        ReasonCode::LicenseWorldTooShort => "World name on the license is too short",
+        ReasonCode::LicenseWorldTooLong => "World name on the license is too long",
+        ReasonCode::ServerOutOfMemory => "Server is out of memory",
+        ReasonCode::SdkMustUpgrade => "This SDK build is too old and must be upgraded",
+        ReasonCode::InvalidWorld => "Invalid world",
+        ReasonCode::ServerOutdated => "World server is outdated",
+        ReasonCode::WorldAlreadyStarted => "World has already been started",
+        ReasonCode::NotWorldOwner => "Not the owner of this world",
+        ReasonCode::NoSuchWorld => "No such world",
+        ReasonCode::UnableToChangeCitizen => "Unable to change citizen",
+        ReasonCode::NotLoggedIn => "Not logged in",
+        ReasonCode::Unauthorized => "Unauthorized",
+        ReasonCode::WorldAlreadyExists => "World already exists",
+        ReasonCode::NoSuchLicense => "No such license",
+        ReasonCode::UnableToSendTelegram => "Unable to send telegram",
+        ReasonCode::UnableToGetTelegram => "Unable to get telegram",
+        ReasonCode::UnableToSetContact => "Unable to set contact",
+        ReasonCode::IdentityAlreadyInUse => "Identity is already in use",
+        ReasonCode::UnableToReportLocation => "Unable to report location",
+        ReasonCode::InvalidEmail => "Invalid email address",
+        ReasonCode::NoSuchActingCitizen => "No such acting citizen",
+        ReasonCode::ActingPasswordInvalid => "Acting citizen password is invalid",
+        ReasonCode::UniverseFull => "Universe is full",
+        ReasonCode::BillingTimeout => "Billing system timed out",
+        ReasonCode::BillingRecvFailed => "Could not receive a response from the billing system",
+        ReasonCode::BillingResponseInvalid => "Billing system sent an invalid response",
+        ReasonCode::ImmigrationNotAllowed => "Immigration is not allowed",
+        ReasonCode::BillingRejected => "Billing system rejected the request",
+        ReasonCode::BillingBlocked => "Blocked by the billing system",
+        ReasonCode::TooManyWorlds => "Too many worlds",
+        ReasonCode::MustUpgrade => "This browser build is too old and must be upgraded",
+        ReasonCode::BotLimitExceeded => "Bot limit exceeded",
+        ReasonCode::WorldExpired => "World license has expired",
+        ReasonCode::CitizenDoesNotExpire => "Citizenship does not expire",
+        ReasonCode::LicenseStartsWithNumber => "License name starts with a number",
+        ReasonCode::NoSuchEjection => "No such ejection",
+        ReasonCode::NoSuchSession => "No such session",
+        ReasonCode::EjectionExpired => "Ejection has expired",
+        ReasonCode::ActingCitizenExpired => "Acting citizen has expired",
+        ReasonCode::AlreadyStarted => "Already started",
+        ReasonCode::WorldRunning => "World is running",
+        ReasonCode::WorldNotSet => "World is not set",
+        ReasonCode::NoSuchCell => "No such cell",
+        ReasonCode::NoRegistry => "No registry",
+        ReasonCode::CantOpenRegistry => "Cannot open the registry",
+        ReasonCode::CitizenDisabled => "Citizen is disabled",
+        ReasonCode::WorldDisabled => "World is disabled",
+        ReasonCode::BetaRequired => "A beta browser is required",
+        ReasonCode::ActingCitizenDisabled => "Acting citizen is disabled",
+        ReasonCode::InvalidUserCount => "Invalid user count",
+        ReasonCode::TouristAllowed => "Tourists are allowed",
+        ReasonCode::TelegramBlocked => "Telegram was blocked",
+        ReasonCode::TelegramTooLong => "Telegram is too long",
+        ReasonCode::UnableToUpdateTerrain => "Unable to update terrain",
+        ReasonCode::PrivateWorld => "World is private",
+        ReasonCode::NoTourists => "Tourists are not allowed",
+        ReasonCode::EmailContainsInvalidChar => "Email address contains an invalid character",
+        ReasonCode::EmailEndsWithBlank => "Email address ends with a blank",
+        ReasonCode::EmailMissingDot => "Email address is missing a dot",
+        ReasonCode::EmailMissingAt => "Email address is missing an @",
+        ReasonCode::EmailStartsWithBlank => "Email address starts with a blank",
+        ReasonCode::EmailTooLong => "Email address is too long",
+        ReasonCode::EmailTooShort => "Email address is too short",
+        ReasonCode::NameAlreadyUsed => "Name is already used",
+        ReasonCode::NameContainsNonalphanumericChar => "Name contains a non-alphanumeric character",
+        ReasonCode::NameContainsInvalidBlank => "Name contains an invalid blank",
+        ReasonCode::NameDoesntExist => "Name does not exist",
+        ReasonCode::NameEndsWithBlank => "Name ends with a blank",
+        ReasonCode::NameTooLong => "Name is too long",
+        ReasonCode::NameTooShort => "Name is too short",
+        ReasonCode::NameUnused => "Name is unused",
+        ReasonCode::PasswordTooLong => "Password is too long",
+        ReasonCode::PasswordTooShort => "Password is too short",
+        ReasonCode::PasswordWrong => "Password is wrong",
+        ReasonCode::UnableToDeleteName => "Unable to delete name",
+        ReasonCode::UnableToGetCitizen => "Unable to get citizen",
+        ReasonCode::UnableToInsertCitizen => "Unable to insert citizen",
+        ReasonCode::UnableToInsertName => "Unable to insert name",
+        ReasonCode::UnableToPutCitizenCount => "Unable to store the citizen count",
+        ReasonCode::UnableToDeleteCitizen => "Unable to delete citizen",
+        ReasonCode::NumberAlreadyUsed => "Citizen number is already used",
+        ReasonCode::NumberOutOfRange => "Citizen number is out of range",
+        ReasonCode::PrivilegePasswordIsTooShort => "Privilege password is too short",
+        ReasonCode::PrivilegePasswordIsTooLong => "Privilege password is too long",
+        ReasonCode::UnableToChangeLicense => "Unable to change license",
+        ReasonCode::BotgramNotYet => "Too soon to send another botgram",
+        ReasonCode::NoPort => "No port",
+        ReasonCode::NotChangeOwner => "Not allowed to change the owner",
+        ReasonCode::CantFindOldElement => "Cannot find the old element",
+        ReasonCode::UnableToChangeAttribute => "Unable to change attribute",
+        ReasonCode::CantChangeOwner => "Cannot change the owner",
+        ReasonCode::Imposter => "Imposter",
+        ReasonCode::InvalidRequest => "Invalid request",
+        ReasonCode::CantBuildHere => "Cannot build here",
+        ReasonCode::JoinRefused => "Join refused",
+        ReasonCode::TelegramBlockedByPlugin => "Telegram was blocked by a plugin",
+        ReasonCode::Encroaches => "Object encroaches on another property",
+        ReasonCode::ObjectTypeInvalid => "Invalid object type",
+        ReasonCode::TooManyBytes => "Too many bytes",
+        ReasonCode::UnableToStore => "Unable to store",
+        ReasonCode::UnregisteredObject => "Unregistered object",
+        ReasonCode::ElementAlreadyExists => "Element already exists",
+        ReasonCode::RestrictedCommand => "Restricted command",
+        ReasonCode::NoBuildRights => "No build rights",
+        ReasonCode::OutOfBounds => "Out of bounds",
+        ReasonCode::RestrictedObject => "Restricted object",
+        ReasonCode::RestrictedArea => "Restricted area",
+        ReasonCode::OutOfMemory => "Out of memory",
+        ReasonCode::NotYet => "Not yet",
+        ReasonCode::Timeout => "Timed out",
+        ReasonCode::NullPointer => "Null pointer",
+        ReasonCode::UnableToContactUniverse => "Unable to contact the universe",
+        ReasonCode::UnableToContactWorld => "Unable to contact the world",
+        ReasonCode::InvalidWorldName => "Invalid world name",
+        ReasonCode::SendFailed => "Send failed",
+        ReasonCode::ReceiveFailed => "Receive failed",
+        ReasonCode::StreamEmpty => "Stream is empty",
+        ReasonCode::StreamMessageTooLong => "Stream message is too long",
+        ReasonCode::WorldNameTooLong => "World name is too long",
+        ReasonCode::MessageTooLong => "Message is too long",
+        ReasonCode::TooManyResets => "Too many resets",
+        ReasonCode::UnableToCreateSocket => "Unable to create socket",
+        ReasonCode::UnableToConnect => "Unable to connect",
+        ReasonCode::UnableToSetNonblocking => "Unable to set nonblocking mode",
+        ReasonCode::CantOpenStream => "Cannot open stream",
+        ReasonCode::CantWriteStream => "Cannot write to stream",
+        ReasonCode::CantCloseStream => "Cannot close stream",
+        ReasonCode::NoConnection => "No connection",
+        ReasonCode::UnableToInitializeNetwork => "Unable to initialize the network",
+        ReasonCode::IncorrectMessageLength => "Incorrect message length",
+        ReasonCode::NotInitialized => "Not initialized",
+        ReasonCode::NoInstance => "No instance",
+        ReasonCode::OutBufferFull => "Outgoing buffer is full",
+        ReasonCode::InvalidCallback => "Invalid callback",
+        ReasonCode::InvalidAttribute => "Invalid attribute",
+        ReasonCode::TypeMismatch => "Type mismatch",
+        ReasonCode::StringTooLong => "String is too long",
+        ReasonCode::ReadOnly => "Read only",
+        ReasonCode::UnableToRegisterResolve => "Unable to register the host name lookup",
+        ReasonCode::InvalidInstance => "Invalid instance",
+        ReasonCode::VersionMismatch => "Version mismatch",
+        ReasonCode::InBufferFull => "Incoming buffer is full",
+        ReasonCode::ProtocolError => "Protocol error",
+        ReasonCode::QueryInProgress => "Query already in progress",
+        ReasonCode::WorldFull => "World is full",
+        ReasonCode::Ejected => "Ejected",
+        ReasonCode::NotWelcome => "Not welcome",
+        ReasonCode::UnableToBind => "Unable to bind",
+        ReasonCode::UnableToListen => "Unable to listen",
+        ReasonCode::UnableToAccept => "Unable to accept",
+        ReasonCode::ConnectionLost => "Connection lost",
+        ReasonCode::NoStream => "No stream",
+        ReasonCode::NotAvailable => "Not available",
+        ReasonCode::OldUniverse => "Universe is too old",
+        ReasonCode::OldWorld => "World server is too old",
+        ReasonCode::WorldNotRunning => "World is not running",
+        ReasonCode::CantResolveUniverseHost => "Cannot resolve the universe host name",
+        ReasonCode::InvalidArgument => "Invalid argument",
+        ReasonCode::UnableToUpdateCav => "Unable to update custom avatar",
+        ReasonCode::UnableToDeleteCav => "Unable to delete custom avatar",
+        ReasonCode::NoSuchCav => "No such custom avatar",
+        ReasonCode::NoCavTemplate => "No custom avatar template",
+        ReasonCode::UnableToGetContacts => "Unable to get contacts",
+        ReasonCode::WorldInstanceAlreadyExists => "World instance already exists",
+        ReasonCode::WorldInstanceInvalid => "Invalid world instance",
+        ReasonCode::PluginNotAvailable => "Plugin not available",
+        ReasonCode::ContactAddBlocked => "Contact request was blocked",
+        ReasonCode::EmailChangeNotAllowed => "Changing the email address is not allowed",
+        ReasonCode::NameChangeNotAllowed => "Changing the name is not allowed",
+        ReasonCode::EmailAlreadyUsed => "Email address is already used",
+        ReasonCode::EmailNotAllowed => "Email address is not allowed",
+        ReasonCode::WorldRedirect => "Redirected to another world",
+        ReasonCode::DatabaseError => "Database error",
+        ReasonCode::NoDatabase => "No database",
+        ReasonCode::ZBufError => "Compressed data buffer error",
+        ReasonCode::ZMemError => "Out of memory while decompressing data",
+        ReasonCode::ZDataError => "Compressed data is corrupt",
+    }
+}
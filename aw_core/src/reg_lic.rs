@@ -55,6 +55,12 @@ impl AWRegLic {
     }
 }
 
+/// Longest name that fits in a license, leaving room for the null terminator.
+pub const MAX_LICENSE_NAME_LENGTH: usize = 0x1F;
+
+/// Name given to licenses that do not set one.
+pub const DEFAULT_LICENSE_NAME: &str = "aw";
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AWRegLicData {
     license_version: u32,
@@ -185,6 +191,73 @@ impl AWRegLicData {
     }
 }
 
+/// Fields of a license to set. Fields that are not set keep the value of
+/// [`AWRegLicData::default`], apart from the name, which is
+/// [`DEFAULT_LICENSE_NAME`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AWRegLicFields {
+    pub license_version: Option<u32>,
+    pub ip_address: Option<Ipv4Addr>,
+    pub port: Option<u32>,
+    pub land_limit: Option<u32>,
+    pub max_users: Option<u32>,
+    pub world_limit: Option<u32>,
+    pub expiration_time: Option<i32>,
+    pub major_version: Option<u16>,
+    pub minor_version: Option<u16>,
+    pub name: Option<String>,
+    pub can_have_bots: Option<bool>,
+}
+
+impl AWRegLicFields {
+    /// Build the license data, refusing names that do not fit in a license.
+    pub fn build(&self) -> Result<AWRegLicData, String> {
+        let mut data = AWRegLicData::default().set_name(DEFAULT_LICENSE_NAME);
+
+        if let Some(license_version) = self.license_version {
+            data = data.set_license_version(license_version);
+        }
+        if let Some(ip_address) = &self.ip_address {
+            data = data.set_ip_address(ip_address);
+        }
+        if let Some(port) = self.port {
+            data = data.set_port(port);
+        }
+        if let Some(land_limit) = self.land_limit {
+            data = data.set_land_limit(land_limit);
+        }
+        if let Some(max_users) = self.max_users {
+            data = data.set_max_users(max_users);
+        }
+        if let Some(world_limit) = self.world_limit {
+            data = data.set_world_limit(world_limit);
+        }
+        if let Some(expiration_time) = self.expiration_time {
+            data = data.set_expiration_time(expiration_time);
+        }
+        if let Some(major_version) = self.major_version {
+            data = data.set_major_version(major_version);
+        }
+        if let Some(minor_version) = self.minor_version {
+            data = data.set_minor_version(minor_version);
+        }
+        if let Some(name) = &self.name {
+            if name.len() > MAX_LICENSE_NAME_LENGTH {
+                return Err(format!(
+                    "Name {name:?} is {} bytes long, but at most {MAX_LICENSE_NAME_LENGTH} fit in a license.",
+                    name.len()
+                ));
+            }
+            data = data.set_name(name);
+        }
+        if let Some(can_have_bots) = self.can_have_bots {
+            data = data.set_can_have_bots(can_have_bots);
+        }
+
+        Ok(data)
+    }
+}
+
 impl Default for AWRegLicData {
     fn default() -> Self {
         Self {
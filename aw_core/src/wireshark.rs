@@ -0,0 +1,21 @@
+//! Wireshark support for the AW protocol.
+
+/// Lua dissector for the AW protocol, with its packet type, variable, data type
+/// and reason code tables generated from this crate's enums at build time.
+///
+/// It decrypts captured traffic using a [`crate::KeyLog`].
+pub const DISSECTOR: &str = include_str!(concat!(env!("OUT_DIR"), "/aw.lua"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dissector_has_generated_tables() {
+        assert!(!DISSECTOR.contains("@GENERATED_TABLES@"));
+        assert!(DISSECTOR.contains("local packet_types = {\n    [1] = \"PublicKeyResponse\","));
+        assert!(DISSECTOR.contains("    [2] = \"StreamKeyResponse\","));
+        assert!(DISSECTOR.contains("local data_types = {\n    [0] = \"Unknown\","));
+        assert!(DISSECTOR.contains("local reason_codes = {\n    [0] = \"Success\","));
+    }
+}
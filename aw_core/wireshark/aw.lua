@@ -0,0 +1,425 @@
+-- Wireshark dissector for the Active Worlds protocol.
+--
+-- aw_core's build script fills in the packet type, variable, data type and
+-- reason code tables below from the Rust sources. Print the finished
+-- dissector with `cargo run -p aw_core --example wireshark_dissector` and copy
+-- it into Wireshark's personal Lua plugins folder.
+--
+-- Traffic is encrypted after each side sends its StreamKeyResponse. To decrypt
+-- it, point the "Key log file" preference at a key log written by a universe
+-- with `key_log_file` set. Decrypting AWCryptAES traffic needs Wireshark's
+-- Gcrypt Lua bindings.
+
+local aw = Proto("aw", "Active Worlds")
+
+-- @GENERATED_TABLES@
+
+-- Payload size of the data types with a fixed size; the others use the size
+-- in the variable header.
+local fixed_sizes = { Byte = 1, Int = 4, Float = 4 }
+
+local TAG_HEADER_LENGTH = 10
+local COMPRESSED_OPCODE = -1
+
+local function lookup(names, wanted)
+    for value, name in pairs(names) do
+        if name == wanted then
+            return value
+        end
+    end
+end
+
+local STREAM_KEY_RESPONSE = lookup(packet_types, "StreamKeyResponse")
+local REASON_CODE_VAR = lookup(var_ids, "ReasonCode")
+
+local f = {
+    length = ProtoField.uint16("aw.length", "Length"),
+    header_0 = ProtoField.uint16("aw.header_0", "Header 0", base.HEX),
+    opcode = ProtoField.int16("aw.opcode", "Packet type", base.DEC, packet_types),
+    header_1 = ProtoField.uint16("aw.header_1", "Header 1", base.HEX),
+    var_count = ProtoField.uint16("aw.var_count", "Variable count"),
+    compressed = ProtoField.bytes("aw.compressed", "Compressed data"),
+    var = ProtoField.none("aw.var", "Variable"),
+    var_id = ProtoField.uint16("aw.var.id", "ID", base.DEC, var_ids),
+    var_type = ProtoField.uint8("aw.var.type", "Data type", base.DEC, data_types),
+    var_size = ProtoField.uint16("aw.var.size", "Size"),
+    var_byte = ProtoField.uint8("aw.var.byte", "Byte"),
+    var_int = ProtoField.int32("aw.var.int", "Int"),
+    var_float = ProtoField.float("aw.var.float", "Float"),
+    var_string = ProtoField.string("aw.var.string", "String"),
+    var_data = ProtoField.bytes("aw.var.data", "Data"),
+    reason_code = ProtoField.int32("aw.reason_code", "Reason code", base.DEC, reason_codes),
+}
+aw.fields = f
+
+local e_no_key = ProtoExpert.new("aw.no_key", "Encrypted, and no stream key is known for this direction",
+    expert.group.DECRYPTION, expert.severity.NOTE)
+local e_bad_var = ProtoExpert.new("aw.bad_var", "Variable runs past the end of the packet",
+    expert.group.MALFORMED, expert.severity.ERROR)
+local e_bad_zlib = ProtoExpert.new("aw.bad_zlib", "Could not decompress packet",
+    expert.group.MALFORMED, expert.severity.ERROR)
+aw.experts = { e_no_key, e_bad_var, e_bad_zlib }
+
+aw.prefs.ports = Pref.range("TCP ports", "6670,5670", "Ports of universe and world servers", 65535)
+aw.prefs.key_log = Pref.string("Key log file", "", "Stream key log written by the universe")
+
+local tcp_seq = Field.new("tcp.seq")
+
+local bxor = (bit32 and bit32.bxor) or (bit and bit.bxor)
+    or load("return function(a, b) return a ~ b end")()
+
+---------------------------------------------------------------------------
+-- Stream ciphers
+
+local function key_bytes(hex)
+    local key = {}
+    for pair in hex:gmatch("%x%x") do
+        key[#key + 1] = tonumber(pair, 16)
+    end
+    return key
+end
+
+local function new_a4(key)
+    local s = {}
+    for i = 0, 255 do
+        s[i] = i
+    end
+
+    local j = 0
+    for i = 0, 255 do
+        j = (j + s[i] + key[(i % #key) + 1]) % 256
+        s[i], s[j] = s[j], s[i]
+    end
+
+    local a, b = 0, 0
+    return function(data)
+        for n = 0, data:len() - 1 do
+            a = (a + 1) % 256
+            b = (b + s[a]) % 256
+            s[a], s[b] = s[b], s[a]
+            data:set_index(n, bxor(data:get_index(n), s[(s[a] + s[b]) % 256]))
+        end
+        return data
+    end
+end
+
+local function new_aes(key)
+    if not GcryptCipher then
+        return nil
+    end
+
+    -- The AES key is every byte of the stream key summed from the end,
+    -- wrapping around 32 bytes, and the IV is bytes 16 to 31.
+    local aes_key = ByteArray.new()
+    aes_key:set_size(32)
+    for i = 0, #key - 1 do
+        local index = i % 32
+        aes_key:set_index(index, (aes_key:get_index(index) + key[#key - i]) % 256)
+    end
+
+    local iv = ByteArray.new()
+    iv:set_size(16)
+    for i = 0, 15 do
+        iv:set_index(i, key[17 + i])
+    end
+
+    local cipher = GcryptCipher.open(GCRY_CIPHER_AES256, GCRY_CIPHER_MODE_OFB, 0)
+    cipher:setkey(aes_key)
+    cipher:setiv(iv)
+
+    return function(data)
+        return cipher:decrypt(nil, data) or data
+    end
+end
+
+local key_log = nil
+local key_log_path = nil
+
+-- Stream keys by "source destination", as written by aw_core's KeyLog.
+local function load_key_log()
+    if key_log_path == aw.prefs.key_log and key_log then
+        return key_log
+    end
+
+    key_log = {}
+    key_log_path = aw.prefs.key_log
+    if key_log_path == "" then
+        return key_log
+    end
+
+    local file = io.open(key_log_path, "r")
+    if not file then
+        return key_log
+    end
+
+    for line in file:lines() do
+        local source, destination, cipher, hex = line:match("^AW_STREAM_KEY (%S+) (%S+) (%S+) (%x+)")
+        if source then
+            key_log[source .. " " .. destination] = { cipher = cipher, key = key_bytes(hex) }
+        end
+    end
+    file:close()
+
+    return key_log
+end
+
+local function socket_name(address, port)
+    local name = tostring(address)
+    if name:find(":") then
+        name = "[" .. name .. "]"
+    end
+    return name .. ":" .. port
+end
+
+local function new_cipher(pinfo)
+    local entry = load_key_log()[socket_name(pinfo.src, pinfo.src_port) .. " " .. socket_name(pinfo.dst, pinfo.dst_port)]
+    if not entry then
+        return nil
+    end
+
+    if entry.cipher == "A4" then
+        return new_a4(entry.key)
+    elseif entry.cipher == "AES" then
+        return new_aes(entry.key)
+    end
+end
+
+---------------------------------------------------------------------------
+-- Reassembly
+--
+-- The stream ciphers carry state from one segment to the next, so each
+-- direction is decrypted and split into AW packets in order on the first
+-- pass. The complete packets ending in each frame are kept for display.
+
+local directions = {}
+local frames = {}
+
+function aw.init()
+    directions = {}
+    frames = {}
+    key_log = nil
+end
+
+local function u16(data, offset)
+    return data:get_index(offset) * 256 + data:get_index(offset + 1)
+end
+
+local function new_direction()
+    return { plain = ByteArray.new(), encrypted = false, cipher = nil, dead = false, next_seq = nil }
+end
+
+local function process_segment(tvb, pinfo)
+    local id = socket_name(pinfo.src, pinfo.src_port) .. " " .. socket_name(pinfo.dst, pinfo.dst_port)
+    local dir = directions[id]
+    if not dir then
+        dir = new_direction()
+        directions[id] = dir
+    end
+
+    local record = { packets = ByteArray.new(), no_key = false }
+    frames[pinfo.number] = record
+
+    if dir.dead then
+        record.no_key = true
+        return
+    end
+
+    -- Skip retransmitted data, which would throw the stream cipher off
+    local seq = tcp_seq()
+    if seq then
+        seq = seq.value
+        if dir.next_seq and seq < dir.next_seq then
+            return
+        end
+        dir.next_seq = seq + tvb:len()
+    end
+
+    local data = tvb:bytes()
+    if dir.encrypted then
+        data = dir.cipher(data)
+    end
+    dir.plain:append(data)
+
+    while dir.plain:len() >= TAG_HEADER_LENGTH do
+        local length = u16(dir.plain, 0)
+        if length < TAG_HEADER_LENGTH or length > dir.plain:len() then
+            break
+        end
+
+        local opcode = u16(dir.plain, 4)
+        if opcode >= 0x8000 then
+            opcode = opcode - 0x10000
+        end
+
+        record.packets:append(dir.plain:subset(0, length))
+        local rest = dir.plain:len() - length
+        if rest > 0 then
+            dir.plain = dir.plain:subset(length, rest)
+        else
+            dir.plain = ByteArray.new()
+        end
+
+        -- Everything after the first StreamKeyResponse is encrypted
+        if opcode == STREAM_KEY_RESPONSE and not dir.encrypted then
+            dir.encrypted = true
+            dir.cipher = new_cipher(pinfo)
+            if not dir.cipher then
+                dir.dead = true
+                record.no_key = true
+                return
+            end
+            if dir.plain:len() > 0 then
+                dir.plain = dir.cipher(dir.plain)
+            end
+        end
+    end
+end
+
+---------------------------------------------------------------------------
+-- Packets
+
+local function dissect_var(tvb, offset, tree, pinfo, packet_end)
+    if offset + 4 > packet_end then
+        tree:add_proto_expert_info(e_bad_var)
+        return packet_end
+    end
+
+    local id = tvb(offset, 2):uint()
+    local type_and_size = tvb(offset + 2, 2):uint()
+    local data_type = math.floor(type_and_size / 4096)
+    local size = fixed_sizes[data_types[data_type]] or (type_and_size % 4096)
+
+    local var_end = offset + 4 + size
+    if var_end > packet_end then
+        tree:add_proto_expert_info(e_bad_var)
+        return packet_end
+    end
+
+    local name = var_ids[id] or tostring(id)
+    local var_tree = tree:add(f.var, tvb(offset, 4 + size)):set_text(name)
+    var_tree:add(f.var_id, tvb(offset, 2))
+    var_tree:add(f.var_type, tvb(offset + 2, 1), data_type)
+    var_tree:add(f.var_size, tvb(offset + 2, 2), type_and_size % 4096)
+
+    local payload = tvb(offset + 4, size)
+    local kind = data_types[data_type]
+    if kind == "Byte" then
+        var_tree:add(f.var_byte, payload)
+        var_tree:append_text(" = " .. payload:uint())
+    elseif kind == "Int" then
+        var_tree:add_le(f.var_int, payload)
+        var_tree:append_text(" = " .. payload:le_int())
+        if id == REASON_CODE_VAR then
+            var_tree:add_le(f.reason_code, payload)
+            local reason = reason_codes[payload:le_int()]
+            if reason then
+                var_tree:append_text(" (" .. reason .. ")")
+            end
+        end
+    elseif kind == "Float" then
+        var_tree:add_le(f.var_float, payload)
+        var_tree:append_text(" = " .. payload:le_float())
+    elseif kind == "String" and size > 0 then
+        local text = payload:stringz(ENC_ISO_8859_1)
+        var_tree:add(f.var_string, payload, text)
+        var_tree:append_text(" = \"" .. text .. "\"")
+    elseif size > 0 then
+        var_tree:add(f.var_data, payload)
+    end
+
+    return var_end
+end
+
+local dissect_packets
+
+local function dissect_packet(tvb, offset, tree, pinfo)
+    local length = tvb(offset, 2):uint()
+    local opcode = tvb(offset + 4, 2):int()
+    local header_1 = tvb(offset + 6, 2):uint()
+    local var_count = tvb(offset + 8, 2):uint()
+
+    local packet_tree = tree:add(aw, tvb(offset, length))
+    packet_tree:add(f.length, tvb(offset, 2))
+    packet_tree:add(f.header_0, tvb(offset + 2, 2))
+    packet_tree:add(f.opcode, tvb(offset + 4, 2))
+    packet_tree:add(f.header_1, tvb(offset + 6, 2))
+    packet_tree:add(f.var_count, tvb(offset + 8, 2))
+
+    if opcode == COMPRESSED_OPCODE and header_1 ~= 0 then
+        packet_tree:set_text("Compressed packets")
+        local compressed = tvb(offset + TAG_HEADER_LENGTH, length - TAG_HEADER_LENGTH)
+        packet_tree:add(f.compressed, compressed)
+
+        local ok, inflated = pcall(function()
+            return compressed:uncompress("Decompressed AW")
+        end)
+        if ok and inflated then
+            dissect_packets(inflated, packet_tree, pinfo)
+        else
+            packet_tree:add_proto_expert_info(e_bad_zlib)
+        end
+        return
+    end
+
+    local name = packet_types[opcode] or tostring(opcode)
+    packet_tree:set_text("AW " .. name)
+    pinfo.cols.info:append(name .. " ")
+
+    local var_offset = offset + TAG_HEADER_LENGTH
+    for _ = 1, var_count do
+        var_offset = dissect_var(tvb, var_offset, packet_tree, pinfo, offset + length)
+    end
+end
+
+dissect_packets = function(tvb, tree, pinfo)
+    local offset = 0
+    while offset + TAG_HEADER_LENGTH <= tvb:len() do
+        local length = tvb(offset, 2):uint()
+        if length < TAG_HEADER_LENGTH or offset + length > tvb:len() then
+            return
+        end
+        dissect_packet(tvb, offset, tree, pinfo)
+        offset = offset + length
+    end
+end
+
+function aw.dissector(tvb, pinfo, tree)
+    if tvb:len() == 0 then
+        return 0
+    end
+
+    pinfo.cols.protocol = aw.name
+    pinfo.cols.info:clear()
+
+    if not pinfo.visited then
+        process_segment(tvb, pinfo)
+    end
+
+    local record = frames[pinfo.number]
+    if not record then
+        return tvb:len()
+    end
+
+    if record.packets:len() > 0 then
+        dissect_packets(record.packets:tvb("AW stream"), tree, pinfo)
+    end
+
+    if record.no_key then
+        tree:add(aw, tvb()):set_text("Encrypted AW data"):add_proto_expert_info(e_no_key)
+    end
+
+    return tvb:len()
+end
+
+local registered_ports = nil
+
+function aw.prefs_changed()
+    local tcp_port = DissectorTable.get("tcp.port")
+    if registered_ports then
+        tcp_port:remove(registered_ports, aw)
+    end
+    registered_ports = aw.prefs.ports
+    tcp_port:add(registered_ports, aw)
+end
+
+aw.prefs_changed()
@@ -0,0 +1,182 @@
+use std::{
+    fs::{File, OpenOptions},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use fs2::FileExt;
+use rusqlite::{backup::Backup, Connection, DatabaseName, OpenFlags};
+
+use crate::{Backend, Database, DatabaseBackupError};
+
+/// Number of pages copied per backup step. Small steps let other users of the
+/// source database get a word in while a snapshot is being taken.
+const PAGES_PER_STEP: i32 = 128;
+const PAUSE_BETWEEN_STEPS: Duration = Duration::from_millis(1);
+
+/// Advisory lock held next to an internal database file for as long as a
+/// process has the database open. Offline tools such as restore check it
+/// before touching the file.
+#[derive(Debug)]
+pub struct SqliteLock {
+    file: File,
+}
+
+impl SqliteLock {
+    /// Lock the database at `db_path`, failing immediately if another
+    /// process already holds it.
+    pub fn acquire(db_path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(db_path.as_ref()))?;
+        file.try_lock_exclusive()?;
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for SqliteLock {
+    fn drop(&mut self) {
+        // The lock file itself is left behind; removing it would race with
+        // another process that has just opened it.
+        self.file.unlock().ok();
+    }
+}
+
+/// Path of the lock file belonging to the database at `db_path`.
+pub fn lock_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+impl Database {
+    /// Write a consistent snapshot of the open database to `dest` using
+    /// SQLite's online backup API. Only supported for internal databases.
+    pub fn backup_to(&self, dest: impl AsRef<Path>) -> Result<(), DatabaseBackupError> {
+        match &self.backend {
+            Backend::External { .. } => Err(DatabaseBackupError::Unsupported),
+            Backend::Internal { conn, .. } => copy_database(conn, dest.as_ref()),
+        }
+    }
+}
+
+/// Snapshot the internal database at `source` into `dest` without taking its
+/// lock, so this is safe to run while a universe has the database open.
+pub fn backup_sqlite(
+    source: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+) -> Result<(), DatabaseBackupError> {
+    let conn = Connection::open_with_flags(
+        source,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+
+    copy_database(&conn, dest.as_ref())
+}
+
+/// Replace the internal database at `target` with the contents of the
+/// snapshot at `snapshot`. Refuses to run while another process holds the
+/// database open.
+pub fn restore_sqlite(
+    snapshot: impl AsRef<Path>,
+    target: impl AsRef<Path>,
+) -> Result<(), DatabaseBackupError> {
+    let snapshot = snapshot.as_ref();
+    if !snapshot.is_file() {
+        return Err(DatabaseBackupError::MissingSnapshot(
+            snapshot.display().to_string(),
+        ));
+    }
+
+    let _lock = SqliteLock::acquire(target.as_ref()).map_err(DatabaseBackupError::InUse)?;
+
+    let source = Connection::open_with_flags(snapshot, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut dest = Connection::open(target)?;
+    Backup::new(&source, &mut dest)?.run_to_completion(
+        PAGES_PER_STEP,
+        PAUSE_BETWEEN_STEPS,
+        None,
+    )?;
+
+    Ok(())
+}
+
+fn copy_database(conn: &Connection, dest: &Path) -> Result<(), DatabaseBackupError> {
+    if dest.exists() {
+        return Err(DatabaseBackupError::DestinationExists(
+            dest.display().to_string(),
+        ));
+    }
+
+    let mut dest_conn = Connection::open(dest)?;
+    Backup::new_with_names(conn, DatabaseName::Main, &mut dest_conn, DatabaseName::Main)?
+        .run_to_completion(PAGES_PER_STEP, PAUSE_BETWEEN_STEPS, None)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{aw_params, DatabaseConfig, DatabaseType, MysqlConfig, SqliteConfig};
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aw_db_{name}_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn open_internal(path: &Path) -> Database {
+        Database::new(DatabaseConfig {
+            database_type: DatabaseType::Internal,
+            mysql_config: MysqlConfig {
+                hostname: String::new(),
+                port: 0,
+                username: String::new(),
+                password: String::new(),
+                database: String::new(),
+            },
+            sqlite_config: SqliteConfig {
+                path: path.display().to_string(),
+            },
+            slow_query_ms: 0,
+        })
+        .unwrap()
+    }
+
+    fn count_rows(db: &Database) -> usize {
+        match db.exec("SELECT * FROM t", vec![]) {
+            crate::DatabaseResult::Ok(rows) => rows.len(),
+            crate::DatabaseResult::DatabaseError => panic!("query failed"),
+        }
+    }
+
+    #[test]
+    fn backup_and_restore() {
+        let dir = scratch_dir("backup_and_restore");
+        let db_path = dir.join("universe.db");
+        let snapshot = dir.join("snapshot.db");
+
+        let db = open_internal(&db_path);
+        assert!(!db.exec("CREATE TABLE t (x INTEGER)", vec![]).is_err());
+        assert!(!db.exec("INSERT INTO t VALUES (?)", aw_params!(1)).is_err());
+        db.backup_to(&snapshot).unwrap();
+        assert!(!db.exec("INSERT INTO t VALUES (?)", aw_params!(2)).is_err());
+
+        // The database is still open, so restoring over it must be refused.
+        assert!(matches!(
+            restore_sqlite(&snapshot, &db_path),
+            Err(DatabaseBackupError::InUse(_))
+        ));
+        drop(db);
+
+        restore_sqlite(&snapshot, &db_path).unwrap();
+        assert_eq!(count_rows(&open_internal(&db_path)), 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
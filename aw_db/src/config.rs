@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Internal (sqlite, local, on-disk, self contained) database or external (server) database
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum DatabaseType {
     External,
     #[default]
@@ -28,6 +28,14 @@ pub struct MysqlConfig {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct DatabaseConfig {
     pub database_type: DatabaseType,
+    /// Statements taking at least this many milliseconds are logged as slow. 0 disables the log.
+    // Plain values must come before the tables below for the config to serialize as TOML.
+    #[serde(default = "default_slow_query_ms")]
+    pub slow_query_ms: u64,
     pub mysql_config: MysqlConfig,
     pub sqlite_config: SqliteConfig,
 }
+
+fn default_slow_query_ms() -> u64 {
+    100
+}
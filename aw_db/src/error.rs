@@ -4,6 +4,28 @@ pub enum DatabaseOpenError {
     ConnectionPoolFailure(#[from] mysql::Error),
     #[error("Couldn't open the Sqlite database: {0}")]
     SqliteOpenFailure(#[from] rusqlite::Error),
+    #[error("The Sqlite database is in use by another process: {0}")]
+    SqliteInUse(std::io::Error),
+    #[error("The MySQL database {0} is in use by another process")]
+    MysqlInUse(String),
+    #[error("Couldn't lock the MySQL database: {0}")]
+    MysqlLockFailure(DatabaseExecError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DatabaseBackupError {
+    #[error("Backups are only supported for the internal database")]
+    Unsupported,
+    #[error("The database is in use by another process: {0}")]
+    InUse(std::io::Error),
+    #[error("The backup destination {0} already exists")]
+    DestinationExists(String),
+    #[error("The snapshot {0} does not exist")]
+    MissingSnapshot(String),
+    #[error("Encountered sqlite error {0}")]
+    SqliteError(#[from] rusqlite::Error),
+    #[error("Encountered I/O error {0}")]
+    Io(std::io::Error),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -1,42 +1,124 @@
+mod backup;
 mod config;
 mod error;
+mod metrics;
 mod mysql_wrap;
 mod row;
 mod sqlite_wrap;
 
-use mysql_wrap::mysql_exec;
+use std::{
+    cell::{Cell, RefCell},
+    time::{Duration, Instant},
+};
 
+use mysql::prelude::Queryable;
+use mysql_wrap::{mysql_exec, mysql_exec_on, MysqlLock};
+
+pub use backup::{backup_sqlite, restore_sqlite, SqliteLock};
 pub use config::{DatabaseConfig, DatabaseType, MysqlConfig, SqliteConfig};
-pub use error::{DatabaseExecError, DatabaseOpenError, DatabaseResult};
+pub use error::{DatabaseBackupError, DatabaseExecError, DatabaseOpenError, DatabaseResult};
+pub use metrics::{QueryMetrics, StatementStats, LATENCY_BUCKETS};
 pub use row::Row;
 
-pub enum Database {
-    External { pool: mysql::Pool },
-    Internal { conn: rusqlite::Connection },
+pub struct Database {
+    backend: Backend,
+    metrics: QueryMetrics,
+    slow_query_threshold: Duration,
+    /// Number of nested [`Database::transaction`] calls currently running
+    transaction_depth: Cell<u32>,
+    /// Whether a nested transaction failed, so the outermost one rolls back
+    transaction_failed: Cell<bool>,
+}
+
+enum Backend {
+    External {
+        pool: mysql::Pool,
+        /// Connection holding the open transaction, which every statement
+        /// uses until the transaction ends
+        transaction: RefCell<Option<mysql::PooledConn>>,
+        /// Held for as long as the database is open, unless it is read-only
+        _lock: Option<MysqlLock>,
+    },
+    Internal {
+        conn: rusqlite::Connection,
+        /// Held for as long as the connection is open, unless it is read-only
+        _lock: Option<SqliteLock>,
+    },
+}
+
+fn mysql_pool(config: &MysqlConfig) -> Result<mysql::Pool, DatabaseOpenError> {
+    let username = &config.username;
+    let password = &config.password;
+    let hostname = &config.hostname;
+    let port = &config.port;
+    let database_name = &config.database;
+    let uri = format!("mysql://{username}:{password}@{hostname}:{port}/{database_name}");
+
+    Ok(mysql::Pool::new(uri.as_str())?)
 }
 
 impl Database {
     pub fn new(config: DatabaseConfig) -> Result<Self, DatabaseOpenError> {
-        let db = match config.database_type {
+        let backend = match config.database_type {
             DatabaseType::External => {
-                let username = &config.mysql_config.username;
-                let password = &config.mysql_config.password;
-                let hostname = &config.mysql_config.hostname;
-                let port = &config.mysql_config.port;
+                let pool = mysql_pool(&config.mysql_config)?;
                 let database_name = &config.mysql_config.database;
-                let uri =
-                    format!("mysql://{username}:{password}@{hostname}:{port}/{database_name}");
+                let lock = MysqlLock::acquire(&pool, database_name)
+                    .map_err(DatabaseOpenError::MysqlLockFailure)?
+                    .ok_or_else(|| DatabaseOpenError::MysqlInUse(database_name.clone()))?;
+
+                Backend::External {
+                    pool,
+                    transaction: RefCell::new(None),
+                    _lock: Some(lock),
+                }
+            }
+            DatabaseType::Internal => {
+                let lock = SqliteLock::acquire(&config.sqlite_config.path)
+                    .map_err(DatabaseOpenError::SqliteInUse)?;
 
-                Self::External {
-                    pool: mysql::Pool::new(uri.as_str())?,
+                Backend::Internal {
+                    conn: rusqlite::Connection::open(config.sqlite_config.path)?,
+                    _lock: Some(lock),
                 }
             }
-            DatabaseType::Internal => Self::Internal {
-                conn: rusqlite::Connection::open(config.sqlite_config.path)?,
+        };
+
+        Ok(Self {
+            backend,
+            metrics: QueryMetrics::default(),
+            slow_query_threshold: Duration::from_millis(config.slow_query_ms),
+            transaction_depth: Cell::new(0),
+            transaction_failed: Cell::new(false),
+        })
+    }
+
+    /// Open a database for reading alongside a running universe, without
+    /// taking its lock. The internal database is also opened read-only.
+    pub fn open_read_only(config: DatabaseConfig) -> Result<Self, DatabaseOpenError> {
+        let backend = match config.database_type {
+            DatabaseType::External => Backend::External {
+                pool: mysql_pool(&config.mysql_config)?,
+                transaction: RefCell::new(None),
+                _lock: None,
+            },
+            DatabaseType::Internal => Backend::Internal {
+                conn: rusqlite::Connection::open_with_flags(
+                    config.sqlite_config.path,
+                    rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY
+                        | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                )?,
+                _lock: None,
             },
         };
 
-        Ok(db)
+        Ok(Self {
+            backend,
+            metrics: QueryMetrics::default(),
+            slow_query_threshold: Duration::from_millis(config.slow_query_ms),
+            transaction_depth: Cell::new(0),
+            transaction_failed: Cell::new(false),
+        })
     }
 
     #[must_use]
@@ -51,13 +133,22 @@ impl Database {
             &parameters
         );
 
-        let res = match &self {
-            Database::External { pool } => mysql_exec(pool, statement.as_ref(), parameters.clone()),
-            Database::Internal { conn } => {
+        let started = Instant::now();
+        let res = match &self.backend {
+            Backend::External {
+                pool, transaction, ..
+            } => match transaction.borrow_mut().as_mut() {
+                Some(conn) => mysql_exec_on(conn, statement.as_ref(), parameters.clone()),
+                None => mysql_exec(pool, statement.as_ref(), parameters.clone()),
+            },
+            Backend::Internal { conn, .. } => {
                 sqlite_wrap::sqlite_exec(conn, statement.as_ref(), parameters.clone())
             }
         };
 
+        let elapsed = started.elapsed();
+        self.record_timing(statement.as_ref(), parameters.len(), elapsed, res.is_err());
+
         match res {
             Ok(rows) => DatabaseResult::Ok(rows),
             Err(why) => {
@@ -70,19 +161,120 @@ impl Database {
         }
     }
 
+    /// Run `f` in a transaction, committing what it wrote if it succeeds and
+    /// rolling it back if it fails. Transactions started inside `f` are part
+    /// of this one, and a failure in any of them rolls the whole one back.
+    pub fn transaction<T>(&self, f: impl FnOnce() -> DatabaseResult<T>) -> DatabaseResult<T> {
+        let depth = self.transaction_depth.get();
+        if depth > 0 {
+            self.transaction_depth.set(depth + 1);
+            let r = f();
+            self.transaction_depth.set(depth);
+            if r.is_err() {
+                self.transaction_failed.set(true);
+            }
+            return r;
+        }
+
+        if let Err(why) = self.begin() {
+            log::error!("Could not begin a transaction: {why:?}");
+            return DatabaseResult::DatabaseError;
+        }
+
+        self.transaction_depth.set(1);
+        self.transaction_failed.set(false);
+        let r = f();
+        self.transaction_depth.set(0);
+
+        let ended = if r.is_err() || self.transaction_failed.get() {
+            self.end("ROLLBACK").map(|_| false)
+        } else {
+            self.end("COMMIT").map(|_| true)
+        };
+
+        match ended {
+            Ok(true) => r,
+            Ok(false) => {
+                log::warn!("Rolled back a transaction that failed part way through");
+                DatabaseResult::DatabaseError
+            }
+            Err(why) => {
+                log::error!("Could not end a transaction: {why:?}");
+                DatabaseResult::DatabaseError
+            }
+        }
+    }
+
+    fn begin(&self) -> Result<(), DatabaseExecError> {
+        match &self.backend {
+            Backend::External {
+                pool, transaction, ..
+            } => {
+                let mut conn = pool.get_conn()?;
+                conn.query_drop("START TRANSACTION")?;
+                *transaction.borrow_mut() = Some(conn);
+            }
+            Backend::Internal { conn, .. } => conn.execute_batch("BEGIN")?,
+        }
+        Ok(())
+    }
+
+    /// Commit or roll back the open transaction.
+    fn end(&self, statement: &str) -> Result<(), DatabaseExecError> {
+        match &self.backend {
+            Backend::External { transaction, .. } => {
+                // The connection goes back to the pool even if this fails
+                if let Some(mut conn) = transaction.borrow_mut().take() {
+                    conn.query_drop(statement)?;
+                }
+            }
+            Backend::Internal { conn, .. } => conn.execute_batch(statement)?,
+        }
+        Ok(())
+    }
+
+    pub fn database_type(&self) -> DatabaseType {
+        match &self.backend {
+            Backend::External { .. } => DatabaseType::External,
+            Backend::Internal { .. } => DatabaseType::Internal,
+        }
+    }
+
     pub fn auto_increment_not_null(&self) -> &'static str {
-        match &self {
-            Database::External { .. } => "NOT NULL AUTO_INCREMENT",
-            Database::Internal { .. } => "AUTOINCREMENT NOT NULL",
+        match &self.backend {
+            Backend::External { .. } => "NOT NULL AUTO_INCREMENT",
+            Backend::Internal { .. } => "AUTOINCREMENT NOT NULL",
         }
     }
 
     pub fn unsigned_str(&self) -> &'static str {
-        match &self {
-            Database::External { .. } => "unsigned",
-            Database::Internal { .. } => "",
+        match &self.backend {
+            Backend::External { .. } => "unsigned",
+            Backend::Internal { .. } => "",
         }
     }
+
+    /// Execution statistics per statement shape, slowest in total first.
+    pub fn query_metrics(&self) -> Vec<(String, StatementStats)> {
+        self.metrics.snapshot()
+    }
+
+    fn record_timing(
+        &self,
+        statement: &str,
+        parameter_count: usize,
+        elapsed: Duration,
+        failed: bool,
+    ) {
+        let shape = metrics::statement_shape(statement);
+
+        // Only the number of parameters is logged, since they may contain passwords.
+        if !self.slow_query_threshold.is_zero() && elapsed >= self.slow_query_threshold {
+            log::warn!("Slow query took {elapsed:?} with {parameter_count} parameters: {shape:?}");
+        }
+
+        self.metrics.record(shape, elapsed, failed);
+    }
 }
 
 #[macro_export]
@@ -97,3 +289,66 @@ macro_rules! aw_params {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_scratch(name: &str) -> (Database, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("aw_db_{name}_{}.db", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let db = Database::new(DatabaseConfig {
+            database_type: DatabaseType::Internal,
+            mysql_config: MysqlConfig {
+                hostname: String::new(),
+                port: 0,
+                username: String::new(),
+                password: String::new(),
+                database: String::new(),
+            },
+            sqlite_config: SqliteConfig {
+                path: path.display().to_string(),
+            },
+            slow_query_ms: 0,
+        })
+        .unwrap();
+        (db, path)
+    }
+
+    fn count_rows(db: &Database) -> usize {
+        match db.exec("SELECT * FROM t", vec![]) {
+            DatabaseResult::Ok(rows) => rows.len(),
+            DatabaseResult::DatabaseError => panic!("query failed"),
+        }
+    }
+
+    #[test]
+    fn failed_transactions_roll_back() {
+        let (db, path) = open_scratch("transactions");
+        assert!(!db.exec("CREATE TABLE t (x INTEGER)", vec![]).is_err());
+
+        let r = db.transaction(|| {
+            assert!(!db.exec("INSERT INTO t VALUES (?)", aw_params!(1)).is_err());
+            db.exec("INSERT INTO missing VALUES (?)", aw_params!(2))
+        });
+        assert!(r.is_err());
+        assert_eq!(count_rows(&db), 0);
+
+        // A nested failure rolls back the outer transaction even if the
+        // outer one carries on
+        let r = db.transaction(|| {
+            assert!(!db.exec("INSERT INTO t VALUES (?)", aw_params!(1)).is_err());
+            let _ = db.transaction(|| db.exec("INSERT INTO missing VALUES (?)", aw_params!(2)));
+            DatabaseResult::Ok(())
+        });
+        assert!(r.is_err());
+        assert_eq!(count_rows(&db), 0);
+
+        let r = db.transaction(|| db.exec("INSERT INTO t VALUES (?)", aw_params!(1)));
+        assert!(!r.is_err());
+        assert_eq!(count_rows(&db), 1);
+
+        drop(db);
+        std::fs::remove_file(&path).ok();
+    }
+}
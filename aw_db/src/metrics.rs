@@ -0,0 +1,126 @@
+use std::{collections::HashMap, fmt, sync::Mutex, time::Duration};
+
+/// Upper bounds of the latency histogram buckets. Anything slower than the
+/// last bound lands in a final overflow bucket.
+pub const LATENCY_BUCKETS: [Duration; 5] = [
+    Duration::from_micros(100),
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+];
+
+/// Timing statistics for every execution of one statement shape.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StatementStats {
+    pub count: u64,
+    pub errors: u64,
+    pub total: Duration,
+    pub max: Duration,
+    /// Number of executions per latency bucket, see [`LATENCY_BUCKETS`].
+    pub histogram: [u64; LATENCY_BUCKETS.len() + 1],
+}
+
+impl StatementStats {
+    fn record(&mut self, elapsed: Duration, failed: bool) {
+        self.count += 1;
+        if failed {
+            self.errors += 1;
+        }
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| elapsed < *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.histogram[bucket] += 1;
+    }
+
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.count) {
+            Ok(0) => Duration::ZERO,
+            Ok(count) => self.total / count,
+            Err(_) => Duration::from_secs_f64(self.total.as_secs_f64() / self.count as f64),
+        }
+    }
+}
+
+impl fmt::Display for StatementStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} calls ({} failed), total {:?}, mean {:?}, max {:?}, histogram {:?}",
+            self.count,
+            self.errors,
+            self.total,
+            self.mean(),
+            self.max,
+            self.histogram
+        )
+    }
+}
+
+/// Per-statement-shape execution statistics for a database.
+#[derive(Debug, Default)]
+pub struct QueryMetrics {
+    statements: Mutex<HashMap<String, StatementStats>>,
+}
+
+impl QueryMetrics {
+    pub fn record(&self, shape: String, elapsed: Duration, failed: bool) {
+        let Ok(mut statements) = self.statements.lock() else {
+            return;
+        };
+
+        statements.entry(shape).or_default().record(elapsed, failed);
+    }
+
+    /// Statistics for every statement shape seen so far, slowest in total first.
+    pub fn snapshot(&self) -> Vec<(String, StatementStats)> {
+        let mut result: Vec<(String, StatementStats)> = match self.statements.lock() {
+            Ok(statements) => statements
+                .iter()
+                .map(|(shape, stats)| (shape.clone(), stats.clone()))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        result.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total));
+        result
+    }
+}
+
+/// Reduce a statement to its shape by collapsing whitespace, so that the same
+/// query written across several lines is counted as one.
+pub fn statement_shape(statement: &str) -> String {
+    statement
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_and_buckets() {
+        let metrics = QueryMetrics::default();
+        let shape = statement_shape("SELECT *\n    FROM awu_citizen   WHERE ID=?");
+        assert_eq!(shape, "SELECT * FROM awu_citizen WHERE ID=?");
+
+        metrics.record(shape.clone(), Duration::from_micros(50), false);
+        metrics.record(shape.clone(), Duration::from_millis(5), true);
+        metrics.record(shape, Duration::from_secs(2), false);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 1);
+
+        let stats = &snapshot[0].1;
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.max, Duration::from_secs(2));
+        assert_eq!(stats.histogram, [1, 0, 1, 0, 0, 1]);
+    }
+}
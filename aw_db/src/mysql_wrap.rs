@@ -8,6 +8,15 @@ pub(crate) fn mysql_exec(
     parameters: Vec<String>,
 ) -> Result<Vec<Row>, DatabaseExecError> {
     let mut conn = pool.get_conn()?;
+    mysql_exec_on(&mut conn, statement, parameters)
+}
+
+/// Execute a statement on one connection, such as one holding a transaction.
+pub(crate) fn mysql_exec_on(
+    conn: &mut mysql::PooledConn,
+    statement: impl AsRef<str>,
+    parameters: Vec<String>,
+) -> Result<Vec<Row>, DatabaseExecError> {
     let r: mysql::Result<Vec<mysql::Row>> = conn.exec(statement.as_ref(), parameters.clone());
 
     Ok(r?
@@ -15,3 +24,46 @@ pub(crate) fn mysql_exec(
         .map(|r| Row::MysqlRow(r.clone()))
         .collect::<Vec<Row>>())
 }
+
+/// Advisory lock on a MySQL database, held by one connection for as long as a
+/// process has the database open. Offline tools check it before changing rows
+/// that a running universe may have cached.
+pub(crate) struct MysqlLock {
+    conn: mysql::PooledConn,
+    name: String,
+}
+
+impl MysqlLock {
+    /// Lock the database, returning `None` if another connection already
+    /// holds the lock.
+    pub(crate) fn acquire(
+        pool: &mysql::Pool,
+        database_name: &str,
+    ) -> Result<Option<Self>, DatabaseExecError> {
+        let mut conn = pool.get_conn()?;
+        // Lock names are shared by every database on the server
+        let name = format!("awu_universe.{database_name}");
+        let rows = mysql_exec_on(
+            &mut conn,
+            "SELECT GET_LOCK(?, 0) AS Locked",
+            vec![name.clone()],
+        )?;
+
+        match rows.first().and_then(|row| row.fetch_int("Locked")) {
+            Some(1) => Ok(Some(Self { conn, name })),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Drop for MysqlLock {
+    fn drop(&mut self) {
+        // The connection goes back to the pool, which would keep the lock
+        mysql_exec_on(
+            &mut self.conn,
+            "SELECT RELEASE_LOCK(?) AS Released",
+            vec![self.name.clone()],
+        )
+        .ok();
+    }
+}
@@ -1,4 +1,4 @@
-use aw_core::encoding::latin1_to_string;
+use aw_core::encoding::decode_stored_text;
 
 use crate::sqlite_wrap::SqliteValue;
 
@@ -30,10 +30,31 @@ impl Row {
         }
     }
 
+    pub fn fetch_float(&self, name: &str) -> Option<f64> {
+        match &self {
+            Row::MysqlRow(row) => match row.get::<mysql::Value, _>(name) {
+                Some(mysql::Value::Float(x)) => Some(f64::from(x)),
+                Some(mysql::Value::Double(x)) => Some(x),
+                _ => None,
+            },
+            Row::SqliteRow { column_names, row } => match column_names
+                .iter()
+                .enumerate()
+                .find(|(_, cname)| *cname == name)
+                .and_then(|(i, _)| row.get(i))
+            {
+                Some(SqliteValue::Float(x)) => Some(*x),
+                // Whole numbers may come back as integers depending on column affinity
+                Some(SqliteValue::Int(x)) => Some(*x as f64),
+                _ => None,
+            },
+        }
+    }
+
     pub fn fetch_string(&self, name: &str) -> Option<String> {
         match &self {
             Row::MysqlRow(row) => match row.get::<mysql::Value, _>(name) {
-                Some(mysql::Value::Bytes(x)) => Some(latin1_to_string(&x)),
+                Some(mysql::Value::Bytes(x)) => Some(decode_stored_text(&x)),
                 _ => None,
             },
             Row::SqliteRow { column_names, row } => match column_names
@@ -6,6 +6,7 @@ use crate::{error::DatabaseExecError, Row};
 pub enum SqliteValue {
     None,
     Int(i64),
+    Float(f64),
     String(String),
 }
 
@@ -30,6 +31,8 @@ pub(crate) fn sqlite_exec(
         for i in 0..column_names.len() {
             if let Ok(num) = row.get::<usize, i64>(i) {
                 row_vec.push(SqliteValue::Int(num))
+            } else if let Ok(num) = row.get::<usize, f64>(i) {
+                row_vec.push(SqliteValue::Float(num))
             } else if let Ok(b) = row.get::<usize, String>(i) {
                 row_vec.push(SqliteValue::String(b))
             } else {
@@ -0,0 +1,230 @@
+//! Derive macros for the types in `aw_core`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Field, Fields, GenericArgument,
+    PathArguments, Type,
+};
+
+/// Derives `aw_core::PacketFields` for a struct with named fields, and
+/// `aw_core::TypedPacket` if the struct declares its packet type.
+///
+/// ```ignore
+/// #[derive(TypedPacket)]
+/// #[aw(packet = PacketType::WorldStop)]
+/// struct WorldStopParams {
+///     #[aw(var = VarID::WorldName)]
+///     world_name: String,
+///     #[aw(var = VarID::ReasonCode)]
+///     reason: Option<i32>,
+///     #[aw(var = VarID::WorldFreeEntry, default)]
+///     free_entry: bool,
+/// }
+/// ```
+///
+/// Fields are required unless they are an `Option`, which is `None` when the
+/// variable is absent, or are marked `default`, which falls back to
+/// `Default::default()`. A field marked `flatten` is decoded from and encoded
+/// into the same packet as the containing struct.
+#[proc_macro_derive(TypedPacket, attributes(aw))]
+pub fn derive_typed_packet(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+enum FieldKind {
+    Required(Expr),
+    Optional(Expr),
+    Default(Expr),
+    Flatten,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "TypedPacket can only be derived for structs",
+        ));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            input.span(),
+            "TypedPacket requires a struct with named fields",
+        ));
+    };
+
+    let mut packet_type = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("aw")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("packet") {
+                packet_type = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `packet = PacketType::...`"))
+            }
+        })?;
+    }
+
+    let mut decoders = Vec::new();
+    let mut encoders = Vec::new();
+    let mut idents = Vec::new();
+
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+
+        let (decode, encode) = match field_kind(field)? {
+            FieldKind::Required(var) => (
+                quote! {
+                    match <#ty as ::aw_core::PacketField>::from_packet(packet, #var) {
+                        Some(x) => x,
+                        None => return Err(::aw_core::TypedPacketError::for_var(packet, #var)),
+                    }
+                },
+                quote! {
+                    ::aw_core::PacketField::add_to_packet(&self.#ident, packet, #var);
+                },
+            ),
+            FieldKind::Optional(var) => {
+                let inner = option_inner(ty).expect("optional field");
+                (
+                    quote! {
+                        match <#inner as ::aw_core::PacketField>::from_packet(packet, #var) {
+                            Some(x) => Some(x),
+                            None if packet.get_var(#var).is_none() => None,
+                            None => return Err(::aw_core::TypedPacketError::Invalid(#var)),
+                        }
+                    },
+                    quote! {
+                        if let Some(x) = &self.#ident {
+                            ::aw_core::PacketField::add_to_packet(x, packet, #var);
+                        }
+                    },
+                )
+            }
+            FieldKind::Default(var) => (
+                quote! {
+                    match <#ty as ::aw_core::PacketField>::from_packet(packet, #var) {
+                        Some(x) => x,
+                        None if packet.get_var(#var).is_none() => ::core::default::Default::default(),
+                        None => return Err(::aw_core::TypedPacketError::Invalid(#var)),
+                    }
+                },
+                quote! {
+                    ::aw_core::PacketField::add_to_packet(&self.#ident, packet, #var);
+                },
+            ),
+            FieldKind::Flatten => (
+                quote! {
+                    <#ty as ::aw_core::PacketFields>::decode(packet)?
+                },
+                quote! {
+                    ::aw_core::PacketFields::encode_into(&self.#ident, packet);
+                },
+            ),
+        };
+
+        decoders.push(quote! { let #ident = #decode; });
+        encoders.push(encode);
+        idents.push(ident);
+    }
+
+    let typed_packet = packet_type.map(|packet_type| {
+        quote! {
+            impl #impl_generics ::aw_core::TypedPacket for #name #ty_generics #where_clause {
+                const PACKET_TYPE: ::aw_core::PacketType = #packet_type;
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::aw_core::PacketFields for #name #ty_generics #where_clause {
+            fn decode(packet: &::aw_core::AWPacket) -> Result<Self, ::aw_core::TypedPacketError> {
+                #(#decoders)*
+
+                Ok(Self { #(#idents),* })
+            }
+
+            fn encode_into(&self, packet: &mut ::aw_core::AWPacket) {
+                #(#encoders)*
+            }
+        }
+
+        #typed_packet
+    })
+}
+
+fn field_kind(field: &Field) -> syn::Result<FieldKind> {
+    let mut var = None;
+    let mut default = false;
+    let mut flatten = false;
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("aw")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("var") {
+                var = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                default = true;
+                Ok(())
+            } else if meta.path.is_ident("flatten") {
+                flatten = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `var = VarID::...`, `default` or `flatten`"))
+            }
+        })?;
+    }
+
+    match (var, flatten) {
+        (None, true) if !default => Ok(FieldKind::Flatten),
+        (Some(_), true) | (None, true) => Err(syn::Error::new(
+            field.span(),
+            "a flattened field cannot have a `var` or be `default`",
+        )),
+        (None, false) => Err(syn::Error::new(
+            field.span(),
+            "every field needs `#[aw(var = VarID::...)]` or `#[aw(flatten)]`",
+        )),
+        (Some(var), false) => match (option_inner(&field.ty).is_some(), default) {
+            (true, true) => Err(syn::Error::new(
+                field.span(),
+                "an `Option` field is already optional and cannot be `default`",
+            )),
+            (true, false) => Ok(FieldKind::Optional(var)),
+            (false, true) => Ok(FieldKind::Default(var)),
+            (false, false) => Ok(FieldKind::Required(var)),
+        },
+    }
+}
+
+/// The `T` of an `Option<T>` field.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
@@ -1,4 +1,4 @@
-use aw_core::ReasonCode;
+use aw_core::{ReasonCode, TypedPacketError};
 use std::io;
 use thiserror::Error;
 
@@ -14,7 +14,7 @@ pub enum SdkError {
     Protocol(String),
 
     /// Authentication and authorization errors
-    #[error("ActiveWorlds error: {0:?}")]
+    #[error("ActiveWorlds error: {0}")]
     ActiveWorldsError(ReasonCode),
 
     /// Cryptographic errors (key exchange, encryption/decryption failures)
@@ -30,7 +30,7 @@ pub enum SdkError {
     ConnectionState(String),
 
     /// Server returned an error reason code
-    #[error("Server error: {0:?}")]
+    #[error("Server error: {0}")]
     ServerError(ReasonCode),
 
     /// Missing required data in packet
@@ -81,5 +81,14 @@ impl SdkError {
     }
 }
 
+impl From<TypedPacketError> for SdkError {
+    fn from(err: TypedPacketError) -> Self {
+        match err {
+            TypedPacketError::Missing(var) => Self::missing_field(format!("{var:?}")),
+            TypedPacketError::Invalid(_) => Self::protocol(err.to_string()),
+        }
+    }
+}
+
 /// Type alias for SDK results
 pub type SdkResult<T> = Result<T, SdkError>;
@@ -54,7 +54,8 @@ impl AwInstance {
                 }
                 ProtocolMessage::PacketGroup(_)
                 | ProtocolMessage::StreamKey(_)
-                | ProtocolMessage::Encrypt(_) => {
+                | ProtocolMessage::Encrypt(_)
+                | ProtocolMessage::Encoding(_) => {
                     // TODO: Handle these message types properly - for now just log
                     eprintln!("Received unhandled message type");
                 }
@@ -86,7 +87,8 @@ impl AwInstance {
                     }
                     ProtocolMessage::PacketGroup(_)
                     | ProtocolMessage::StreamKey(_)
-                    | ProtocolMessage::Encrypt(_) => {
+                    | ProtocolMessage::Encrypt(_)
+                    | ProtocolMessage::Encoding(_) => {
                         // TODO: Handle these message types properly - for now just log
                         eprintln!("Received unhandled world message type");
                     }
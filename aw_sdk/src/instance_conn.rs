@@ -4,8 +4,8 @@ use std::{
 };
 
 use aw_core::{
-    AWConnection, AWCryptRSA, AWPacket, AWPacketGroup, AWProtocol, PacketType, PacketTypeResult,
-    ProtocolMessage, VarID,
+    AWConnection, AWPacket, AWPacketGroup, AWProtocol, ClientHandshake, HandshakeError, PacketType,
+    PacketTypeResult, ProtocolMessage,
 };
 
 use crate::{SdkError, SdkResult};
@@ -25,7 +25,7 @@ impl AwInstanceConnection {
         let protocol = AWProtocol::new(stream)
             .map_err(|e| SdkError::protocol(format!("Failed to create protocol: {}", e)))?;
         let conn = AWConnection::new(protocol, addr);
-        let my_rsa_key = AWCryptRSA::new();
+        let mut handshake = ClientHandshake::new(conn.get_send_key());
 
         let mut conn = Self {
             domain: domain.to_string(),
@@ -34,61 +34,22 @@ impl AwInstanceConnection {
             backlog_packets: Vec::new(),
         };
 
-        // Do public key request
-        let public_key_request = AWPacket::new(PacketType::PublicKeyRequest);
-        conn.send(public_key_request);
-
-        let public_key_response = conn
-            .wait_for_packet(PacketType::PublicKeyResponse, None)
-            .ok_or_else(|| SdkError::protocol("Failed to receive public key response"))?;
-
-        // Get the server's public RSA key
-        let server_rsa_key = public_key_response
-            .get_data(VarID::EncryptionKey)
-            .ok_or_else(|| SdkError::missing_field("EncryptionKey"))?;
-        let mut server_rsa = AWCryptRSA::new();
-        server_rsa.decode_public_key(&server_rsa_key).map_err(|e| {
-            SdkError::crypto(format!("Failed to decode server public key: {:?}", e))
-        })?;
-        server_rsa.randomize();
-
-        // Encrypt our A4 key with the server's public key
-        let my_a4_key = conn.get_send_key();
-        let my_encrypted_a4_key = server_rsa
-            .encrypt_public(&my_a4_key)
-            .map_err(|e| SdkError::crypto(format!("Failed to encrypt A4 key: {:?}", e)))?;
-
-        // Send the encrypted A4 key to the server
-        let mut my_stream_pkt = AWPacket::new(PacketType::StreamKeyResponse);
-        my_stream_pkt.add_data(VarID::EncryptionKey, my_encrypted_a4_key);
-        conn.send(my_stream_pkt);
-        conn.encrypt_data(true);
-
-        // Send our public RSA key to the server
-        let my_public_key = my_rsa_key
-            .encode_public_key()
-            .ok_or_else(|| SdkError::crypto("Failed to encode public key".to_string()))?;
-        let mut my_public_key_pkt = AWPacket::new(PacketType::PublicKeyResponse);
-        my_public_key_pkt.add_data(VarID::EncryptionKey, my_public_key);
-        my_public_key_pkt.set_header_1(2);
-        conn.send(my_public_key_pkt);
-
-        let stream_key_response = conn
-            .wait_for_packet(PacketType::StreamKeyResponse, None)
-            .ok_or_else(|| SdkError::protocol("Failed to receive stream key response"))?;
-
-        // Decrypt the server's stream key with our private RSA key
-        let encrypted_server_stream_key = stream_key_response
-            .get_data(VarID::EncryptionKey)
-            .ok_or_else(|| SdkError::missing_field("EncryptionKey"))?;
-        let server_stream_key = my_rsa_key
-            .decrypt_private(&encrypted_server_stream_key)
-            .map_err(|e| {
-                SdkError::crypto(format!("Failed to decrypt server stream key: {:?}", e))
+        conn.conn.apply_handshake(handshake.start());
+        while !handshake.is_complete() {
+            let packet = conn
+                .wait_for_packets(
+                    &[PacketType::PublicKeyResponse, PacketType::StreamKeyResponse],
+                    None,
+                )
+                .ok_or_else(|| SdkError::protocol("Disconnected during key exchange"))?;
+
+            let actions = handshake.handle(&packet).map_err(|e| match e {
+                HandshakeError::MissingKey => SdkError::missing_field("EncryptionKey"),
+                HandshakeError::UnexpectedPacket(_) => SdkError::protocol(e.to_string()),
+                _ => SdkError::crypto(e.to_string()),
             })?;
-        conn.set_recv_key(&server_stream_key);
-
-        // eprintln!("Stream key set");
+            conn.conn.apply_handshake(actions);
+        }
 
         Ok(conn)
     }
@@ -165,7 +126,8 @@ impl AwInstanceConnection {
                     }
                     ProtocolMessage::PacketGroup(_)
                     | ProtocolMessage::StreamKey(_)
-                    | ProtocolMessage::Encrypt(_) => {
+                    | ProtocolMessage::Encrypt(_)
+                    | ProtocolMessage::Encoding(_) => {
                         // TODO: Handle these message types properly - for now just log
                         eprintln!("Received unhandled message type in wait_for_packet");
                     }
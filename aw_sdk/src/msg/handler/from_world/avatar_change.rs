@@ -1,4 +1,4 @@
-use aw_core::{AWPacket, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, TypedPacket, VarID};
 
 use crate::{AwEvent, AwInstance, SdkError};
 
@@ -18,13 +18,20 @@ pub fn handle_avatar_change(
     };
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, TypedPacket)]
+#[aw(packet = PacketType::AvatarChange)]
 pub struct AvatarChangeInfo {
+    #[aw(var = VarID::MySession)]
     pub session_id: u32,
+    #[aw(var = VarID::MyName)]
     pub name: String,
-    pub north: i32,    // x
-    pub height: i32,   // y
-    pub west: i32,     // z
+    #[aw(var = VarID::PositionNorth, default)]
+    pub north: i32, // x
+    #[aw(var = VarID::PositionHeight, default)]
+    pub height: i32, // y
+    #[aw(var = VarID::PositionWest, default)]
+    pub west: i32, // z
+    #[aw(var = VarID::PositionRotation, default)]
     pub rotation: i32, // yaw
 }
 
@@ -32,17 +39,6 @@ impl TryFrom<&AWPacket> for AvatarChangeInfo {
     type Error = SdkError;
 
     fn try_from(packet: &AWPacket) -> Result<Self, Self::Error> {
-        Ok(Self {
-            session_id: packet
-                .get_uint(VarID::MySession)
-                .ok_or(SdkError::missing_field("MySession"))?,
-            name: packet
-                .get_string(VarID::MyName)
-                .ok_or(SdkError::missing_field("MyName"))?,
-            north: packet.get_int(VarID::PositionNorth).unwrap_or(0),
-            height: packet.get_int(VarID::PositionHeight).unwrap_or(0),
-            west: packet.get_int(VarID::PositionWest).unwrap_or(0),
-            rotation: packet.get_int(VarID::PositionRotation).unwrap_or(0),
-        })
+        Ok(Self::decode(packet)?)
     }
 }
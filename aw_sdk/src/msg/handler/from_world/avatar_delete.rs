@@ -1,4 +1,4 @@
-use aw_core::{AWPacket, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, TypedPacket, VarID};
 
 use crate::{AwEvent, AwInstance, SdkError};
 
@@ -18,9 +18,12 @@ pub fn handle_avatar_delete(
     };
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, TypedPacket)]
+#[aw(packet = PacketType::AvatarDelete)]
 pub struct AvatarDeleteInfo {
+    #[aw(var = VarID::MySession)]
     pub session_id: u32,
+    #[aw(var = VarID::MyName)]
     pub name: String,
 }
 
@@ -28,13 +31,6 @@ impl TryFrom<&AWPacket> for AvatarDeleteInfo {
     type Error = SdkError;
 
     fn try_from(packet: &AWPacket) -> Result<Self, Self::Error> {
-        Ok(Self {
-            session_id: packet
-                .get_uint(VarID::MySession)
-                .ok_or(SdkError::missing_field("MySession"))?,
-            name: packet
-                .get_string(VarID::MyName)
-                .ok_or(SdkError::missing_field("MyName"))?,
-        })
+        Ok(Self::decode(packet)?)
     }
 }
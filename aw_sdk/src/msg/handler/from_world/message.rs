@@ -1,4 +1,4 @@
-use aw_core::{AWPacket, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, TypedPacket, VarID};
 
 use crate::{AwEvent, AwInstance, SdkError};
 
@@ -10,11 +10,16 @@ pub fn handle_message(_instance: &mut AwInstance, packet: &AWPacket, events: &mu
     events.push(AwEvent::Message(message_info));
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, TypedPacket)]
+#[aw(packet = PacketType::Message)]
 pub struct MessageInfo {
+    #[aw(var = VarID::ChatMessage)]
     pub message: String,
+    #[aw(var = VarID::ChatType)]
     pub chat_type: u32,
+    #[aw(var = VarID::MyName)]
     pub name: String,
+    #[aw(var = VarID::MySession)]
     pub user_session: u32,
 }
 
@@ -22,23 +27,6 @@ impl TryFrom<&AWPacket> for MessageInfo {
     type Error = SdkError;
 
     fn try_from(packet: &AWPacket) -> Result<Self, Self::Error> {
-        let Some(message) = packet.get_string(VarID::ChatMessage) else {
-            return Err(SdkError::missing_field("ChatMessage"));
-        };
-        let Some(chat_type) = packet.get_uint(VarID::ChatType) else {
-            return Err(SdkError::missing_field("ChatType"));
-        };
-        let Some(name) = packet.get_string(VarID::MyName) else {
-            return Err(SdkError::missing_field("MyName"));
-        };
-        let Some(user_session) = packet.get_uint(VarID::MySession) else {
-            return Err(SdkError::missing_field("MySession"));
-        };
-        Ok(Self {
-            message,
-            chat_type,
-            name,
-            user_session,
-        })
+        Ok(Self::decode(packet)?)
     }
 }
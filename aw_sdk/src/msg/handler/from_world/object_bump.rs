@@ -1,4 +1,4 @@
-use aw_core::{AWPacket, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, TypedPacket, VarID};
 
 use crate::{AwEvent, AwInstance, SdkError, msg::handler::from_world::ObjectInfo};
 
@@ -14,10 +14,14 @@ pub fn handle_object_bump(instance: &mut AwInstance, packet: &AWPacket, events:
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, TypedPacket)]
+#[aw(packet = PacketType::ObjectBump)]
 pub struct ObjectBumpInfo {
+    #[aw(var = VarID::MySession)]
     pub avatar_session: u32,
+    #[aw(var = VarID::MyName)]
     pub avatar_name: String,
+    #[aw(flatten)]
     pub object_info: ObjectInfo,
 }
 
@@ -25,16 +29,6 @@ impl TryFrom<&AWPacket> for ObjectBumpInfo {
     type Error = SdkError;
 
     fn try_from(packet: &AWPacket) -> Result<Self, Self::Error> {
-        let avatar_session = packet
-            .get_uint(VarID::MySession)
-            .ok_or_else(|| SdkError::missing_field("MySession"))?;
-        let avatar_name = packet
-            .get_string(VarID::MyName)
-            .ok_or_else(|| SdkError::missing_field("MyName"))?;
-        Ok(Self {
-            avatar_session,
-            avatar_name,
-            object_info: ObjectInfo::try_from(packet)?,
-        })
+        Ok(Self::decode(packet)?)
     }
 }
@@ -1,4 +1,4 @@
-use aw_core::{AWPacket, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, TypedPacket, VarID};
 
 use crate::{AwEvent, AwInstance, SdkError, msg::handler::from_world::ObjectInfo};
 
@@ -18,10 +18,14 @@ pub fn handle_object_click(
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, TypedPacket)]
+#[aw(packet = PacketType::ObjectClick)]
 pub struct ObjectClickInfo {
+    #[aw(var = VarID::MySession)]
     pub avatar_session: u32,
+    #[aw(var = VarID::MyName)]
     pub avatar_name: String,
+    #[aw(flatten)]
     pub object_info: ObjectInfo,
 }
 
@@ -29,16 +33,6 @@ impl TryFrom<&AWPacket> for ObjectClickInfo {
     type Error = SdkError;
 
     fn try_from(packet: &AWPacket) -> Result<Self, Self::Error> {
-        let avatar_session = packet
-            .get_uint(VarID::MySession)
-            .ok_or_else(|| SdkError::missing_field("MySession"))?;
-        let avatar_name = packet
-            .get_string(VarID::MyName)
-            .ok_or_else(|| SdkError::missing_field("MyName"))?;
-        Ok(Self {
-            avatar_session,
-            avatar_name,
-            object_info: ObjectInfo::try_from(packet)?,
-        })
+        Ok(Self::decode(packet)?)
     }
 }
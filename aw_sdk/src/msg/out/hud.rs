@@ -1,4 +1,4 @@
-use aw_core::{AWPacket, AWPacketVar, PacketType, VarID};
+use aw_core::{AWPacket, AWPacketVar, PacketFields, PacketType, TypedPacket, VarID};
 
 use crate::{AwInstance, SdkError, SdkResult};
 
@@ -134,8 +134,12 @@ pub enum HudElementFlags {
     Highlight = 0x0100,
 }
 
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::HudResult)]
 pub struct HudCreateResult {
+    #[aw(var = VarID::HudElementId)]
     pub element_id: u32,
+    #[aw(var = VarID::HudElementSession)]
     pub user_session: u32,
 }
 
@@ -143,13 +147,6 @@ impl TryFrom<&AWPacket> for HudCreateResult {
     type Error = SdkError;
 
     fn try_from(packet: &AWPacket) -> Result<Self, Self::Error> {
-        Ok(Self {
-            element_id: packet
-                .get_uint(VarID::HudElementId)
-                .ok_or(SdkError::missing_field("HudElementId"))?,
-            user_session: packet
-                .get_uint(VarID::HudElementSession)
-                .ok_or(SdkError::missing_field("HudElementSession"))?,
-        })
+        Ok(Self::decode(packet)?)
     }
 }
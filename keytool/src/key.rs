@@ -0,0 +1,179 @@
+//! The rsaref key layout that `AWCryptRSA` reads and writes.
+//!
+//! A key starts with its size in bits as a little endian 32-bit integer,
+//! followed by fixed-size big endian numbers padded with leading zeros:
+//! the modulus and public exponent, and for private keys the private
+//! exponent, both primes, both prime exponents and the CRT coefficient.
+
+/// Size of the buffers holding the modulus and exponents.
+const MAX_MODULUS_LEN: usize = 128;
+
+/// Size of the buffers holding the primes and values derived from them.
+const MAX_PRIME_LEN: usize = MAX_MODULUS_LEN / 2;
+
+pub const PUBLIC_KEY_LEN: usize = 4 + 2 * MAX_MODULUS_LEN;
+pub const PRIVATE_KEY_LEN: usize = 4 + 3 * MAX_MODULUS_LEN + 5 * MAX_PRIME_LEN;
+
+/// Numbers only a private key has. All are big endian without leading zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivateParts {
+    pub exponent: Vec<u8>,
+    pub primes: [Vec<u8>; 2],
+    pub prime_exponents: [Vec<u8>; 2],
+    pub coefficient: Vec<u8>,
+}
+
+/// A public or private key. Numbers are big endian without leading zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaKey {
+    pub bits: u32,
+    pub modulus: Vec<u8>,
+    pub public_exponent: Vec<u8>,
+    pub private: Option<PrivateParts>,
+}
+
+impl RsaKey {
+    /// Decode a key, telling public and private keys apart by their length.
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        if data.len() != PUBLIC_KEY_LEN && data.len() != PRIVATE_KEY_LEN {
+            return Err(format!(
+                "A key is {PUBLIC_KEY_LEN} bytes (public) or {PRIVATE_KEY_LEN} bytes (private), not {}.",
+                data.len()
+            ));
+        }
+
+        let mut reader = Reader { data };
+        let bits = u32::from_le_bytes(reader.take(4).try_into().expect("4 bytes"));
+        let modulus = reader.number(MAX_MODULUS_LEN);
+        let public_exponent = reader.number(MAX_MODULUS_LEN);
+
+        let private = if data.len() == PRIVATE_KEY_LEN {
+            Some(PrivateParts {
+                exponent: reader.number(MAX_MODULUS_LEN),
+                primes: [reader.number(MAX_PRIME_LEN), reader.number(MAX_PRIME_LEN)],
+                prime_exponents: [reader.number(MAX_PRIME_LEN), reader.number(MAX_PRIME_LEN)],
+                coefficient: reader.number(MAX_PRIME_LEN),
+            })
+        } else {
+            None
+        };
+
+        let key = Self {
+            bits,
+            modulus,
+            public_exponent,
+            private,
+        };
+        key.check_bits()?;
+        Ok(key)
+    }
+
+    /// Encode the key in the layout [`RsaKey::decode`] reads.
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        self.check_bits()?;
+
+        let mut data = self.bits.to_le_bytes().to_vec();
+        write_number(&mut data, &self.modulus, MAX_MODULUS_LEN)?;
+        write_number(&mut data, &self.public_exponent, MAX_MODULUS_LEN)?;
+
+        if let Some(private) = &self.private {
+            write_number(&mut data, &private.exponent, MAX_MODULUS_LEN)?;
+            for number in private.primes.iter().chain(&private.prime_exponents) {
+                write_number(&mut data, number, MAX_PRIME_LEN)?;
+            }
+            write_number(&mut data, &private.coefficient, MAX_PRIME_LEN)?;
+        }
+
+        Ok(data)
+    }
+
+    /// The public half of the key.
+    pub fn public(&self) -> Self {
+        Self {
+            private: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.private.is_some()
+    }
+
+    fn check_bits(&self) -> Result<(), String> {
+        let modulus_bits = bit_length(&self.modulus);
+        if modulus_bits != self.bits {
+            return Err(format!(
+                "Key claims to be {} bits, but its modulus is {modulus_bits} bits.",
+                self.bits
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Number of significant bits in a big endian number without leading zeros.
+pub fn bit_length(number: &[u8]) -> u32 {
+    match number.first() {
+        Some(first) => (number.len() as u32 - 1) * 8 + (8 - first.leading_zeros()),
+        None => 0,
+    }
+}
+
+/// Strip the leading zeros of a big endian number.
+pub fn trim_number(number: &[u8]) -> Vec<u8> {
+    let start = number.iter().position(|&b| b != 0).unwrap_or(number.len());
+    number[start..].to_vec()
+}
+
+fn write_number(data: &mut Vec<u8>, number: &[u8], len: usize) -> Result<(), String> {
+    let number = trim_number(number);
+    if number.len() > len {
+        return Err(format!(
+            "A {}-byte number does not fit in a key field of {len} bytes.",
+            number.len()
+        ));
+    }
+    data.resize(data.len() + len - number.len(), 0);
+    data.extend_from_slice(&number);
+    Ok(())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        taken
+    }
+
+    fn number(&mut self, len: usize) -> Vec<u8> {
+        trim_number(self.take(len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AW_PRIVATE: &[u8] = include_bytes!("../../universe/src/keys/aw.priv");
+    const AW_PUBLIC: &[u8] = include_bytes!("../../universe/src/keys/aw.pub");
+
+    #[test]
+    fn bundled_keys_round_trip() {
+        let private = RsaKey::decode(AW_PRIVATE).unwrap();
+        let public = RsaKey::decode(AW_PUBLIC).unwrap();
+
+        assert_eq!(private.bits, 512);
+        assert!(private.is_private());
+        assert!(!public.is_private());
+        assert_eq!(private.public(), public);
+
+        assert_eq!(private.encode().unwrap(), AW_PRIVATE);
+        assert_eq!(public.encode().unwrap(), AW_PUBLIC);
+
+        assert!(RsaKey::decode(&AW_PUBLIC[1..]).is_err());
+    }
+}
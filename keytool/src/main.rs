@@ -0,0 +1,189 @@
+use aw_core::{AWCryptRSA, RSAKey, DEFAULT_RSA_BITS, MAX_RSA_BITS};
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+
+mod key;
+mod pem;
+use key::RsaKey;
+
+#[derive(Parser)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a new key pair
+    Generate {
+        /// File to write the private key to
+        private_key_file: PathBuf,
+
+        /// File to write the public key to
+        public_key_file: PathBuf,
+
+        #[clap(
+            long,
+            default_value_t = DEFAULT_RSA_BITS,
+            value_parser = clap::value_parser!(u16).range(512..=i64::from(MAX_RSA_BITS))
+        )]
+        /// Size of the key in bits, from 512 to 1024
+        bits: u16,
+    },
+    /// Print the size and fingerprint of keys
+    Fingerprint {
+        #[clap(required = true)]
+        /// Public or private keys to describe
+        key_files: Vec<PathBuf>,
+    },
+    /// Convert a key to PKCS#1 PEM
+    ToPem {
+        /// Public or private key to convert
+        key_file: PathBuf,
+
+        #[clap(long)]
+        /// File to write the PEM to instead of standard output
+        output: Option<PathBuf>,
+    },
+    /// Convert a PKCS#1 PEM key back to the format the universe and licgen read
+    FromPem {
+        /// PEM file to convert
+        pem_file: PathBuf,
+
+        /// File to write the key to
+        output_file: PathBuf,
+    },
+    /// Check that a private key and a public key belong together
+    Verify {
+        private_key_file: PathBuf,
+        public_key_file: PathBuf,
+    },
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let result = match args.command {
+        Command::Generate {
+            private_key_file,
+            public_key_file,
+            bits,
+        } => generate(&private_key_file, &public_key_file, bits),
+        Command::Fingerprint { key_files } => fingerprint(&key_files),
+        Command::ToPem { key_file, output } => to_pem(&key_file, output.as_deref()),
+        Command::FromPem {
+            pem_file,
+            output_file,
+        } => from_pem(&pem_file, &output_file),
+        Command::Verify {
+            private_key_file,
+            public_key_file,
+        } => verify(&private_key_file, &public_key_file),
+    };
+
+    if let Err(err) = result {
+        println!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn read_key(path: &Path) -> Result<RsaKey, String> {
+    let data =
+        std::fs::read(path).map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+    RsaKey::decode(&data).map_err(|err| format!("Could not decode {}: {err}", path.display()))
+}
+
+fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), String> {
+    std::fs::write(path, contents)
+        .map_err(|err| format!("Could not write {}: {err}", path.display()))
+}
+
+fn describe(key: &RsaKey) -> String {
+    let kind = if key.is_private() {
+        "private"
+    } else {
+        "public"
+    };
+    format!("{} bit {kind} key {}", key.bits, pem::fingerprint(key))
+}
+
+fn generate(private_key_file: &Path, public_key_file: &Path, bits: u16) -> Result<(), String> {
+    let rsa = AWCryptRSA::generate(bits).map_err(|_| "Could not generate a key pair.")?;
+    let private_key = rsa
+        .encode_private_key()
+        .ok_or("Could not encode the private key.")?;
+    let public_key = rsa
+        .encode_public_key()
+        .ok_or("Could not encode the public key.")?;
+
+    write_file(private_key_file, &private_key)?;
+    write_file(public_key_file, &public_key)?;
+
+    println!("Generated {}", describe(&RsaKey::decode(&private_key)?));
+    Ok(())
+}
+
+fn fingerprint(key_files: &[PathBuf]) -> Result<(), String> {
+    for path in key_files {
+        println!("{}: {}", path.display(), describe(&read_key(path)?));
+    }
+    Ok(())
+}
+
+fn to_pem(key_file: &Path, output: Option<&Path>) -> Result<(), String> {
+    let pem = pem::to_pem(&read_key(key_file)?);
+    match output {
+        Some(path) => write_file(path, pem),
+        None => {
+            print!("{pem}");
+            Ok(())
+        }
+    }
+}
+
+fn from_pem(pem_file: &Path, output_file: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(pem_file)
+        .map_err(|err| format!("Could not read {}: {err}", pem_file.display()))?;
+    let key = pem::from_pem(&text)?;
+    write_file(output_file, key.encode()?)?;
+
+    println!("Wrote {}", describe(&key));
+    Ok(())
+}
+
+fn verify(private_key_file: &Path, public_key_file: &Path) -> Result<(), String> {
+    let private_key = read_key(private_key_file)?;
+    let public_key = read_key(public_key_file)?;
+    if !private_key.is_private() {
+        return Err(format!(
+            "{} is not a private key.",
+            private_key_file.display()
+        ));
+    }
+
+    if private_key.public() != public_key.public() {
+        return Err(format!(
+            "The keys do not match: {} belongs to {}",
+            describe(&public_key),
+            describe(&private_key.public())
+        ));
+    }
+
+    // Make sure the private key actually works, and not just its public half
+    let mut rsa = AWCryptRSA::default();
+    rsa.decode_private_key(&private_key.encode()?)
+        .map_err(|_| "Could not decode the private key.")?;
+    rsa.randomize();
+
+    let message = b"keytool verify";
+    let decrypted = rsa
+        .encrypt(message, RSAKey::Public)
+        .and_then(|encrypted| rsa.decrypt(&encrypted, RSAKey::Private))
+        .map_err(|_| "The private key could not decrypt a message for the public key.")?;
+    if decrypted != message {
+        return Err("The private key decrypted a message incorrectly.".to_string());
+    }
+
+    println!("The keys match: {}", describe(&public_key));
+    Ok(())
+}
@@ -0,0 +1,219 @@
+//! Conversion between [`RsaKey`] and PKCS#1 PEM, so keys can be inspected
+//! with common tools such as `openssl rsa -RSAPublicKey_in -text`.
+use crate::key::{bit_length, trim_number, PrivateParts, RsaKey};
+use sha2::{Digest, Sha256};
+
+const PUBLIC_LABEL: &str = "RSA PUBLIC KEY";
+const PRIVATE_LABEL: &str = "RSA PRIVATE KEY";
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// Encode a key as a PKCS#1 DER structure.
+pub fn to_der(key: &RsaKey) -> Vec<u8> {
+    let mut numbers: Vec<&[u8]> = Vec::new();
+    if key.private.is_some() {
+        // Version of the private key structure
+        numbers.push(&[]);
+    }
+    numbers.push(&key.modulus);
+    numbers.push(&key.public_exponent);
+    if let Some(private) = &key.private {
+        numbers.push(&private.exponent);
+        numbers.extend(private.primes.iter().map(Vec::as_slice));
+        numbers.extend(private.prime_exponents.iter().map(Vec::as_slice));
+        numbers.push(&private.coefficient);
+    }
+
+    let mut body = Vec::new();
+    for number in numbers {
+        let mut integer = trim_number(number);
+        if integer.is_empty() || integer[0] & 0x80 != 0 {
+            // Keep the integer positive, and encode zero as a single byte
+            integer.insert(0, 0);
+        }
+        write_element(&mut body, TAG_INTEGER, &integer);
+    }
+
+    let mut der = Vec::new();
+    write_element(&mut der, TAG_SEQUENCE, &body);
+    der
+}
+
+/// Decode a PKCS#1 DER structure, telling public and private keys apart by
+/// the number of integers in it.
+pub fn from_der(der: &[u8]) -> Result<RsaKey, String> {
+    let invalid = || "The key is not a PKCS#1 RSA key.".to_string();
+
+    let mut reader = DerReader { data: der };
+    let body = reader.element(TAG_SEQUENCE).ok_or_else(invalid)?;
+    if !reader.data.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut reader = DerReader { data: body };
+    let mut numbers = Vec::new();
+    while !reader.data.is_empty() {
+        let integer = reader.element(TAG_INTEGER).ok_or_else(invalid)?;
+        numbers.push(trim_number(integer));
+    }
+
+    let key = match numbers.as_slice() {
+        [modulus, public_exponent] => RsaKey {
+            bits: bit_length(modulus),
+            modulus: modulus.clone(),
+            public_exponent: public_exponent.clone(),
+            private: None,
+        },
+        [version, modulus, public_exponent, exponent, p, q, dp, dq, coefficient]
+            if version.is_empty() =>
+        {
+            RsaKey {
+                bits: bit_length(modulus),
+                modulus: modulus.clone(),
+                public_exponent: public_exponent.clone(),
+                private: Some(PrivateParts {
+                    exponent: exponent.clone(),
+                    primes: [p.clone(), q.clone()],
+                    prime_exponents: [dp.clone(), dq.clone()],
+                    coefficient: coefficient.clone(),
+                }),
+            }
+        }
+        _ => return Err(invalid()),
+    };
+
+    Ok(key)
+}
+
+/// Encode a key as PEM text.
+pub fn to_pem(key: &RsaKey) -> String {
+    let label = if key.is_private() {
+        PRIVATE_LABEL
+    } else {
+        PUBLIC_LABEL
+    };
+
+    let encoded = base64::encode(to_der(key));
+    let mut pem = format!("-----BEGIN {label}-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {label}-----\n"));
+    pem
+}
+
+/// Decode PEM text containing a single key.
+pub fn from_pem(pem: &str) -> Result<RsaKey, String> {
+    let mut lines = pem.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    let label = lines
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .ok_or("The file does not start with a PEM header.")?;
+    if label != PUBLIC_LABEL && label != PRIVATE_LABEL {
+        return Err(format!(
+            "Expected {PUBLIC_LABEL} or {PRIVATE_LABEL}, not {label}."
+        ));
+    }
+
+    let footer = format!("-----END {label}-----");
+    let mut encoded = String::new();
+    for line in lines.by_ref() {
+        if line == footer {
+            break;
+        }
+        encoded.push_str(line);
+    }
+
+    let der = base64::decode(encoded).map_err(|_| "The PEM body is not valid base64.")?;
+    let key = from_der(&der)?;
+
+    if key.is_private() != (label == PRIVATE_LABEL) {
+        return Err(format!("The key does not match its {label} header."));
+    }
+    Ok(key)
+}
+
+/// SHA-256 of the DER encoded public key, formatted like an SSH fingerprint.
+pub fn fingerprint(key: &RsaKey) -> String {
+    let digest = Sha256::digest(to_der(&key.public()));
+    format!(
+        "SHA256:{}",
+        base64::encode_config(digest, base64::STANDARD_NO_PAD)
+    )
+}
+
+fn write_element(out: &mut Vec<u8>, tag: u8, content: &[u8]) {
+    out.push(tag);
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len = content.len().to_be_bytes();
+        let len = trim_number(&len);
+        out.push(0x80 | len.len() as u8);
+        out.extend_from_slice(&len);
+    }
+    out.extend_from_slice(content);
+}
+
+struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(taken)
+    }
+
+    /// Read an element with the given tag and return its content.
+    fn element(&mut self, tag: u8) -> Option<&'a [u8]> {
+        let header = self.take(2)?;
+        if header[0] != tag {
+            return None;
+        }
+
+        let len = match header[1] {
+            len @ 0..=0x7F => usize::from(len),
+            0x81..=0x84 => {
+                let len_bytes = self.take(usize::from(header[1] & 0x7F))?;
+                len_bytes
+                    .iter()
+                    .fold(0usize, |len, &b| (len << 8) | usize::from(b))
+            }
+            _ => return None,
+        };
+
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AW_PRIVATE: &[u8] = include_bytes!("../../universe/src/keys/aw.priv");
+
+    #[test]
+    fn pem_round_trips() {
+        let private = RsaKey::decode(AW_PRIVATE).unwrap();
+        let public = private.public();
+
+        for key in [&private, &public] {
+            let pem = to_pem(key);
+            assert_eq!(&from_pem(&pem).unwrap(), key);
+        }
+        assert!(to_pem(&public).starts_with("-----BEGIN RSA PUBLIC KEY-----\n"));
+        assert_eq!(fingerprint(&private), fingerprint(&public));
+
+        let mismatched = to_pem(&public).replace(PUBLIC_LABEL, PRIVATE_LABEL);
+        assert!(from_pem(&mismatched).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Parsing of license expiration times.
+//!
+//! Licenses store the expiration as a signed 32-bit Unix timestamp, with
+//! `i32::MAX` meaning the license never expires.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aw_core::date::{civil_from_days, days_from_civil, days_in_month};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Current Unix time in seconds.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Parse an expiration time relative to `now`. Accepted forms are:
+///
+/// * `never`
+/// * a Unix timestamp, e.g. `1893456000`
+/// * a date in UTC, e.g. `2030-01-01`
+/// * a date and time in UTC, e.g. `2030-01-01T12:00:00` or `2030-01-01 12:00`
+/// * a time from now in days, weeks or years, e.g. `+30d`, `+6w` or `+1y`
+pub fn parse(text: &str, now: i64) -> Result<i32, String> {
+    let text = text.trim();
+
+    let timestamp = if text.eq_ignore_ascii_case("never") {
+        return Ok(i32::MAX);
+    } else if let Some(offset) = text.strip_prefix('+') {
+        now.checked_add(parse_offset(offset)?)
+            .ok_or_else(|| format!("Expiration {text} is out of range."))?
+    } else if let Ok(timestamp) = text.parse::<i64>() {
+        timestamp
+    } else {
+        parse_date_time(text)?
+    };
+
+    i32::try_from(timestamp).map_err(|_| format!("Expiration {text} is out of range."))
+}
+
+/// Length of an offset like `30d` in seconds.
+fn parse_offset(offset: &str) -> Result<i64, String> {
+    let invalid = || format!("Invalid offset +{offset}; expected e.g. +30d, +6w or +1y.");
+
+    let unit_start = offset
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (count, unit) = offset.split_at(unit_start);
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+
+    let days = match unit {
+        "d" => 1,
+        "w" => 7,
+        "y" => 365,
+        _ => return Err(invalid()),
+    };
+
+    count
+        .checked_mul(days * SECONDS_PER_DAY)
+        .ok_or_else(invalid)
+}
+
+/// Parse `YYYY-MM-DD`, optionally followed by `T` or a space and
+/// `HH:MM` or `HH:MM:SS`, as a time in UTC.
+fn parse_date_time(text: &str) -> Result<i64, String> {
+    let invalid = || format!("Invalid expiration {text}; expected e.g. 2030-01-01.");
+
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+
+    let date: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = date[..] else {
+        return Err(invalid());
+    };
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let month: u32 = month.parse().map_err(|_| invalid())?;
+    let day: u32 = day.parse().map_err(|_| invalid())?;
+    if !(0..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+    {
+        return Err(invalid());
+    }
+
+    let seconds = match time {
+        Some(time) => {
+            let parts = time
+                .split(':')
+                .map(|part| part.parse::<u32>().map_err(|_| invalid()))
+                .collect::<Result<Vec<u32>, String>>()?;
+            let (hour, minute, second) = match parts[..] {
+                [hour, minute] => (hour, minute, 0),
+                [hour, minute, second] => (hour, minute, second),
+                _ => return Err(invalid()),
+            };
+            if hour > 23 || minute > 59 || second > 59 {
+                return Err(invalid());
+            }
+            i64::from(hour * 3600 + minute * 60 + second)
+        }
+        None => 0,
+    };
+
+    days_from_civil(year, month, day)
+        .checked_mul(SECONDS_PER_DAY)
+        .and_then(|timestamp| timestamp.checked_add(seconds))
+        .ok_or_else(|| format!("Expiration {text} is out of range."))
+}
+
+/// Format a timestamp the way [`parse`] accepts it back.
+pub fn format(timestamp: i32) -> String {
+    if timestamp == i32::MAX {
+        return "never".to_string();
+    }
+
+    let timestamp = i64::from(timestamp);
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+    let seconds = timestamp.rem_euclid(SECONDS_PER_DAY);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_form() {
+        let now = 1_700_000_000;
+
+        assert_eq!(parse("never", now), Ok(i32::MAX));
+        assert_eq!(parse("1893456000", now), Ok(1_893_456_000));
+        assert_eq!(parse("2030-01-01", now), Ok(1_893_456_000));
+        assert_eq!(parse("2030-01-01T12:30", now), Ok(1_893_501_000));
+        assert_eq!(parse("2024-02-29 00:00:01", now), Ok(1_709_164_801));
+        assert_eq!(parse("+30d", now), Ok(1_702_592_000));
+        assert_eq!(parse("+1y", now), Ok(1_731_536_000));
+
+        assert!(parse("2023-02-29", now).is_err());
+        assert!(parse("2030-13-01", now).is_err());
+        assert!(parse("+30x", now).is_err());
+        assert!(parse("2040-01-01", now).is_err());
+    }
+
+    #[test]
+    fn huge_offsets_are_out_of_range() {
+        let now = 1_700_000_000;
+
+        assert!(parse("+9999999999999d", now).is_err());
+        assert!(parse("+9999999999999999y", now).is_err());
+        assert!(parse("+1d", i64::MAX).is_err());
+        assert!(parse("+100y", now).is_err());
+        assert!(parse("9223372036854775807-01-01", now).is_err());
+    }
+
+    #[test]
+    fn format_round_trips() {
+        for timestamp in [0, 951_782_400, 1_893_501_000, i32::MAX] {
+            assert_eq!(parse(&format(timestamp), 0), Ok(timestamp));
+        }
+        assert_eq!(format(1_893_501_000), "2030-01-01T12:30:00");
+    }
+}
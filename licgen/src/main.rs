@@ -1,45 +1,130 @@
-use aw_core::{AWCryptRSA, AWRegLic, AWRegLicData, RSAKey};
-use clap::Parser;
-use std::net::Ipv4Addr;
+use aw_core::{AWCryptRSA, AWRegLic, RSAKey};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+mod expiration;
+mod spec;
+use spec::LicenseSpec;
 
 #[derive(Parser)]
 struct Args {
-    private_key_file: String,
-    ip_address: Ipv4Addr,
-    port: u16,
-    output_file: String,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a license signed with a private key
+    Generate {
+        /// Private key to sign the license with
+        private_key_file: PathBuf,
+
+        #[clap(long)]
+        /// TOML or JSON file with license fields; flags take precedence over it
+        spec: Option<PathBuf>,
+
+        #[clap(flatten)]
+        license: LicenseSpec,
+
+        #[clap(long)]
+        /// File to write the base64 license to
+        output: Option<PathBuf>,
+
+        #[clap(long)]
+        /// File to write the raw binary license to
+        binary_output: Option<PathBuf>,
+    },
+    /// Print a license spec with every field at its default value
+    Template {
+        #[clap(long, value_enum, default_value_t = SpecFormat::Toml)]
+        /// Format of the spec
+        format: SpecFormat,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SpecFormat {
+    Toml,
+    Json,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let key_bytes = std::fs::read(args.private_key_file).unwrap_or_else(|_| {
-        println!("Could not read private key file.");
+    let result = match args.command {
+        Command::Generate {
+            private_key_file,
+            spec,
+            license,
+            output,
+            binary_output,
+        } => generate(private_key_file, spec, license, output, binary_output),
+        Command::Template { format } => template(format),
+    };
+
+    if let Err(err) = result {
+        println!("{err}");
         std::process::exit(1);
-    });
+    }
+}
+
+fn generate(
+    private_key_file: PathBuf,
+    spec_file: Option<PathBuf>,
+    flags: LicenseSpec,
+    output: Option<PathBuf>,
+    binary_output: Option<PathBuf>,
+) -> Result<(), String> {
+    let key_bytes =
+        std::fs::read(private_key_file).map_err(|_| "Could not read private key file.")?;
 
     let mut rsa = AWCryptRSA::new();
-    rsa.decode_private_key(&key_bytes).unwrap_or_else(|_| {
-        println!("Could not decode private key.");
-        std::process::exit(1);
-    });
+    rsa.decode_private_key(&key_bytes)
+        .map_err(|_| "Could not decode private key.")?;
+
+    let spec = match spec_file {
+        Some(path) => LicenseSpec::load(&path)?.merge(flags),
+        None => flags,
+    };
+    let reg_lic_data = spec.build(expiration::now())?;
 
     let mut reg_lic = AWRegLic::new(rsa);
-    let reg_lic_data = AWRegLicData::default()
-        .set_ip_address(&args.ip_address)
-        .set_port(args.port.into())
-        .set_name("aw")
-        .set_expiration_time(i32::MAX);
-
-    let encrypted_data = reg_lic
-        .code_generate_base64(&reg_lic_data, RSAKey::Private)
-        .unwrap_or_else(|_| {
-            println!("Could not generate encrypted license.");
-            std::process::exit(1);
-        });
-
-    std::fs::write(args.output_file, encrypted_data).unwrap_or_else(|_| {
-        println!("Failed to write to output file.");
-        std::process::exit(1);
-    });
+    let binary = reg_lic
+        .code_generate_binary(&reg_lic_data, RSAKey::Private)
+        .map_err(|_| "Could not generate encrypted license.")?;
+    let base64 = base64::encode(&binary);
+
+    if let Some(path) = &binary_output {
+        std::fs::write(path, &binary).map_err(|_| "Failed to write to binary output file.")?;
+    }
+
+    match &output {
+        Some(path) => {
+            std::fs::write(path, &base64).map_err(|_| "Failed to write to output file.")?
+        }
+        // Only print the license if it is not written anywhere else
+        None if binary_output.is_none() => println!("{base64}"),
+        None => {}
+    }
+
+    eprintln!(
+        "Generated license for {}:{}, expiring {}",
+        reg_lic_data.get_ip_address(),
+        reg_lic_data.get_port(),
+        expiration::format(reg_lic_data.get_expiration_time())
+    );
+
+    Ok(())
+}
+
+fn template(format: SpecFormat) -> Result<(), String> {
+    let spec = LicenseSpec::from_data(&LicenseSpec::default().build(expiration::now())?);
+
+    let text = match format {
+        SpecFormat::Toml => toml::to_string(&spec).map_err(|err| err.to_string())?,
+        SpecFormat::Json => serde_json::to_string_pretty(&spec).map_err(|err| err.to_string())?,
+    };
+    println!("{text}");
+
+    Ok(())
 }
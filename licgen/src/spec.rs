@@ -0,0 +1,172 @@
+//! Description of a license, read from a file and/or the command line.
+use crate::expiration;
+use aw_core::{AWRegLicData, AWRegLicFields};
+use serde::{Deserialize, Serialize};
+use std::{net::Ipv4Addr, path::Path};
+
+/// Every field of a license. Fields that are not set are left as
+/// [`AWRegLicFields::build`] leaves them.
+#[derive(clap::Args, Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LicenseSpec {
+    #[clap(long, value_parser)]
+    /// Version of the license format
+    pub license_version: Option<u32>,
+
+    #[clap(long = "ip", value_parser)]
+    /// IP address the universe is reached at
+    pub ip_address: Option<Ipv4Addr>,
+
+    #[clap(long, value_parser)]
+    /// Port the universe listens on
+    pub port: Option<u32>,
+
+    #[clap(long, value_parser)]
+    /// Most land the universe may have, or 0 for no limit
+    pub land_limit: Option<u32>,
+
+    #[clap(long, value_parser)]
+    /// Most users online at once, or 0 for no limit
+    pub max_users: Option<u32>,
+
+    #[clap(long, value_parser)]
+    /// Most worlds the universe may have, or 0 for no limit
+    pub world_limit: Option<u32>,
+
+    #[clap(long = "expires", value_parser)]
+    /// When the license expires: never, 2030-01-01, 2030-01-01T12:00:00, +30d, +6w, +1y or a Unix timestamp
+    pub expiration: Option<String>,
+
+    #[clap(long, value_parser)]
+    /// Major version of the universe server
+    pub major_version: Option<u16>,
+
+    #[clap(long, value_parser)]
+    /// Minor version of the universe server
+    pub minor_version: Option<u16>,
+
+    #[clap(long, value_parser)]
+    /// Name of the universe, at most 31 bytes
+    pub name: Option<String>,
+
+    #[clap(long = "bots", value_name = "BOOL", value_parser)]
+    /// Whether the universe may have bots: <true | false>
+    pub can_have_bots: Option<bool>,
+}
+
+impl LicenseSpec {
+    /// Read a spec from a JSON file if its extension is `.json`, or a TOML
+    /// file otherwise.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        if is_json {
+            serde_json::from_str(&text)
+                .map_err(|err| format!("Could not parse {}: {err}", path.display()))
+        } else {
+            toml::from_str(&text)
+                .map_err(|err| format!("Could not parse {}: {err}", path.display()))
+        }
+    }
+
+    /// Spec with every field set to the value in `data`.
+    pub fn from_data(data: &AWRegLicData) -> Self {
+        Self {
+            license_version: Some(data.get_license_version()),
+            ip_address: Some(data.get_ip_address()),
+            port: Some(data.get_port()),
+            land_limit: Some(data.get_land_limit()),
+            max_users: Some(data.get_max_users()),
+            world_limit: Some(data.get_world_limit()),
+            expiration: Some(expiration::format(data.get_expiration_time())),
+            major_version: Some(data.get_major_version()),
+            minor_version: Some(data.get_minor_version()),
+            name: Some(data.get_name().trim_end_matches('\0').to_string()),
+            can_have_bots: Some(data.get_can_have_bots()),
+        }
+    }
+
+    /// Fields set in `other` replace the ones in `self`.
+    pub fn merge(self, other: LicenseSpec) -> Self {
+        Self {
+            license_version: other.license_version.or(self.license_version),
+            ip_address: other.ip_address.or(self.ip_address),
+            port: other.port.or(self.port),
+            land_limit: other.land_limit.or(self.land_limit),
+            max_users: other.max_users.or(self.max_users),
+            world_limit: other.world_limit.or(self.world_limit),
+            expiration: other.expiration.or(self.expiration),
+            major_version: other.major_version.or(self.major_version),
+            minor_version: other.minor_version.or(self.minor_version),
+            name: other.name.or(self.name),
+            can_have_bots: other.can_have_bots.or(self.can_have_bots),
+        }
+    }
+
+    /// Build the license data, with relative expiration times counted from `now`.
+    pub fn build(&self, now: i64) -> Result<AWRegLicData, String> {
+        let expiration_time = match &self.expiration {
+            Some(expiration) => Some(expiration::parse(expiration, now)?),
+            None => None,
+        };
+
+        AWRegLicFields {
+            license_version: self.license_version,
+            ip_address: self.ip_address,
+            port: self.port,
+            land_limit: self.land_limit,
+            max_users: self.max_users,
+            world_limit: self.world_limit,
+            expiration_time,
+            major_version: self.major_version,
+            minor_version: self.minor_version,
+            name: self.name.clone(),
+            can_have_bots: self.can_have_bots,
+        }
+        .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_line_overrides_file() {
+        let file: LicenseSpec = toml::from_str(
+            r#"
+            ip_address = "10.0.0.1"
+            port = 5670
+            name = "test"
+            expiration = "2030-01-01"
+            "#,
+        )
+        .unwrap();
+        let flags = LicenseSpec {
+            port: Some(6671),
+            can_have_bots: Some(false),
+            ..Default::default()
+        };
+
+        let data = file.merge(flags).build(0).unwrap();
+        assert_eq!(data.get_ip_address(), Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(data.get_port(), 6671);
+        assert_eq!(data.get_expiration_time(), 1_893_456_000);
+        assert!(!data.get_can_have_bots());
+        assert_eq!(data.get_major_version(), 5);
+
+        let spec = LicenseSpec::from_data(&data);
+        assert_eq!(spec.name.as_deref(), Some("test"));
+        assert_eq!(spec.build(0).unwrap().encode(), data.encode());
+
+        let long_name = LicenseSpec {
+            name: Some("x".repeat(aw_core::MAX_LICENSE_NAME_LENGTH + 1)),
+            ..Default::default()
+        };
+        assert!(long_name.build(0).is_err());
+    }
+}
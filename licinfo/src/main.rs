@@ -1,38 +1,321 @@
-use aw_core::{AWCryptRSA, AWRegLic, RSAKey};
-use clap::Parser;
+use aw_core::{AWCryptRSA, AWRegLic, AWRegLicData, RSAKey};
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Public keys of the licenses the universe can hand out, in the order they are tried.
+const BUNDLED_KEYS: [(&str, &[u8]); 2] = [
+    ("AW", include_bytes!("../../universe/src/keys/aw.pub")),
+    (
+        "Vortex",
+        include_bytes!("../../universe/src/keys/vortex.pub"),
+    ),
+];
 
 #[derive(Parser)]
 struct Args {
-    input_file: String,
-    public_key_file: String,
+    #[clap(required = true)]
+    /// License files to read, as base64 text or raw binary
+    input_files: Vec<PathBuf>,
+
+    #[clap(long = "public-key")]
+    /// Public key to try before the bundled AW and Vortex keys; may be given more than once
+    public_keys: Vec<PathBuf>,
+
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    /// How to print the licenses
+    format: OutputFormat,
+
+    #[clap(long, value_name = "CONFIG_FILE")]
+    /// Warn about licenses that do not match the license_ip and port of a universe.toml
+    check: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// The parts of a universe configuration file a license has to match.
+#[derive(Deserialize)]
+struct UniverseFile {
+    universe: UniverseSection,
+}
+
+#[derive(Deserialize)]
+struct UniverseSection {
+    license_ip: Ipv4Addr,
+    port: u16,
+}
+
+struct PublicKey {
+    name: String,
+    data: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct LicenseReport {
+    file: PathBuf,
+    /// Name of the public key that decrypted the license
+    key: String,
+    /// Whether the file held base64 text or raw binary
+    encoding: &'static str,
+    license_version: u32,
+    ip_address: Ipv4Addr,
+    port: u32,
+    land_limit: u32,
+    max_users: u32,
+    world_limit: u32,
+    expiration_time: i32,
+    expired: bool,
+    major_version: u16,
+    minor_version: u16,
+    name: String,
+    can_have_bots: bool,
+    /// Reasons clients may refuse the license
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum FileResult {
+    License(LicenseReport),
+    Error { file: PathBuf, error: String },
 }
 
 fn main() {
     let args = Args::parse();
 
-    let license_text = std::fs::read_to_string(args.input_file).unwrap_or_else(|_| {
-        println!("Could not read input file.");
-        std::process::exit(1);
-    });
-
-    let key_bytes = std::fs::read(args.public_key_file).unwrap_or_else(|_| {
-        println!("Could not read public key file.");
-        std::process::exit(1);
-    });
-
-    let mut rsa = AWCryptRSA::new();
-    rsa.decode_public_key(&key_bytes).unwrap_or_else(|_| {
-        println!("Could not decode public key.");
-        std::process::exit(1);
-    });
-
-    let mut reg_lic = AWRegLic::new(rsa);
-    let license_data = reg_lic
-        .code_process_base64(&license_text, RSAKey::Public)
-        .unwrap_or_else(|err| {
-            println!("{}", err);
+    match run(&args) {
+        Ok(true) => {}
+        // Some licenses could not be read or have warnings
+        Ok(false) => std::process::exit(1),
+        Err(err) => {
+            println!("{err}");
             std::process::exit(1);
+        }
+    }
+}
+
+/// Print every license, and return whether they were all read without warnings.
+fn run(args: &Args) -> Result<bool, String> {
+    let mut keys = Vec::new();
+    for path in &args.public_keys {
+        let data = std::fs::read(path)
+            .map_err(|err| format!("Could not read public key {}: {err}", path.display()))?;
+        keys.push(PublicKey {
+            name: path.display().to_string(),
+            data,
         });
+    }
+    keys.extend(BUNDLED_KEYS.iter().map(|(name, data)| PublicKey {
+        name: name.to_string(),
+        data: data.to_vec(),
+    }));
+
+    let universe = match &args.check {
+        Some(path) => Some(read_universe_config(path)?),
+        None => None,
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+
+    let results: Vec<FileResult> = args
+        .input_files
+        .iter()
+        .map(
+            |path| match read_license(path, &keys, universe.as_ref(), now) {
+                Ok(report) => FileResult::License(report),
+                Err(error) => FileResult::Error {
+                    file: path.clone(),
+                    error,
+                },
+            },
+        )
+        .collect();
+
+    match args.format {
+        OutputFormat::Text => print_text(&results, now),
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&results).map_err(|err| err.to_string())?;
+            println!("{json}");
+        }
+    }
+
+    Ok(results.iter().all(|result| match result {
+        FileResult::License(report) => report.warnings.is_empty(),
+        FileResult::Error { .. } => false,
+    }))
+}
+
+fn read_universe_config(path: &Path) -> Result<UniverseSection, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+    let config: UniverseFile = toml::from_str(&text)
+        .map_err(|err| format!("Could not parse {}: {err}", path.display()))?;
+    Ok(config.universe)
+}
+
+fn read_license(
+    path: &Path,
+    keys: &[PublicKey],
+    universe: Option<&UniverseSection>,
+    now: i64,
+) -> Result<LicenseReport, String> {
+    let contents = std::fs::read(path).map_err(|err| format!("Could not read file: {err}"))?;
+
+    // licgen writes base64 text by default, but can also write the raw bytes
+    let base64 = std::str::from_utf8(&contents)
+        .ok()
+        .and_then(|text| base64::decode(text.trim()).ok());
+    let (encoding, code) = match base64 {
+        Some(decoded) => ("base64", decoded),
+        None => ("binary", contents),
+    };
+
+    let (key, data) = keys
+        .iter()
+        .find_map(|key| {
+            let mut rsa = AWCryptRSA::default();
+            rsa.decode_public_key(&key.data).ok()?;
+            let data = AWRegLic::new(rsa)
+                .code_process_binary(&code, RSAKey::Public)
+                .ok()?;
+            Some((key, data))
+        })
+        .ok_or("None of the public keys could decrypt the license.")?;
+
+    let expired = i64::from(data.get_expiration_time()) <= now;
+    let mut warnings = Vec::new();
+    if expired {
+        warnings.push("The license has expired.".to_string());
+    }
+    if let Some(universe) = universe {
+        warnings.extend(check_universe(&data, universe));
+    }
+
+    Ok(LicenseReport {
+        file: path.to_path_buf(),
+        key: key.name.clone(),
+        encoding,
+        license_version: data.get_license_version(),
+        ip_address: data.get_ip_address(),
+        port: data.get_port(),
+        land_limit: data.get_land_limit(),
+        max_users: data.get_max_users(),
+        world_limit: data.get_world_limit(),
+        expiration_time: data.get_expiration_time(),
+        expired,
+        major_version: data.get_major_version(),
+        minor_version: data.get_minor_version(),
+        name: data.get_name().trim_end_matches('\0').to_string(),
+        can_have_bots: data.get_can_have_bots(),
+        warnings,
+    })
+}
+
+/// Clients compare the license with the address they connected to, and
+/// report error 471 if they differ.
+fn check_universe(data: &AWRegLicData, universe: &UniverseSection) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if data.get_ip_address() != universe.license_ip {
+        warnings.push(format!(
+            "The license is for IP {}, but the universe uses license_ip {}; clients will report error 471.",
+            data.get_ip_address(),
+            universe.license_ip
+        ));
+    }
+    if data.get_port() != u32::from(universe.port) {
+        warnings.push(format!(
+            "The license is for port {}, but the universe listens on port {}; clients will report error 471.",
+            data.get_port(),
+            universe.port
+        ));
+    }
+
+    warnings
+}
+
+fn describe_expiration(expiration_time: i32, now: i64) -> String {
+    if expiration_time == i32::MAX {
+        return "never".to_string();
+    }
+
+    let days = (i64::from(expiration_time) - now) / (24 * 60 * 60);
+    if i64::from(expiration_time) <= now {
+        format!("{expiration_time} (expired {} days ago)", -days)
+    } else {
+        format!("{expiration_time} (in {days} days)")
+    }
+}
+
+fn print_text(results: &[FileResult], now: i64) {
+    for (index, result) in results.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        match result {
+            FileResult::License(report) => {
+                println!(
+                    "{}: {} license, decrypted with the {} key",
+                    report.file.display(),
+                    report.encoding,
+                    report.key
+                );
+                println!("license_version: {}", report.license_version);
+                println!("ip_address: {}", report.ip_address);
+                println!("port: {}", report.port);
+                println!("land_limit: {}", report.land_limit);
+                println!("max_users: {}", report.max_users);
+                println!("world_limit: {}", report.world_limit);
+                println!(
+                    "expiration_time: {}",
+                    describe_expiration(report.expiration_time, now)
+                );
+                println!("major_version: {}", report.major_version);
+                println!("minor_version: {}", report.minor_version);
+                println!("name: {}", report.name);
+                println!("can_have_bots: {}", report.can_have_bots);
+                for warning in &report.warnings {
+                    println!("warning: {warning}");
+                }
+            }
+            FileResult::Error { file, error } => println!("{}: {error}", file.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_reports_mismatched_address() {
+        let universe = UniverseSection {
+            license_ip: Ipv4Addr::new(10, 0, 0, 1),
+            port: 6670,
+        };
+
+        let matching = AWRegLicData::default().set_ip_address(&universe.license_ip);
+        assert!(check_universe(&matching, &universe).is_empty());
+
+        let wrong_port = matching.set_port(5670);
+        assert_eq!(check_universe(&wrong_port, &universe).len(), 1);
+
+        let wrong_both = AWRegLicData::default().set_port(5670);
+        assert_eq!(check_universe(&wrong_both, &universe).len(), 2);
 
-    println!("{license_data}");
+        assert_eq!(describe_expiration(i32::MAX, 0), "never");
+        assert_eq!(describe_expiration(86_400 * 3, 0), "259200 (in 3 days)");
+    }
 }
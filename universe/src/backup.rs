@@ -0,0 +1,193 @@
+use std::{
+    path::{Path, PathBuf},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use aw_db::{DatabaseBackupError, DatabaseConfig, DatabaseType};
+
+use crate::{configuration::BackupConfig, timestamp};
+
+const SNAPSHOT_PREFIX: &str = "universe-";
+const SNAPSHOT_EXTENSION: &str = "db";
+
+/// Takes snapshots of the universe database on a fixed interval.
+///
+/// Snapshots are copied on a worker thread through a separate read-only
+/// connection, so a large database does not stall the main loop.
+pub struct BackupScheduler {
+    config: BackupConfig,
+    /// The internal database file, or `None` for an external database
+    source: Option<PathBuf>,
+    last_run: Instant,
+    running: Option<JoinHandle<Result<PathBuf, DatabaseBackupError>>>,
+}
+
+impl BackupScheduler {
+    pub fn new(config: BackupConfig, sql: &DatabaseConfig) -> Self {
+        let source = match sql.database_type {
+            DatabaseType::Internal => Some(PathBuf::from(&sql.sqlite_config.path)),
+            DatabaseType::External => None,
+        };
+
+        if source.is_none() && config.interval_minutes != 0 {
+            log::warn!("Scheduled backups are only supported for the internal database.");
+        }
+
+        Self {
+            config,
+            source,
+            last_run: Instant::now(),
+            running: None,
+        }
+    }
+
+    /// Start a snapshot if the configured interval has elapsed since the last
+    /// one, and report on a snapshot that has finished.
+    pub fn run_if_due(&mut self) {
+        if self.running.as_ref().is_some_and(JoinHandle::is_finished) {
+            self.finish();
+        }
+
+        if self.config.interval_minutes == 0 || self.running.is_some() {
+            return;
+        }
+
+        let Some(source) = self.source.clone() else {
+            return;
+        };
+
+        let interval = Duration::from_secs(self.config.interval_minutes.saturating_mul(60));
+        if self.last_run.elapsed() < interval {
+            return;
+        }
+        self.last_run = Instant::now();
+
+        let config = self.config.clone();
+        let spawned = std::thread::Builder::new()
+            .name("backup".to_string())
+            .spawn(move || take_snapshot(&config, |dest| aw_db::backup_sqlite(&source, dest)));
+
+        match spawned {
+            Ok(handle) => self.running = Some(handle),
+            Err(why) => log::error!("Could not start a scheduled database backup: {why}"),
+        }
+    }
+
+    /// Wait for a running snapshot to finish and report on it.
+    pub fn finish(&mut self) {
+        let Some(handle) = self.running.take() else {
+            return;
+        };
+
+        match handle.join() {
+            Ok(Ok(path)) => log::info!("Wrote database snapshot {}", path.display()),
+            Ok(Err(why)) => log::error!("Scheduled database backup failed: {why}"),
+            Err(_) => log::error!("Scheduled database backup panicked"),
+        }
+    }
+}
+
+/// Write a new snapshot into the backup directory using `backup`, then
+/// delete the oldest snapshots beyond the configured retention count.
+pub fn take_snapshot(
+    config: &BackupConfig,
+    backup: impl FnOnce(&Path) -> Result<(), DatabaseBackupError>,
+) -> Result<PathBuf, DatabaseBackupError> {
+    let directory = Path::new(&config.directory);
+    std::fs::create_dir_all(directory).map_err(DatabaseBackupError::Io)?;
+
+    let dest = snapshot_path(directory, timestamp::unix_epoch_timestamp_u64());
+    backup(&dest)?;
+
+    rotate_snapshots(directory, config.keep);
+
+    Ok(dest)
+}
+
+/// A path for a new snapshot taken at `timestamp`. Snapshots taken within
+/// the same second are told apart by a counter after the timestamp.
+fn snapshot_path(directory: &Path, timestamp: u64) -> PathBuf {
+    let mut path = directory.join(format!("{SNAPSHOT_PREFIX}{timestamp}.{SNAPSHOT_EXTENSION}"));
+    let mut counter = 1u32;
+    while path.exists() {
+        path = directory.join(format!(
+            "{SNAPSHOT_PREFIX}{timestamp}-{counter}.{SNAPSHOT_EXTENSION}"
+        ));
+        counter += 1;
+    }
+    path
+}
+
+/// Delete all but the newest `keep` snapshots in `directory`.
+fn rotate_snapshots(directory: &Path, keep: usize) {
+    let mut snapshots = list_snapshots(directory);
+    if snapshots.len() <= keep {
+        return;
+    }
+
+    // Oldest first
+    snapshots.sort();
+    let excess = snapshots.len() - keep;
+    for (_, path) in snapshots.into_iter().take(excess) {
+        match std::fs::remove_file(&path) {
+            Ok(()) => log::info!("Removed old database snapshot {}", path.display()),
+            Err(why) => log::warn!("Could not remove snapshot {}: {why}", path.display()),
+        }
+    }
+}
+
+/// Snapshots in `directory` along with the timestamp they were taken at and
+/// their counter within that second.
+fn list_snapshots(directory: &Path) -> Vec<((u64, u32), PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            if path.extension()? != SNAPSHOT_EXTENSION {
+                return None;
+            }
+            let stem = path.file_stem()?.to_str()?;
+            let name = stem.strip_prefix(SNAPSHOT_PREFIX)?;
+            let (timestamp, counter) = match name.split_once('-') {
+                Some((timestamp, counter)) => (timestamp, counter.parse::<u32>().ok()?),
+                None => (name, 0),
+            };
+            Some(((timestamp.parse::<u64>().ok()?, counter), path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_in_the_same_second_get_distinct_names() {
+        let directory =
+            std::env::temp_dir().join(format!("universe_snapshots_{}", std::process::id()));
+        std::fs::remove_dir_all(&directory).ok();
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for _ in 0..3 {
+            std::fs::write(snapshot_path(&directory, 100), b"").unwrap();
+        }
+        std::fs::write(snapshot_path(&directory, 99), b"").unwrap();
+
+        let mut snapshots = list_snapshots(&directory)
+            .into_iter()
+            .map(|(order, _)| order)
+            .collect::<Vec<_>>();
+        snapshots.sort();
+        assert_eq!(snapshots, [(99, 0), (100, 0), (100, 1), (100, 2)]);
+
+        rotate_snapshots(&directory, 2);
+        assert_eq!(list_snapshots(&directory).len(), 2);
+
+        std::fs::remove_dir_all(&directory).ok();
+    }
+}
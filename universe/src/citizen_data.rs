@@ -0,0 +1,385 @@
+use aw_db::DatabaseResult;
+use serde::Serialize;
+
+use crate::{
+    configuration::CitizenDeletionPolicy,
+    database::{
+        cav::CavQuery, citizen::CitizenQuery, contact::ContactQuery, license::LicenseQuery,
+        quota::WorldQuotaQuery, telegram::TelegramQuery, CavDB, CitizenDB, ContactDB, LicenseDB,
+        QuotaDB, TelegramDB, UniverseDatabase, WorldAccessDB,
+    },
+    timestamp::unix_epoch_timestamp_u64,
+};
+
+/// Delete a citizen along with the data that references them. What happens to
+/// their licenses and sent telegrams depends on `policy`.
+pub fn delete_citizen(
+    database: &UniverseDatabase,
+    citizen_id: u32,
+    policy: CitizenDeletionPolicy,
+    heir: u32,
+) -> DatabaseResult<()> {
+    if policy == CitizenDeletionPolicy::Reassign {
+        if heir == citizen_id {
+            log::error!(
+                "Citizen {citizen_id} cannot be deleted because they are the deletion heir"
+            );
+            return DatabaseResult::DatabaseError;
+        }
+
+        match database.citizen_by_number(heir) {
+            DatabaseResult::Ok(Some(_)) => {}
+            DatabaseResult::Ok(None) => {
+                log::error!("Deletion heir {heir} does not exist");
+                return DatabaseResult::DatabaseError;
+            }
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        }
+    }
+
+    // Either everything about the citizen goes, or nothing does
+    database.transaction(|| delete_citizen_rows(database, citizen_id, policy, heir))
+}
+
+fn delete_citizen_rows(
+    database: &UniverseDatabase,
+    citizen_id: u32,
+    policy: CitizenDeletionPolicy,
+    heir: u32,
+) -> DatabaseResult<()> {
+    if database.contact_delete_all(citizen_id).is_err()
+        || database.telegram_delete_to(citizen_id).is_err()
+        || database.cav_delete_citizen(citizen_id).is_err()
+        || database.world_quota_delete(citizen_id).is_err()
+        || database.world_access_delete_citizen(citizen_id).is_err()
+    {
+        return DatabaseResult::DatabaseError;
+    }
+
+    let r = match policy {
+        CitizenDeletionPolicy::Cascade => {
+            if database.telegram_delete_from(citizen_id).is_err() {
+                return DatabaseResult::DatabaseError;
+            }
+            if delete_owned_access_lists(database, citizen_id).is_err() {
+                return DatabaseResult::DatabaseError;
+            }
+            database.license_delete_by_owner(citizen_id)
+        }
+        CitizenDeletionPolicy::Reassign => database.license_reassign_owner(citizen_id, heir),
+    };
+
+    if r.is_err() {
+        return DatabaseResult::DatabaseError;
+    }
+
+    database.citizen_delete(citizen_id)
+}
+
+/// Delete the access lists of the licenses a citizen owns, before the
+/// licenses themselves are deleted.
+fn delete_owned_access_lists(database: &UniverseDatabase, citizen_id: u32) -> DatabaseResult<()> {
+    let licenses = match database.license_by_owner(citizen_id) {
+        DatabaseResult::Ok(licenses) => licenses,
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    };
+
+    for lic in &licenses {
+        if database.world_access_delete_license(lic.id).is_err() {
+            return DatabaseResult::DatabaseError;
+        }
+    }
+
+    DatabaseResult::Ok(())
+}
+
+/// Everything stored about a citizen. Passwords are left out.
+#[derive(Debug, Serialize)]
+pub struct CitizenExport {
+    pub exported_at: u64,
+    pub citizen: CitizenRecord,
+    pub contacts: Vec<ContactRecord>,
+    pub telegrams_received: Vec<TelegramRecord>,
+    pub telegrams_sent: Vec<TelegramRecord>,
+    pub cav: Vec<CavRecord>,
+    pub licenses: Vec<LicenseRecord>,
+    /// Quota an admin gave the citizen instead of the universe default
+    pub world_quota: Option<WorldQuotaRecord>,
+    /// Names of the worlds whose access list the citizen is on
+    pub world_access: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CitizenRecord {
+    pub id: u32,
+    pub name: String,
+    pub email: String,
+    pub comment: String,
+    pub url: String,
+    pub immigration: u64,
+    pub expiration: u64,
+    pub last_login: u64,
+    pub last_address: u32,
+    pub total_time: u32,
+    pub bot_limit: u32,
+    pub beta: bool,
+    pub enabled: bool,
+    pub trial: bool,
+    pub privacy: u32,
+    pub cav_enabled: bool,
+    pub cav_template: u32,
+}
+
+impl From<CitizenQuery> for CitizenRecord {
+    fn from(citizen: CitizenQuery) -> Self {
+        Self {
+            id: citizen.id,
+            name: citizen.name,
+            email: citizen.email,
+            comment: citizen.comment,
+            url: citizen.url,
+            immigration: citizen.immigration,
+            expiration: citizen.expiration,
+            last_login: citizen.last_login,
+            last_address: citizen.last_address,
+            total_time: citizen.total_time,
+            bot_limit: citizen.bot_limit,
+            beta: citizen.beta != 0,
+            enabled: citizen.enabled != 0,
+            trial: citizen.trial != 0,
+            privacy: citizen.privacy,
+            cav_enabled: citizen.cav_enabled != 0,
+            cav_template: citizen.cav_template,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContactRecord {
+    /// Citizen number of the contact, or 0 for the default options
+    pub contact: u32,
+    pub options: u32,
+}
+
+impl From<ContactQuery> for ContactRecord {
+    fn from(contact: ContactQuery) -> Self {
+        Self {
+            contact: contact.contact,
+            options: contact.options.bits(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TelegramRecord {
+    pub to: u32,
+    pub from: u32,
+    pub timestamp: u64,
+    pub message: String,
+    pub delivered: bool,
+}
+
+impl From<TelegramQuery> for TelegramRecord {
+    fn from(telegram: TelegramQuery) -> Self {
+        Self {
+            to: telegram.citizen,
+            from: telegram.from,
+            timestamp: telegram.timestamp,
+            message: telegram.message,
+            delivered: telegram.delivered != 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CavRecord {
+    pub citizen: u32,
+    pub template: u32,
+    pub keyframe1_scale: f64,
+    pub keyframe2_scale: f64,
+    pub height: f64,
+    pub skin_color: u32,
+    pub hair_color: u32,
+}
+
+impl From<CavQuery> for CavRecord {
+    fn from(cav: CavQuery) -> Self {
+        Self {
+            citizen: cav.citizen,
+            template: cav.template,
+            keyframe1_scale: cav.keyframe1_scale,
+            keyframe2_scale: cav.keyframe2_scale,
+            height: cav.height,
+            skin_color: cav.skin_color,
+            hair_color: cav.hair_color,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LicenseRecord {
+    pub name: String,
+    pub email: String,
+    pub comment: String,
+    pub creation: u64,
+    pub expiration: u64,
+    pub last_start: u64,
+    pub users: u32,
+    pub world_size: u32,
+}
+
+impl From<LicenseQuery> for LicenseRecord {
+    fn from(lic: LicenseQuery) -> Self {
+        Self {
+            name: lic.name,
+            email: lic.email,
+            comment: lic.comment,
+            creation: lic.creation,
+            expiration: lic.expiration,
+            last_start: lic.last_start,
+            users: lic.users,
+            world_size: lic.world_size,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorldQuotaRecord {
+    pub licenses: u32,
+    pub running_worlds: u32,
+}
+
+impl From<WorldQuotaQuery> for WorldQuotaRecord {
+    fn from(quota: WorldQuotaQuery) -> Self {
+        Self {
+            licenses: quota.licenses,
+            running_worlds: quota.running_worlds,
+        }
+    }
+}
+
+/// Gather everything stored about a citizen, or `None` if there is no such citizen.
+pub fn export_citizen(
+    database: &UniverseDatabase,
+    citizen_id: u32,
+) -> DatabaseResult<Option<CitizenExport>> {
+    let citizen = match database.citizen_by_number(citizen_id) {
+        DatabaseResult::Ok(Some(citizen)) => citizen,
+        DatabaseResult::Ok(None) => return DatabaseResult::Ok(None),
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    };
+
+    let (
+        DatabaseResult::Ok(contacts),
+        DatabaseResult::Ok(telegrams_received),
+        DatabaseResult::Ok(telegrams_sent),
+        DatabaseResult::Ok(cav),
+        DatabaseResult::Ok(licenses),
+        DatabaseResult::Ok(world_quota),
+        DatabaseResult::Ok(world_access),
+    ) = (
+        database.contact_get_all(citizen_id),
+        database.telegram_get_all(citizen_id),
+        database.telegram_get_sent(citizen_id),
+        database.cav_by_citizen(citizen_id),
+        database.license_by_owner(citizen_id),
+        database.world_quota_get(citizen_id),
+        database.world_access_by_citizen(citizen_id),
+    )
+    else {
+        return DatabaseResult::DatabaseError;
+    };
+
+    DatabaseResult::Ok(Some(CitizenExport {
+        exported_at: unix_epoch_timestamp_u64(),
+        citizen: citizen.into(),
+        contacts: contacts.into_iter().map(Into::into).collect(),
+        telegrams_received: telegrams_received.into_iter().map(Into::into).collect(),
+        telegrams_sent: telegrams_sent.into_iter().map(Into::into).collect(),
+        cav: cav.into_iter().map(Into::into).collect(),
+        licenses: licenses.into_iter().map(Into::into).collect(),
+        world_quota: world_quota.map(Into::into),
+        world_access,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::Config;
+
+    fn open_scratch(name: &str) -> (UniverseDatabase, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("universe_{name}_{}.db", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let mut config = Config::default();
+        config.sql.sqlite_config.path = path.display().to_string();
+        let database = UniverseDatabase::new(config.sql, &config.universe).unwrap();
+        (database, path)
+    }
+
+    fn ok<T>(r: DatabaseResult<T>) -> T {
+        match r {
+            DatabaseResult::Ok(x) => x,
+            DatabaseResult::DatabaseError => panic!("database error"),
+        }
+    }
+
+    fn license(name: &str, owner: u32) -> LicenseQuery {
+        LicenseQuery {
+            id: 0,
+            name: name.to_string(),
+            password: String::new(),
+            email: String::new(),
+            comment: String::new(),
+            creation: 0,
+            expiration: 0,
+            last_start: 0,
+            last_address: 0,
+            users: 10,
+            world_size: 50,
+            hidden: 0,
+            changed: 0,
+            tourists: 0,
+            voip: 0,
+            plugins: 0,
+            owner,
+        }
+    }
+
+    #[test]
+    fn export_has_what_deletion_removes() {
+        let (database, path) = open_scratch("export");
+
+        let mut citizen = ok(database.citizen_by_number(1)).unwrap();
+        citizen.id = 2;
+        citizen.name = "Visitor".to_string();
+        ok(database.citizen_add(&citizen));
+
+        for name in ["Home", "Away"] {
+            ok(database.license_add(&license(name, 1)));
+            let lic = ok(database.license_by_name(name)).unwrap();
+            ok(database.world_access_add(lic.id, 2));
+        }
+        let quota = WorldQuotaQuery {
+            citizen: 2,
+            licenses: 3,
+            running_worlds: 1,
+        };
+        ok(database.world_quota_set(&quota));
+
+        let export = ok(export_citizen(&database, 2)).unwrap();
+        assert_eq!(export.citizen.name, "Visitor");
+        assert_eq!(export.world_access, ["Away", "Home"]);
+        let world_quota = export.world_quota.unwrap();
+        assert_eq!(world_quota.licenses, 3);
+        assert_eq!(world_quota.running_worlds, 1);
+
+        let export = ok(export_citizen(&database, 1)).unwrap();
+        assert_eq!(export.licenses.len(), 2);
+        assert!(export.world_quota.is_none());
+        assert!(export.world_access.is_empty());
+
+        drop(database);
+        std::fs::remove_file(path).ok();
+    }
+}
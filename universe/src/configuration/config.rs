@@ -1,6 +1,7 @@
 use std::{env, net::Ipv4Addr, path::PathBuf};
 
 use super::configurator::run_configurator;
+use aw_core::{encoding::StringEncoding, DeserializeLimits};
 use aw_db::{DatabaseConfig, DatabaseType, MysqlConfig, SqliteConfig};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -10,6 +11,14 @@ use std::path::Path;
 pub struct Config {
     pub universe: UniverseConfig,
     pub sql: DatabaseConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub license: LicenseConfig,
+    #[serde(default)]
+    pub owner_license_changes: OwnerLicenseChanges,
+    #[serde(default)]
+    pub world_quota: WorldQuotaConfig,
 }
 
 /// Configuration section for the universe
@@ -23,6 +32,283 @@ pub struct UniverseConfig {
     pub allow_immigration: bool,
     pub connection_limit: u16,
     pub player_limit: u16,
+    /// What happens to a deleted citizen's licenses and sent telegrams
+    #[serde(default)]
+    pub citizen_deletion: CitizenDeletionPolicy,
+    /// Citizen that inherits a deleted citizen's licenses under the reassign policy
+    #[serde(default = "default_deletion_heir")]
+    pub deletion_heir: u32,
+    /// Log a warning for every packet that does not match the protocol schema
+    #[serde(default)]
+    pub validate_packets: bool,
+    /// File that the stream keys of every connection are appended to, for
+    /// decrypting captured traffic in Wireshark
+    #[serde(default)]
+    pub key_log_file: Option<String>,
+    /// Bytes that may be waiting to be sent to a connection before it is
+    /// disconnected for not keeping up
+    #[serde(default = "default_send_queue_limit")]
+    pub send_queue_limit: usize,
+    /// Largest size in bytes that one compressed packet from a client may
+    /// decompress to
+    #[serde(default = "default_max_decompressed_size")]
+    pub max_decompressed_size: usize,
+    /// Most variables in one packet from a client
+    #[serde(default = "default_max_packet_vars")]
+    pub max_packet_vars: u16,
+    /// Longest string variable in bytes that a client may send, including its
+    /// terminator
+    #[serde(default = "default_max_string_length")]
+    pub max_string_length: usize,
+    /// Minutes between logging traffic totals and the busiest connections,
+    /// or 0 to only log them on shutdown
+    #[serde(default)]
+    pub traffic_report_minutes: u64,
+    /// Encoding of strings sent to and from clients
+    #[serde(default)]
+    pub string_encoding: StringEncoding,
+    /// Browsers and bots of at least this build send and receive UTF-8
+    #[serde(default)]
+    pub utf8_min_build: Option<i32>,
+    /// Days before a world license expires that its owner and world server
+    /// are warned, or 0 to never warn
+    #[serde(default = "default_license_warning_days")]
+    pub license_warning_days: u64,
+    /// Days that renewing a world license extends it by
+    #[serde(default = "default_license_renewal_days")]
+    pub license_renewal_days: u64,
+    /// Minutes between samples of the users in every running world, or 0 to
+    /// not record world statistics
+    #[serde(default)]
+    pub world_stats_minutes: u64,
+    /// Days that samples are kept before they are combined into one entry
+    /// per world and day
+    #[serde(default = "default_world_stats_raw_days")]
+    pub world_stats_raw_days: u64,
+}
+
+impl UniverseConfig {
+    /// Limits on what clients may send, beyond which they are disconnected.
+    pub fn deserialize_limits(&self) -> DeserializeLimits {
+        DeserializeLimits {
+            max_decompressed_size: self.max_decompressed_size,
+            max_var_count: self.max_packet_vars,
+            max_string_length: self.max_string_length,
+        }
+    }
+
+    /// The string encoding to use with a client of the given build.
+    pub fn encoding_for_build(&self, build: i32) -> StringEncoding {
+        match self.utf8_min_build {
+            Some(min_build) if build >= min_build => StringEncoding::Utf8,
+            _ => self.string_encoding,
+        }
+    }
+}
+
+/// How the data of a deleted citizen is handled. Contacts in both directions,
+/// received telegrams and CAV rows are always removed.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CitizenDeletionPolicy {
+    /// Also remove the citizen's licenses and the telegrams they sent
+    Cascade,
+    /// Give the citizen's licenses to the deletion heir and keep the telegrams they sent
+    #[default]
+    Reassign,
+}
+
+fn default_deletion_heir() -> u32 {
+    1
+}
+
+fn default_send_queue_limit() -> usize {
+    aw_core::DEFAULT_SEND_QUEUE_LIMIT
+}
+
+fn default_max_decompressed_size() -> usize {
+    DeserializeLimits::default().max_decompressed_size
+}
+
+fn default_max_packet_vars() -> u16 {
+    DeserializeLimits::default().max_var_count
+}
+
+fn default_max_string_length() -> usize {
+    DeserializeLimits::default().max_string_length
+}
+
+fn default_license_warning_days() -> u64 {
+    14
+}
+
+fn default_license_renewal_days() -> u64 {
+    365
+}
+
+fn default_world_stats_raw_days() -> u64 {
+    7
+}
+
+/// Configuration section for snapshots of the internal database
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BackupConfig {
+    /// Directory that snapshots are written to
+    pub directory: String,
+    /// Minutes between scheduled snapshots, or 0 to only take them on demand
+    pub interval_minutes: u64,
+    /// Number of snapshots to keep before the oldest are deleted
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            directory: "backups".to_string(),
+            interval_minutes: 0,
+            keep: 7,
+        }
+    }
+}
+
+/// Configuration section for which fields of their own world licenses
+/// owners may change. Everything else, such as the user limit and world
+/// size, can only be changed by admins.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct OwnerLicenseChanges {
+    pub password: bool,
+    pub email: bool,
+    pub comment: bool,
+    pub hidden: bool,
+    pub tourists: bool,
+}
+
+impl Default for OwnerLicenseChanges {
+    fn default() -> Self {
+        Self {
+            password: true,
+            email: true,
+            comment: true,
+            hidden: true,
+            tourists: true,
+        }
+    }
+}
+
+/// Configuration section for how many worlds citizens may have. Admins can
+/// give single citizens a different quota, and are not limited themselves.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct WorldQuotaConfig {
+    /// Licenses a citizen may hold and still add another one themselves, or
+    /// 0 to only let admins add licenses
+    pub licenses: u32,
+    /// Worlds of a citizen's licenses that may run at once, or 0 for no limit
+    pub running_worlds: u32,
+    /// User limit of licenses that citizens add themselves
+    pub users: u32,
+    /// World size of licenses that citizens add themselves
+    pub world_size: u32,
+}
+
+impl Default for WorldQuotaConfig {
+    fn default() -> Self {
+        Self {
+            licenses: 0,
+            running_worlds: 0,
+            users: 20,
+            world_size: 50,
+        }
+    }
+}
+
+/// Configuration section for the licenses sent to clients at login
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LicenseConfig {
+    /// Rules tried in order for each client build. Builds that no rule
+    /// matches get a license signed with the bundled AW key.
+    #[serde(default = "default_license_rules")]
+    pub rules: Vec<LicenseRule>,
+}
+
+impl Default for LicenseConfig {
+    fn default() -> Self {
+        Self {
+            rules: default_license_rules(),
+        }
+    }
+}
+
+fn default_license_rules() -> Vec<LicenseRule> {
+    vec![
+        LicenseRule {
+            // Vortex 5.1, Vortex 5.1 SDK and Miuchiz R7
+            builds: vec![1217, 85, 2007],
+            ..LicenseRule::with_key(BUNDLED_VORTEX_KEY)
+        },
+        LicenseRule::with_key(BUNDLED_AW_KEY),
+    ]
+}
+
+/// Name of the bundled key used by regular AW clients
+pub const BUNDLED_AW_KEY: &str = "aw";
+
+/// Name of the bundled key used by Vortex and Miuchiz clients
+pub const BUNDLED_VORTEX_KEY: &str = "vortex";
+
+/// Which license to send to some client builds. The license always has the
+/// universe's license_ip and port; every other field can be overridden.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct LicenseRule {
+    /// Builds the rule applies to. A rule without builds or build ranges
+    /// applies to every build.
+    #[serde(default)]
+    pub builds: Vec<i32>,
+    /// Inclusive ranges of builds the rule applies to, e.g. [[2000, 2099]]
+    #[serde(default)]
+    pub build_ranges: Vec<(i32, i32)>,
+    /// "aw" or "vortex" for a bundled private key, or the path of a private key file
+    pub key: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub license_version: Option<u32>,
+    #[serde(default)]
+    pub land_limit: Option<u32>,
+    #[serde(default)]
+    pub max_users: Option<u32>,
+    #[serde(default)]
+    pub world_limit: Option<u32>,
+    /// Unix timestamp the license expires at
+    #[serde(default)]
+    pub expiration_time: Option<i32>,
+    #[serde(default)]
+    pub major_version: Option<u16>,
+    #[serde(default)]
+    pub minor_version: Option<u16>,
+    #[serde(default)]
+    pub can_have_bots: Option<bool>,
+}
+
+impl LicenseRule {
+    /// Rule for every build that signs with `key` and overrides nothing.
+    pub fn with_key(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the rule applies to a client build.
+    pub fn matches(&self, build: i32) -> bool {
+        if self.builds.is_empty() && self.build_ranges.is_empty() {
+            return true;
+        }
+
+        self.builds.contains(&build)
+            || self
+                .build_ranges
+                .iter()
+                .any(|(min, max)| (*min..=*max).contains(&build))
+    }
 }
 
 impl Config {
@@ -69,6 +355,21 @@ impl Default for Config {
                 allow_immigration: true,
                 connection_limit: 200,
                 player_limit: 100,
+                citizen_deletion: CitizenDeletionPolicy::default(),
+                deletion_heir: default_deletion_heir(),
+                validate_packets: false,
+                key_log_file: None,
+                send_queue_limit: default_send_queue_limit(),
+                max_decompressed_size: default_max_decompressed_size(),
+                max_packet_vars: default_max_packet_vars(),
+                max_string_length: default_max_string_length(),
+                traffic_report_minutes: 0,
+                string_encoding: StringEncoding::default(),
+                utf8_min_build: None,
+                license_warning_days: default_license_warning_days(),
+                license_renewal_days: default_license_renewal_days(),
+                world_stats_minutes: 0,
+                world_stats_raw_days: default_world_stats_raw_days(),
             },
             sql: DatabaseConfig {
                 database_type: DatabaseType::Internal,
@@ -88,7 +389,12 @@ impl Default for Config {
                         path_str
                     },
                 },
+                slow_query_ms: 100,
             },
+            backup: BackupConfig::default(),
+            license: LicenseConfig::default(),
+            owner_license_changes: OwnerLicenseChanges::default(),
+            world_quota: WorldQuotaConfig::default(),
         }
     }
 }
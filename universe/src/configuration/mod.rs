@@ -1,4 +1,7 @@
 mod config;
-pub use config::{Config, UniverseConfig};
+pub use config::{
+    BackupConfig, CitizenDeletionPolicy, Config, LicenseConfig, LicenseRule, OwnerLicenseChanges,
+    UniverseConfig, WorldQuotaConfig, BUNDLED_AW_KEY, BUNDLED_VORTEX_KEY,
+};
 
 mod configurator;
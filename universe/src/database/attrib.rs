@@ -94,6 +94,8 @@ impl AttribDB for UniverseDatabase {
     }
 
     fn attrib_set(&self, attribute_id: Attribute, value: &str) -> DatabaseResult<()> {
+        self.cache.invalidate_attributes();
+
         // Check if attribute is already in the database
         let rows = match self.db.exec(
             r"SELECT * FROM awu_attrib WHERE ID=?",
@@ -137,6 +139,10 @@ impl AttribDB for UniverseDatabase {
     }
 
     fn attrib_get(&self) -> DatabaseResult<HashMap<Attribute, String>> {
+        if let Some(attributes) = self.cache.attributes() {
+            return DatabaseResult::Ok(attributes);
+        }
+
         let mut result = HashMap::<Attribute, String>::new();
 
         // Get all attributes from database
@@ -170,6 +176,8 @@ impl AttribDB for UniverseDatabase {
             }
         }
 
+        self.cache.store_attributes(&result);
+
         DatabaseResult::Ok(result)
     }
 }
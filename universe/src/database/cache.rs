@@ -0,0 +1,216 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+};
+
+use super::{attrib::Attribute, citizen::CitizenQuery, contact::ContactQuery};
+
+/// Hit and miss counts for one kind of cached data.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheCounter {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheCounter {
+    fn hit(counter: &Cell<Self>) {
+        let mut c = counter.get();
+        c.hits += 1;
+        counter.set(c);
+    }
+
+    fn miss(counter: &Cell<Self>) {
+        let mut c = counter.get();
+        c.misses += 1;
+        counter.set(c);
+    }
+}
+
+impl fmt::Display for CacheCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} hits / {} misses", self.hits, self.misses)
+    }
+}
+
+/// Snapshot of the cache counters for each kind of cached data.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub attributes: CacheCounter,
+    pub citizens: CacheCounter,
+    pub contacts: CacheCounter,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "attributes: {}, citizens: {}, contacts: {}",
+            self.attributes, self.citizens, self.contacts
+        )
+    }
+}
+
+/// In-process read-through cache for data that is read far more often than it
+/// is written. Entries are filled on a miss and dropped by the write paths of
+/// the database traits.
+#[derive(Default)]
+pub struct DatabaseCache {
+    attributes: RefCell<Option<HashMap<Attribute, String>>>,
+    citizens: RefCell<HashMap<u32, CitizenQuery>>,
+    /// Name (as it was looked up) to citizen ID
+    citizen_names: RefCell<HashMap<String, u32>>,
+    /// All contact rows of a citizen, keyed by the citizen's ID
+    contacts: RefCell<HashMap<u32, Vec<ContactQuery>>>,
+    attributes_counter: Cell<CacheCounter>,
+    citizens_counter: Cell<CacheCounter>,
+    contacts_counter: Cell<CacheCounter>,
+}
+
+impl DatabaseCache {
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            attributes: self.attributes_counter.get(),
+            citizens: self.citizens_counter.get(),
+            contacts: self.contacts_counter.get(),
+        }
+    }
+
+    pub fn attributes(&self) -> Option<HashMap<Attribute, String>> {
+        let cached = self.attributes.borrow().clone();
+        match cached {
+            Some(_) => CacheCounter::hit(&self.attributes_counter),
+            None => CacheCounter::miss(&self.attributes_counter),
+        }
+        cached
+    }
+
+    pub fn store_attributes(&self, attributes: &HashMap<Attribute, String>) {
+        *self.attributes.borrow_mut() = Some(attributes.clone());
+    }
+
+    pub fn invalidate_attributes(&self) {
+        *self.attributes.borrow_mut() = None;
+    }
+
+    pub fn citizen_by_number(&self, citizen_id: u32) -> Option<CitizenQuery> {
+        let cached = self.citizens.borrow().get(&citizen_id).cloned();
+        match cached {
+            Some(_) => CacheCounter::hit(&self.citizens_counter),
+            None => CacheCounter::miss(&self.citizens_counter),
+        }
+        cached
+    }
+
+    pub fn citizen_by_name(&self, name: &str) -> Option<CitizenQuery> {
+        let cached = self
+            .citizen_names
+            .borrow()
+            .get(name)
+            .and_then(|id| self.citizens.borrow().get(id).cloned());
+        match cached {
+            Some(_) => CacheCounter::hit(&self.citizens_counter),
+            None => CacheCounter::miss(&self.citizens_counter),
+        }
+        cached
+    }
+
+    /// Remember a citizen, optionally under the name it was looked up by, which
+    /// may differ in case from the stored name.
+    pub fn store_citizen(&self, citizen: &CitizenQuery, looked_up_name: Option<&str>) {
+        let mut names = self.citizen_names.borrow_mut();
+        names.insert(citizen.name.clone(), citizen.id);
+        if let Some(name) = looked_up_name {
+            names.insert(name.to_string(), citizen.id);
+        }
+        self.citizens
+            .borrow_mut()
+            .insert(citizen.id, citizen.clone());
+    }
+
+    pub fn invalidate_citizen(&self, citizen_id: u32) {
+        self.citizens.borrow_mut().remove(&citizen_id);
+        self.citizen_names
+            .borrow_mut()
+            .retain(|_, id| *id != citizen_id);
+    }
+
+    pub fn contacts(&self, citizen_id: u32) -> Option<Vec<ContactQuery>> {
+        let cached = self.contacts.borrow().get(&citizen_id).cloned();
+        match cached {
+            Some(_) => CacheCounter::hit(&self.contacts_counter),
+            None => CacheCounter::miss(&self.contacts_counter),
+        }
+        cached
+    }
+
+    pub fn store_contacts(&self, citizen_id: u32, contacts: &[ContactQuery]) {
+        self.contacts
+            .borrow_mut()
+            .insert(citizen_id, contacts.to_vec());
+    }
+
+    pub fn invalidate_contacts(&self, citizen_id: u32) {
+        self.contacts.borrow_mut().remove(&citizen_id);
+    }
+
+    pub fn invalidate_all_contacts(&self) {
+        self.contacts.borrow_mut().clear();
+    }
+
+    /// Drop every entry, keeping the counters.
+    pub fn clear(&self) {
+        self.invalidate_attributes();
+        self.citizens.borrow_mut().clear();
+        self.citizen_names.borrow_mut().clear();
+        self.invalidate_all_contacts();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn citizen(id: u32, name: &str) -> CitizenQuery {
+        CitizenQuery {
+            id,
+            changed: 0,
+            name: name.to_string(),
+            password: String::new(),
+            email: String::new(),
+            priv_pass: String::new(),
+            comment: String::new(),
+            url: String::new(),
+            immigration: 0,
+            expiration: 0,
+            last_login: 0,
+            last_address: 0,
+            total_time: 0,
+            bot_limit: 0,
+            beta: 0,
+            cav_enabled: 0,
+            cav_template: 0,
+            enabled: 1,
+            privacy: 0,
+            trial: 0,
+        }
+    }
+
+    #[test]
+    fn citizen_invalidation_drops_all_names() {
+        let cache = DatabaseCache::default();
+        assert!(cache.citizen_by_number(2).is_none());
+
+        cache.store_citizen(&citizen(2, "Bob"), Some("bob"));
+        assert_eq!(cache.citizen_by_name("bob").map(|c| c.id), Some(2));
+        assert_eq!(cache.citizen_by_name("Bob").map(|c| c.id), Some(2));
+
+        cache.invalidate_citizen(2);
+        assert!(cache.citizen_by_name("bob").is_none());
+        assert!(cache.citizen_by_number(2).is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.citizens.hits, 2);
+        assert_eq!(stats.citizens.misses, 3);
+    }
+}
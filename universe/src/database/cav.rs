@@ -1,9 +1,22 @@
-use aw_db::DatabaseResult;
+use aw_db::{aw_params, DatabaseResult, Row};
 
 use super::UniverseDatabase;
 
+#[derive(Debug, Clone)]
+pub struct CavQuery {
+    pub citizen: u32,
+    pub template: u32,
+    pub keyframe1_scale: f64,
+    pub keyframe2_scale: f64,
+    pub height: f64,
+    pub skin_color: u32,
+    pub hair_color: u32,
+}
+
 pub trait CavDB {
     fn init_cav(&self) -> DatabaseResult<()>;
+    fn cav_by_citizen(&self, citizen_id: u32) -> DatabaseResult<Vec<CavQuery>>;
+    fn cav_delete_citizen(&self, citizen_id: u32) -> DatabaseResult<()>;
 }
 
 impl CavDB for UniverseDatabase {
@@ -52,4 +65,90 @@ impl CavDB for UniverseDatabase {
 
         DatabaseResult::Ok(())
     }
+
+    fn cav_by_citizen(&self, citizen_id: u32) -> DatabaseResult<Vec<CavQuery>> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_cav WHERE Citizen=?;",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut result = Vec::<CavQuery>::new();
+        for row in &rows {
+            match fetch_cav(row) {
+                DatabaseResult::Ok(cav) => result.push(cav),
+                DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(result)
+    }
+
+    fn cav_delete_citizen(&self, citizen_id: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_cav WHERE Citizen=?;",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+}
+
+fn fetch_cav(row: &Row) -> DatabaseResult<CavQuery> {
+    let citizen = match row.fetch_int("Citizen").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let template = match row.fetch_int("Template").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let keyframe1_scale = match row.fetch_float("Keyframe1Scale") {
+        Some(x) => x,
+        None => return DatabaseResult::DatabaseError,
+    };
+
+    let keyframe2_scale = match row.fetch_float("Keyframe2Scale") {
+        Some(x) => x,
+        None => return DatabaseResult::DatabaseError,
+    };
+
+    let height = match row.fetch_float("Height") {
+        Some(x) => x,
+        None => return DatabaseResult::DatabaseError,
+    };
+
+    // Colors are stored as signed integers
+    let skin_color = match row.fetch_int("SkinColor").map(|x| x as u32) {
+        Some(x) => x,
+        None => return DatabaseResult::DatabaseError,
+    };
+
+    let hair_color = match row.fetch_int("HairColor").map(|x| x as u32) {
+        Some(x) => x,
+        None => return DatabaseResult::DatabaseError,
+    };
+
+    DatabaseResult::Ok(CavQuery {
+        citizen,
+        template,
+        keyframe1_scale,
+        keyframe2_scale,
+        height,
+        skin_color,
+        hair_color,
+    })
 }
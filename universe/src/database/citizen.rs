@@ -1,10 +1,10 @@
 use aw_db::{aw_params, DatabaseResult, Row};
 
-use crate::timestamp::unix_epoch_timestamp_u32;
+use crate::timestamp::unix_epoch_timestamp_u64;
 
 use super::UniverseDatabase;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CitizenQuery {
     pub id: u32,
     pub changed: u32,
@@ -14,9 +14,9 @@ pub struct CitizenQuery {
     pub priv_pass: String,
     pub comment: String,
     pub url: String,
-    pub immigration: u32,
-    pub expiration: u32,
-    pub last_login: u32,
+    pub immigration: u64,
+    pub expiration: u64,
+    pub last_login: u64,
     pub last_address: u32,
     pub total_time: u32,
     pub bot_limit: u32,
@@ -52,9 +52,9 @@ impl CitizenDB for UniverseDatabase {
             PrivPass varchar(255) NOT NULL default '', 
             Comment varchar(255) NOT NULL default '', 
             URL varchar(255) NOT NULL default '', 
-            Immigration INTEGER NOT NULL default '0', 
-            Expiration INTEGER NOT NULL default '0', 
-            LastLogin INTEGER NOT NULL default '0', 
+            Immigration BIGINT NOT NULL default '0', 
+            Expiration BIGINT NOT NULL default '0', 
+            LastLogin BIGINT NOT NULL default '0', 
             LastAddress INTEGER NOT NULL default '0', 
             TotalTime INTEGER NOT NULL default '0', 
             BotLimit INTEGER NOT NULL default '0', 
@@ -78,7 +78,7 @@ impl CitizenDB for UniverseDatabase {
             DatabaseResult::Ok(Some(_)) => { /* Administrator exists, no work to be done */ }
             DatabaseResult::Ok(None) => {
                 // Administrator does not exist yet - create it
-                let now = unix_epoch_timestamp_u32();
+                let now = unix_epoch_timestamp_u64();
 
                 let admin = CitizenQuery {
                     id: 1,
@@ -117,6 +117,10 @@ impl CitizenDB for UniverseDatabase {
     }
 
     fn citizen_by_name(&self, name: &str) -> DatabaseResult<Option<CitizenQuery>> {
+        if let Some(citizen) = self.cache.citizen_by_name(name) {
+            return DatabaseResult::Ok(Some(citizen));
+        }
+
         let rows = match self
             .db
             .exec("SELECT * FROM awu_citizen WHERE Name=?", aw_params!(name))
@@ -137,12 +141,19 @@ impl CitizenDB for UniverseDatabase {
         };
 
         match fetch_citizen(user) {
-            DatabaseResult::Ok(user) => DatabaseResult::Ok(Some(user)),
+            DatabaseResult::Ok(user) => {
+                self.cache.store_citizen(&user, Some(name));
+                DatabaseResult::Ok(Some(user))
+            }
             DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
         }
     }
 
     fn citizen_by_number(&self, citizen_id: u32) -> DatabaseResult<Option<CitizenQuery>> {
+        if let Some(citizen) = self.cache.citizen_by_number(citizen_id) {
+            return DatabaseResult::Ok(Some(citizen));
+        }
+
         let rows = match self.db.exec(
             r"SELECT * FROM awu_citizen WHERE ID=?",
             aw_params!(citizen_id),
@@ -163,12 +174,17 @@ impl CitizenDB for UniverseDatabase {
         };
 
         match fetch_citizen(user) {
-            DatabaseResult::Ok(user) => DatabaseResult::Ok(Some(user)),
+            DatabaseResult::Ok(user) => {
+                self.cache.store_citizen(&user, None);
+                DatabaseResult::Ok(Some(user))
+            }
             DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
         }
     }
 
     fn citizen_add(&self, citizen: &CitizenQuery) -> DatabaseResult<()> {
+        self.cache.invalidate_citizen(citizen.id);
+
         let r = self.db.exec(
             r"INSERT INTO awu_citizen(
                 ID, Immigration, Expiration, LastLogin, LastAddress, TotalTime, 
@@ -226,6 +242,7 @@ impl CitizenDB for UniverseDatabase {
         };
 
         citizen.id = id;
+        self.cache.invalidate_citizen(citizen.id);
 
         let r = self.db.exec(
             r"INSERT INTO awu_citizen(
@@ -265,6 +282,8 @@ impl CitizenDB for UniverseDatabase {
     }
 
     fn citizen_change(&self, citizen: &CitizenQuery) -> DatabaseResult<()> {
+        self.cache.invalidate_citizen(citizen.id);
+
         let r = self.db.exec(
             r"UPDATE awu_citizen SET Changed=NOT Changed,
                 Immigration=?, Expiration=?, LastLogin=?, 
@@ -304,6 +323,8 @@ impl CitizenDB for UniverseDatabase {
     }
 
     fn citizen_delete(&self, citizen_id: u32) -> DatabaseResult<()> {
+        self.cache.invalidate_citizen(citizen_id);
+
         let r = self.db.exec(
             r"DELETE FROM awu_citizen WHERE ID=?;",
             aw_params! {
@@ -362,17 +383,17 @@ fn fetch_citizen(row: &Row) -> DatabaseResult<CitizenQuery> {
         None => return DatabaseResult::DatabaseError,
     };
 
-    let immigration = match row.fetch_int("Immigration").map(u32::try_from) {
+    let immigration = match row.fetch_int("Immigration").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
 
-    let expiration = match row.fetch_int("Expiration").map(u32::try_from) {
+    let expiration = match row.fetch_int("Expiration").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
 
-    let last_login = match row.fetch_int("LastLogin").map(u32::try_from) {
+    let last_login = match row.fetch_int("LastLogin").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
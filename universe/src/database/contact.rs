@@ -227,7 +227,7 @@ impl ContactOptions {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ContactQuery {
     pub citizen: u32,
     pub contact: u32,
@@ -258,6 +258,7 @@ pub trait ContactDB {
     fn contact_joins_allowed(&self, citizen_id: u32, contact_id: u32) -> DatabaseResult<bool>;
     fn contact_invites_allowed(&self, citizen_id: u32, contact_id: u32) -> DatabaseResult<bool>;
     fn contact_delete(&self, citizen_id: u32, contact_id: u32) -> DatabaseResult<()>;
+    fn contact_delete_all(&self, citizen_id: u32) -> DatabaseResult<()>;
 }
 
 impl ContactDB for UniverseDatabase {
@@ -283,6 +284,8 @@ impl ContactDB for UniverseDatabase {
     }
 
     fn contact_set(&self, citizen_id: u32, contact_id: u32, options: u32) -> DatabaseResult<()> {
+        self.cache.invalidate_contacts(citizen_id);
+
         // Check if contact pair is already in the database
         let r = self.db.exec(
             r"SELECT * FROM awu_contact WHERE Citizen=? AND Contact=?;",
@@ -331,31 +334,25 @@ impl ContactDB for UniverseDatabase {
         citizen_id: u32,
         contact_id: u32,
     ) -> DatabaseResult<Option<ContactQuery>> {
-        let r = self.db.exec(
-            r"SELECT * FROM awu_contact WHERE Citizen=? AND Contact=?;",
-            aw_params! {
-                citizen_id,
-                contact_id
-            },
-        );
-
-        let rows = match r {
-            DatabaseResult::Ok(rows) => rows,
+        // Read through the citizen's full contact list so that it is cached for
+        // subsequent lookups of any of their contacts.
+        let contacts = match self.contact_get_all(citizen_id) {
+            DatabaseResult::Ok(contacts) => contacts,
             DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
         };
 
-        let Some(contact) = rows.first() else {
-            // No such contact
-            return DatabaseResult::Ok(None);
-        };
-
-        match fetch_contact(contact) {
-            DatabaseResult::Ok(contact) => DatabaseResult::Ok(Some(contact)),
-            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
-        }
+        DatabaseResult::Ok(
+            contacts
+                .into_iter()
+                .find(|contact| contact.contact == contact_id),
+        )
     }
 
     fn contact_get_all(&self, citizen_id: u32) -> DatabaseResult<Vec<ContactQuery>> {
+        if let Some(contacts) = self.cache.contacts(citizen_id) {
+            return DatabaseResult::Ok(contacts);
+        }
+
         let r = self.db.exec(
             r"SELECT * FROM awu_contact WHERE Citizen=?;",
             aw_params! {
@@ -376,6 +373,8 @@ impl ContactDB for UniverseDatabase {
             }
         }
 
+        self.cache.store_contacts(citizen_id, &result);
+
         DatabaseResult::Ok(result)
     }
 
@@ -556,6 +555,8 @@ impl ContactDB for UniverseDatabase {
     }
 
     fn contact_delete(&self, citizen_id: u32, contact_id: u32) -> DatabaseResult<()> {
+        self.cache.invalidate_contacts(citizen_id);
+
         let r = self.db.exec(
             r"DELETE FROM awu_contact WHERE Citizen=?  AND Contact=?;",
             aw_params! {
@@ -569,6 +570,24 @@ impl ContactDB for UniverseDatabase {
             DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
         }
     }
+
+    fn contact_delete_all(&self, citizen_id: u32) -> DatabaseResult<()> {
+        // Other citizens' lists can hold this citizen too
+        self.cache.invalidate_all_contacts();
+
+        let r = self.db.exec(
+            r"DELETE FROM awu_contact WHERE Citizen=? OR Contact=?;",
+            aw_params! {
+                citizen_id,
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
 }
 
 fn fetch_contact(row: &Row) -> DatabaseResult<ContactQuery> {
@@ -7,21 +7,21 @@ pub trait EjectDB {
     fn ejection_set(
         &self,
         address: u32,
-        expiration: u32,
-        creation: u32,
+        expiration: u64,
+        creation: u64,
         comment: &str,
     ) -> DatabaseResult<()>;
     fn ejection_lookup(&self, address: u32) -> DatabaseResult<Option<EjectionQuery>>;
     fn ejection_next(&self, address: u32) -> DatabaseResult<Option<EjectionQuery>>;
     fn ejection_prev(&self, address: u32) -> DatabaseResult<Option<EjectionQuery>>;
     fn ejection_delete(&self, address: u32) -> DatabaseResult<()>;
-    fn ejection_clean(&self, timestamp: u32) -> DatabaseResult<()>;
+    fn ejection_clean(&self, timestamp: u64) -> DatabaseResult<()>;
 }
 
 pub struct EjectionQuery {
     pub address: u32,
-    pub expiration: u32,
-    pub creation: u32,
+    pub expiration: u64,
+    pub creation: u64,
     pub comment: String,
 }
 
@@ -33,8 +33,8 @@ impl EjectDB for UniverseDatabase {
             format!(
                 r"CREATE TABLE IF NOT EXISTS awu_eject ( 
                 ID INTEGER PRIMARY KEY {auto_increment_not_null}, 
-                Expiration INTEGER NOT NULL default '0', 
-                Creation INTEGER NOT NULL default '0', 
+                Expiration BIGINT NOT NULL default '0', 
+                Creation BIGINT NOT NULL default '0', 
                 Address INTEGER {unsigned} NOT NULL default '0', 
                 Comment varchar(255) NOT NULL default '', 
                 Changed tinyint(1) NOT NULL default '0'
@@ -52,8 +52,8 @@ impl EjectDB for UniverseDatabase {
     fn ejection_set(
         &self,
         address: u32,
-        expiration: u32,
-        creation: u32,
+        expiration: u64,
+        creation: u64,
         comment: &str,
     ) -> DatabaseResult<()> {
         // Check if ejection is already in the database
@@ -187,7 +187,7 @@ impl EjectDB for UniverseDatabase {
         }
     }
 
-    fn ejection_clean(&self, timestamp: u32) -> DatabaseResult<()> {
+    fn ejection_clean(&self, timestamp: u64) -> DatabaseResult<()> {
         let r = self.db.exec(
             r"DELETE FROM awu_eject WHERE Expiration>0 AND Expiration<?;",
             aw_params! {
@@ -208,12 +208,12 @@ fn fetch_ejection(row: &Row) -> DatabaseResult<EjectionQuery> {
         _ => return DatabaseResult::DatabaseError,
     };
 
-    let creation = match row.fetch_int("Creation").map(u32::try_from) {
+    let creation = match row.fetch_int("Creation").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
 
-    let expiration = match row.fetch_int("Expiration").map(u32::try_from) {
+    let expiration = match row.fetch_int("Expiration").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
@@ -1,6 +1,6 @@
 use aw_db::{aw_params, DatabaseResult, Row};
 
-use crate::timestamp::unix_epoch_timestamp_u32;
+use crate::timestamp::unix_epoch_timestamp_u64;
 
 use super::UniverseDatabase;
 
@@ -11,9 +11,9 @@ pub struct LicenseQuery {
     pub password: String,
     pub email: String,
     pub comment: String,
-    pub creation: u32,
-    pub expiration: u32,
-    pub last_start: u32,
+    pub creation: u64,
+    pub expiration: u64,
+    pub last_start: u64,
     pub last_address: u32,
     pub users: u32,
     pub world_size: u32,
@@ -22,16 +22,71 @@ pub struct LicenseQuery {
     pub tourists: u32,
     pub voip: u32,
     pub plugins: u32,
+    /// Citizen who owns the world, or 0 if it has no owner
+    pub owner: u32,
+}
+
+/// Something that happened to a license, kept in awu_license_history.
+#[derive(Debug)]
+pub struct LicenseHistoryQuery {
+    pub timestamp: u64,
+    /// What happened, e.g. "renew" or "change"
+    pub event: String,
+    pub old_expiration: u64,
+    pub new_expiration: u64,
+}
+
+/// Which world servers may start a license's world.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerRestrictionQuery {
+    /// Comma separated IPv4 addresses and CIDR blocks, or empty to allow any host
+    pub allowed_addresses: String,
+    /// Hex of the public key the world server must prove it holds, or empty
+    /// for any world server
+    pub server_key: String,
 }
 
 pub trait LicenseDB {
     fn init_license(&self) -> DatabaseResult<()>;
+    fn init_license_history(&self) -> DatabaseResult<()>;
     fn license_by_name(&self, name: &str) -> DatabaseResult<Option<LicenseQuery>>;
     fn license_add(&self, lic: &LicenseQuery) -> DatabaseResult<()>;
     fn license_next(&self, name: &str) -> DatabaseResult<Option<LicenseQuery>>;
     fn license_prev(&self, name: &str) -> DatabaseResult<Option<LicenseQuery>>;
+    fn license_next_owned(&self, name: &str, owner: u32) -> DatabaseResult<Option<LicenseQuery>>;
+    fn license_prev_owned(&self, name: &str, owner: u32) -> DatabaseResult<Option<LicenseQuery>>;
+    fn license_set_owner(&self, license_id: u32, owner: u32) -> DatabaseResult<()>;
     fn license_change(&self, lic: &LicenseQuery) -> DatabaseResult<()>;
     fn license_delete(&self, name: &str) -> DatabaseResult<()>;
+    fn license_by_owner(&self, citizen_id: u32) -> DatabaseResult<Vec<LicenseQuery>>;
+    fn license_all(&self) -> DatabaseResult<Vec<LicenseQuery>>;
+    fn license_delete_by_owner(&self, citizen_id: u32) -> DatabaseResult<()>;
+    fn license_reassign_owner(&self, from_citizen: u32, to_citizen: u32) -> DatabaseResult<()>;
+    fn license_expiring(&self, before: u64) -> DatabaseResult<Vec<LicenseQuery>>;
+    fn license_set_expiry_notified(&self, license_id: u32, expiration: u64) -> DatabaseResult<()>;
+    fn license_renew(
+        &self,
+        lic: &LicenseQuery,
+        new_expiration: u64,
+        timestamp: u64,
+    ) -> DatabaseResult<()>;
+    fn license_add_history(
+        &self,
+        license_id: u32,
+        entry: &LicenseHistoryQuery,
+    ) -> DatabaseResult<()>;
+    fn license_history(&self, license_id: u32) -> DatabaseResult<Vec<LicenseHistoryQuery>>;
+    fn license_server_restrictions(
+        &self,
+        license_id: u32,
+    ) -> DatabaseResult<Option<ServerRestrictionQuery>>;
+    fn license_set_server_restrictions(
+        &self,
+        license_id: u32,
+        restrictions: &ServerRestrictionQuery,
+    ) -> DatabaseResult<()>;
+    fn license_visibility(&self, license_id: u32) -> DatabaseResult<Option<u32>>;
+    fn license_set_visibility(&self, license_id: u32, visibility: u32) -> DatabaseResult<()>;
 }
 
 impl LicenseDB for UniverseDatabase {
@@ -46,9 +101,9 @@ impl LicenseDB for UniverseDatabase {
                 Password varchar(255) NOT NULL default '', 
                 Email varchar(255) NOT NULL default '', 
                 Comment varchar(255) NOT NULL default '', 
-                Creation INTEGER NOT NULL default '0', 
-                Expiration INTEGER NOT NULL default '0', 
-                LastStart INTEGER NOT NULL default '0', 
+                Creation BIGINT NOT NULL default '0', 
+                Expiration BIGINT NOT NULL default '0', 
+                LastStart BIGINT NOT NULL default '0', 
                 LastAddress INTEGER NOT NULL default '0', 
                 Users INTEGER NOT NULL default '0', 
                 WorldSize INTEGER NOT NULL default '0', 
@@ -68,6 +123,29 @@ impl LicenseDB for UniverseDatabase {
         }
     }
 
+    fn init_license_history(&self) -> DatabaseResult<()> {
+        let auto_increment_not_null = self.db.auto_increment_not_null();
+        let unsigned = self.db.unsigned_str();
+        let r = self.db.exec(
+            format!(
+                r"CREATE TABLE IF NOT EXISTS awu_license_history (
+                ID INTEGER PRIMARY KEY {auto_increment_not_null},
+                License INTEGER {unsigned} NOT NULL default '0',
+                `Timestamp` BIGINT NOT NULL default '0',
+                Event varchar(50) NOT NULL default '',
+                OldExpiration BIGINT NOT NULL default '0',
+                NewExpiration BIGINT NOT NULL default '0'
+            );"
+            ),
+            vec![],
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
     fn license_by_name(&self, name: &str) -> DatabaseResult<Option<LicenseQuery>> {
         let r = self.db.exec(
             r"SELECT * FROM awu_license WHERE Name=?",
@@ -98,13 +176,13 @@ impl LicenseDB for UniverseDatabase {
     }
 
     fn license_add(&self, lic: &LicenseQuery) -> DatabaseResult<()> {
-        let now = unix_epoch_timestamp_u32();
+        let now = unix_epoch_timestamp_u64();
 
         let r = self.db.exec(
             r"INSERT INTO awu_license(Creation, Expiration, LastStart, LastAddress, Hidden,
-                Tourists, Users, WorldSize, Voip, Plugins, Name, Password, Email, Comment) 
+                Tourists, Users, WorldSize, Voip, Plugins, Name, Password, Email, Comment, Owner) 
                 VALUES(?, ?, ?, ?, ?, ?,
-                    ?, ?, ?, ?, ?, ?, ?, ?);",
+                    ?, ?, ?, ?, ?, ?, ?, ?, ?);",
             aw_params! {
                 now,
                 lic.expiration,
@@ -119,7 +197,8 @@ impl LicenseDB for UniverseDatabase {
                 &lic.name,
                 &lic.password,
                 &lic.email,
-                &lic.comment
+                &lic.comment,
+                lic.owner
             },
         );
 
@@ -183,6 +262,71 @@ impl LicenseDB for UniverseDatabase {
         }
     }
 
+    /// The first license after `name` that belongs to `owner`.
+    fn license_next_owned(&self, name: &str, owner: u32) -> DatabaseResult<Option<LicenseQuery>> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_license WHERE Name>? AND Owner=? ORDER BY Name LIMIT 1",
+            aw_params! {
+                name,
+                owner
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let Some(row) = rows.first() else {
+            return DatabaseResult::Ok(None);
+        };
+
+        match fetch_license(row) {
+            DatabaseResult::Ok(lic) => DatabaseResult::Ok(Some(lic)),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    /// The last license before `name` that belongs to `owner`.
+    fn license_prev_owned(&self, name: &str, owner: u32) -> DatabaseResult<Option<LicenseQuery>> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_license WHERE Name<? AND Owner=? ORDER BY Name DESC LIMIT 1",
+            aw_params! {
+                name,
+                owner
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let Some(row) = rows.first() else {
+            return DatabaseResult::Ok(None);
+        };
+
+        match fetch_license(row) {
+            DatabaseResult::Ok(lic) => DatabaseResult::Ok(Some(lic)),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn license_set_owner(&self, license_id: u32, owner: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"UPDATE awu_license SET Changed=NOT Changed, Owner=? WHERE ID=?;",
+            aw_params! {
+                owner,
+                license_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
     fn license_change(&self, lic: &LicenseQuery) -> DatabaseResult<()> {
         let r = self.db.exec(
             r"UPDATE awu_license
@@ -228,6 +372,330 @@ impl LicenseDB for UniverseDatabase {
             DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
         }
     }
+
+    fn license_by_owner(&self, citizen_id: u32) -> DatabaseResult<Vec<LicenseQuery>> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_license WHERE Owner=? ORDER BY Name",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut licenses = Vec::<LicenseQuery>::new();
+        for row in &rows {
+            match fetch_license(row) {
+                DatabaseResult::Ok(lic) => licenses.push(lic),
+                DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(licenses)
+    }
+
+    /// Every license, grouped by owner.
+    fn license_all(&self) -> DatabaseResult<Vec<LicenseQuery>> {
+        let r = self
+            .db
+            .exec(r"SELECT * FROM awu_license ORDER BY Owner, Name", vec![]);
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut licenses = Vec::<LicenseQuery>::new();
+        for row in &rows {
+            match fetch_license(row) {
+                DatabaseResult::Ok(lic) => licenses.push(lic),
+                DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(licenses)
+    }
+
+    fn license_delete_by_owner(&self, citizen_id: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_license WHERE Owner=?;",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn license_reassign_owner(&self, from_citizen: u32, to_citizen: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"UPDATE awu_license SET Changed=NOT Changed, Owner=? WHERE Owner=?;",
+            aw_params! {
+                to_citizen,
+                from_citizen
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    /// Licenses that expire before the given time and whose owners have not
+    /// been told about that expiration yet. Licenses that never expire have
+    /// an expiration of 0.
+    fn license_expiring(&self, before: u64) -> DatabaseResult<Vec<LicenseQuery>> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_license
+            WHERE Expiration<>0 AND Expiration<? AND ExpiryNotified<>Expiration
+            ORDER BY Expiration",
+            aw_params! {
+                before
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut licenses = Vec::<LicenseQuery>::new();
+        for row in &rows {
+            match fetch_license(row) {
+                DatabaseResult::Ok(lic) => licenses.push(lic),
+                DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(licenses)
+    }
+
+    /// Remember that the owner was told about a license expiring at `expiration`.
+    fn license_set_expiry_notified(&self, license_id: u32, expiration: u64) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"UPDATE awu_license SET ExpiryNotified=? WHERE ID=?;",
+            aw_params! {
+                expiration,
+                license_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    /// Move the expiration of a license and record it in the license history.
+    fn license_renew(
+        &self,
+        lic: &LicenseQuery,
+        new_expiration: u64,
+        timestamp: u64,
+    ) -> DatabaseResult<()> {
+        self.transaction(|| {
+            let r = self.db.exec(
+                r"UPDATE awu_license SET Changed=NOT Changed, Expiration=? WHERE ID=?;",
+                aw_params! {
+                    new_expiration,
+                    lic.id
+                },
+            );
+
+            if r.is_err() {
+                return DatabaseResult::DatabaseError;
+            }
+
+            self.license_add_history(
+                lic.id,
+                &LicenseHistoryQuery {
+                    timestamp,
+                    event: "renew".to_string(),
+                    old_expiration: lic.expiration,
+                    new_expiration,
+                },
+            )
+        })
+    }
+
+    fn license_add_history(
+        &self,
+        license_id: u32,
+        entry: &LicenseHistoryQuery,
+    ) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"INSERT INTO awu_license_history (License, `Timestamp`, Event, OldExpiration, NewExpiration)
+            VALUES(?, ?, ?, ?, ?);",
+            aw_params! {
+                license_id,
+                entry.timestamp,
+                &entry.event,
+                entry.old_expiration,
+                entry.new_expiration
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn license_history(&self, license_id: u32) -> DatabaseResult<Vec<LicenseHistoryQuery>> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_license_history WHERE License=? ORDER BY `Timestamp`, ID",
+            aw_params! {
+                license_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut history = Vec::<LicenseHistoryQuery>::new();
+        for row in &rows {
+            match fetch_license_history(row) {
+                DatabaseResult::Ok(entry) => history.push(entry),
+                DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(history)
+    }
+
+    fn license_server_restrictions(
+        &self,
+        license_id: u32,
+    ) -> DatabaseResult<Option<ServerRestrictionQuery>> {
+        let r = self.db.exec(
+            r"SELECT AllowedAddresses, ServerKey FROM awu_license WHERE ID=?",
+            aw_params! {
+                license_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let Some(row) = rows.first() else {
+            return DatabaseResult::Ok(None);
+        };
+
+        let allowed_addresses = match row.fetch_string("AllowedAddresses") {
+            Some(x) => x,
+            None => return DatabaseResult::DatabaseError,
+        };
+
+        let server_key = match row.fetch_string("ServerKey") {
+            Some(x) => x,
+            None => return DatabaseResult::DatabaseError,
+        };
+
+        DatabaseResult::Ok(Some(ServerRestrictionQuery {
+            allowed_addresses,
+            server_key,
+        }))
+    }
+
+    fn license_set_server_restrictions(
+        &self,
+        license_id: u32,
+        restrictions: &ServerRestrictionQuery,
+    ) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"UPDATE awu_license SET Changed=NOT Changed, AllowedAddresses=?, ServerKey=?
+            WHERE ID=?;",
+            aw_params! {
+                &restrictions.allowed_addresses,
+                &restrictions.server_key,
+                license_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    /// The visibility of a license, which is a `WorldVisibility`.
+    fn license_visibility(&self, license_id: u32) -> DatabaseResult<Option<u32>> {
+        let r = self.db.exec(
+            r"SELECT Visibility FROM awu_license WHERE ID=?",
+            aw_params! {
+                license_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let Some(row) = rows.first() else {
+            return DatabaseResult::Ok(None);
+        };
+
+        match row.fetch_int("Visibility").map(u32::try_from) {
+            Some(Ok(x)) => DatabaseResult::Ok(Some(x)),
+            _ => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn license_set_visibility(&self, license_id: u32, visibility: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"UPDATE awu_license SET Changed=NOT Changed, Visibility=? WHERE ID=?;",
+            aw_params! {
+                visibility,
+                license_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+}
+
+fn fetch_license_history(row: &Row) -> DatabaseResult<LicenseHistoryQuery> {
+    let timestamp = match row.fetch_int("Timestamp").map(u64::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let event = match row.fetch_string("Event") {
+        Some(x) => x,
+        None => return DatabaseResult::DatabaseError,
+    };
+
+    let old_expiration = match row.fetch_int("OldExpiration").map(u64::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let new_expiration = match row.fetch_int("NewExpiration").map(u64::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    DatabaseResult::Ok(LicenseHistoryQuery {
+        timestamp,
+        event,
+        old_expiration,
+        new_expiration,
+    })
 }
 
 fn fetch_license(row: &Row) -> DatabaseResult<LicenseQuery> {
@@ -256,17 +724,17 @@ fn fetch_license(row: &Row) -> DatabaseResult<LicenseQuery> {
         None => return DatabaseResult::DatabaseError,
     };
 
-    let creation = match row.fetch_int("Creation").map(u32::try_from) {
+    let creation = match row.fetch_int("Creation").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
 
-    let expiration = match row.fetch_int("Expiration").map(u32::try_from) {
+    let expiration = match row.fetch_int("Expiration").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
 
-    let last_start = match row.fetch_int("LastStart").map(u32::try_from) {
+    let last_start = match row.fetch_int("LastStart").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
@@ -312,6 +780,11 @@ fn fetch_license(row: &Row) -> DatabaseResult<LicenseQuery> {
         _ => return DatabaseResult::DatabaseError,
     };
 
+    let owner = match row.fetch_int("Owner").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
     DatabaseResult::Ok(LicenseQuery {
         id,
         name,
@@ -329,5 +802,6 @@ fn fetch_license(row: &Row) -> DatabaseResult<LicenseQuery> {
         voip,
         plugins,
         creation,
+        owner,
     })
 }
@@ -0,0 +1,245 @@
+use aw_db::{aw_params, DatabaseResult, DatabaseType};
+
+use super::UniverseDatabase;
+
+/// A schema change applied once to databases created by older versions.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    apply: fn(&UniverseDatabase) -> DatabaseResult<()>,
+}
+
+/// Every migration in the order it must be applied. Migrations also run on
+/// freshly created databases, so columns added by a migration are left out of
+/// the CREATE TABLE statements.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "64-bit timestamps",
+        apply: migrate_64_bit_timestamps,
+    },
+    Migration {
+        version: 2,
+        description: "license owners",
+        apply: migrate_license_owner,
+    },
+    Migration {
+        version: 3,
+        description: "license expiry notices",
+        apply: migrate_license_expiry_notified,
+    },
+    Migration {
+        version: 4,
+        description: "world server restrictions",
+        apply: migrate_world_server_restrictions,
+    },
+    Migration {
+        version: 5,
+        description: "world visibility",
+        apply: migrate_world_visibility,
+    },
+    Migration {
+        version: 6,
+        description: "world statistics indexes",
+        apply: migrate_world_stats_indexes,
+    },
+    Migration {
+        version: 7,
+        description: "world server keys",
+        apply: migrate_world_server_keys,
+    },
+];
+
+pub trait MigrationDB {
+    fn init_migration(&self) -> DatabaseResult<()>;
+    fn schema_version(&self) -> DatabaseResult<u32>;
+    fn migrate(&self) -> DatabaseResult<()>;
+}
+
+impl MigrationDB for UniverseDatabase {
+    fn init_migration(&self) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"CREATE TABLE IF NOT EXISTS awu_schema (
+            Version INTEGER NOT NULL default '0'
+        );",
+            vec![],
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn schema_version(&self) -> DatabaseResult<u32> {
+        let rows = match self
+            .db
+            .exec(r"SELECT MAX(Version) AS Version FROM awu_schema", vec![])
+        {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        // MAX() of an empty table is NULL, meaning nothing has been applied yet
+        let version = rows
+            .first()
+            .and_then(|row| row.fetch_int("Version"))
+            .and_then(|x| u32::try_from(x).ok())
+            .unwrap_or(0);
+
+        DatabaseResult::Ok(version)
+    }
+
+    fn migrate(&self) -> DatabaseResult<()> {
+        let current = match self.schema_version() {
+            DatabaseResult::Ok(version) => version,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+            log::info!(
+                "Migrating database schema to version {} ({})",
+                migration.version,
+                migration.description
+            );
+
+            if (migration.apply)(self).is_err() {
+                log::error!("Database migration {} failed", migration.version);
+                return DatabaseResult::DatabaseError;
+            }
+
+            let r = self.db.exec(
+                r"INSERT INTO awu_schema (Version) VALUES(?)",
+                aw_params!(migration.version),
+            );
+
+            if r.is_err() {
+                return DatabaseResult::DatabaseError;
+            }
+        }
+
+        DatabaseResult::Ok(())
+    }
+}
+
+/// Timestamp columns used to be 32-bit, which runs out in 2038.
+fn migrate_64_bit_timestamps(database: &UniverseDatabase) -> DatabaseResult<()> {
+    // SQLite integers are always stored with up to 64 bits.
+    if database.db.database_type() == DatabaseType::Internal {
+        return DatabaseResult::Ok(());
+    }
+
+    let columns = [
+        ("awu_citizen", "Immigration"),
+        ("awu_citizen", "Expiration"),
+        ("awu_citizen", "LastLogin"),
+        ("awu_license", "Creation"),
+        ("awu_license", "Expiration"),
+        ("awu_license", "LastStart"),
+        ("awu_eject", "Expiration"),
+        ("awu_eject", "Creation"),
+        ("awu_telegram", "`Timestamp`"),
+    ];
+
+    for (table, column) in columns {
+        let r = database.db.exec(
+            format!("ALTER TABLE {table} MODIFY {column} BIGINT NOT NULL default '0'"),
+            vec![],
+        );
+
+        if r.is_err() {
+            return DatabaseResult::DatabaseError;
+        }
+    }
+
+    DatabaseResult::Ok(())
+}
+
+/// Licenses can belong to a citizen, so that they can follow the citizen when
+/// it is deleted.
+fn migrate_license_owner(database: &UniverseDatabase) -> DatabaseResult<()> {
+    let unsigned = database.db.unsigned_str();
+    let r = database.db.exec(
+        format!("ALTER TABLE awu_license ADD COLUMN Owner INTEGER {unsigned} NOT NULL default '0'"),
+        vec![],
+    );
+
+    match r {
+        DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+        DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+    }
+}
+
+/// Expiration of a license that its owner was last warned about, so that
+/// each expiration is only warned about once.
+fn migrate_license_expiry_notified(database: &UniverseDatabase) -> DatabaseResult<()> {
+    let r = database.db.exec(
+        r"ALTER TABLE awu_license ADD COLUMN ExpiryNotified BIGINT NOT NULL default '0'",
+        vec![],
+    );
+
+    match r {
+        DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+        DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+    }
+}
+
+/// Licenses can restrict which hosts may start their world, beyond knowing
+/// its password.
+fn migrate_world_server_restrictions(database: &UniverseDatabase) -> DatabaseResult<()> {
+    let r = database.db.exec(
+        r"ALTER TABLE awu_license ADD COLUMN AllowedAddresses varchar(1024) NOT NULL default ''",
+        vec![],
+    );
+
+    match r {
+        DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+        DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+    }
+}
+
+/// Worlds can be unlisted or private, on top of the hidden flag browsers set.
+fn migrate_world_visibility(database: &UniverseDatabase) -> DatabaseResult<()> {
+    let r = database.db.exec(
+        r"ALTER TABLE awu_license ADD COLUMN Visibility tinyint(1) NOT NULL default '0'",
+        vec![],
+    );
+
+    match r {
+        DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+        DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+    }
+}
+
+/// World statistics are looked up by world and time, and the samples table
+/// grows with every running world.
+fn migrate_world_stats_indexes(database: &UniverseDatabase) -> DatabaseResult<()> {
+    let indexes = [
+        "awu_world_sample_world_timestamp ON awu_world_sample (World, `Timestamp`)",
+        "awu_world_daily_world_day ON awu_world_daily (World, Day)",
+    ];
+
+    for index in indexes {
+        let r = database.db.exec(format!("CREATE INDEX {index}"), vec![]);
+
+        if r.is_err() {
+            return DatabaseResult::DatabaseError;
+        }
+    }
+
+    DatabaseResult::Ok(())
+}
+
+/// Licenses can require world servers to prove they hold a registered key
+/// pair before starting their world.
+fn migrate_world_server_keys(database: &UniverseDatabase) -> DatabaseResult<()> {
+    let r = database.db.exec(
+        r"ALTER TABLE awu_license ADD COLUMN ServerKey varchar(1024) NOT NULL default ''",
+        vec![],
+    );
+
+    match r {
+        DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+        DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+    }
+}
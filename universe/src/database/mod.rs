@@ -1,24 +1,35 @@
-use aw_db::{Database, DatabaseConfig, DatabaseOpenError};
+use aw_db::{Database, DatabaseConfig, DatabaseOpenError, DatabaseResult, StatementStats};
 
 use crate::configuration::UniverseConfig;
 
 pub use self::attrib::AttribDB;
+pub use self::cache::CacheStats;
 pub use self::cav::CavDB;
 pub use self::citizen::CitizenDB;
 pub use self::contact::ContactDB;
 pub use self::eject::EjectDB;
 pub use self::license::LicenseDB;
+pub use self::migration::MigrationDB;
+pub use self::quota::QuotaDB;
 pub use self::telegram::TelegramDB;
+pub use self::world_access::WorldAccessDB;
+pub use self::world_stats::WorldStatsDB;
 pub mod attrib;
+pub mod cache;
 pub mod cav;
 pub mod citizen;
 pub mod contact;
 pub mod eject;
 pub mod license;
+pub mod migration;
+pub mod quota;
 pub mod telegram;
+pub mod world_access;
+pub mod world_stats;
 
 pub struct UniverseDatabase {
     db: aw_db::Database,
+    cache: cache::DatabaseCache,
 }
 
 impl UniverseDatabase {
@@ -27,20 +38,60 @@ impl UniverseDatabase {
         universe_config: &UniverseConfig,
     ) -> Result<Self, DatabaseOpenError> {
         let db = Database::new(config)?;
-        let unidb = UniverseDatabase { db };
+        let unidb = UniverseDatabase {
+            db,
+            cache: Default::default(),
+        };
 
         unidb.init_tables(universe_config);
 
         Ok(unidb)
     }
 
+    /// Open the database without creating or migrating tables, for tools that
+    /// run alongside a universe.
+    pub fn open_read_only(config: DatabaseConfig) -> Result<Self, DatabaseOpenError> {
+        Ok(UniverseDatabase {
+            db: Database::open_read_only(config)?,
+            cache: Default::default(),
+        })
+    }
+
+    /// Run `f` in a database transaction, which is rolled back if it fails.
+    pub fn transaction<T>(&self, f: impl FnOnce() -> DatabaseResult<T>) -> DatabaseResult<T> {
+        let r = self.db.transaction(f);
+        if r.is_err() {
+            // Entries read or dropped during the transaction may not match
+            // what is stored after the rollback
+            self.cache.clear();
+        }
+        r
+    }
+
+    /// Hit and miss counters of the read-through cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    /// Execution statistics per statement shape, slowest in total first.
+    pub fn query_metrics(&self) -> Vec<(String, StatementStats)> {
+        self.db.query_metrics()
+    }
+
     fn init_tables(&self, universe_config: &UniverseConfig) {
         self.init_attrib(universe_config);
         self.init_citizen();
         self.init_contact();
         self.init_license();
+        self.init_license_history();
         self.init_telegram();
         self.init_cav();
         self.init_eject();
+        self.init_world_quota();
+        self.init_world_sample();
+        self.init_world_daily();
+        self.init_world_access();
+        self.init_migration();
+        self.migrate();
     }
 }
@@ -0,0 +1,138 @@
+use aw_db::{aw_params, DatabaseResult, Row};
+
+use super::UniverseDatabase;
+
+pub trait QuotaDB {
+    fn init_world_quota(&self) -> DatabaseResult<()>;
+    fn world_quota_get(&self, citizen_id: u32) -> DatabaseResult<Option<WorldQuotaQuery>>;
+    fn world_quota_set(&self, quota: &WorldQuotaQuery) -> DatabaseResult<()>;
+    fn world_quota_delete(&self, citizen_id: u32) -> DatabaseResult<()>;
+}
+
+/// Quota an admin has given a single citizen instead of the universe default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldQuotaQuery {
+    pub citizen: u32,
+    /// Licenses the citizen may hold before they cannot add any more
+    pub licenses: u32,
+    /// Worlds of the citizen's licenses that may run at once, or 0 for no limit
+    pub running_worlds: u32,
+}
+
+impl QuotaDB for UniverseDatabase {
+    fn init_world_quota(&self) -> DatabaseResult<()> {
+        let unsigned = self.db.unsigned_str();
+        let auto_increment_not_null = self.db.auto_increment_not_null();
+        let r = self.db.exec(
+            format!(
+                r"CREATE TABLE IF NOT EXISTS awu_world_quota (
+                ID INTEGER PRIMARY KEY {auto_increment_not_null},
+                Citizen INTEGER {unsigned} NOT NULL default '0',
+                Licenses INTEGER {unsigned} NOT NULL default '0',
+                RunningWorlds INTEGER {unsigned} NOT NULL default '0'
+            );"
+            ),
+            vec![],
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn world_quota_get(&self, citizen_id: u32) -> DatabaseResult<Option<WorldQuotaQuery>> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_world_quota WHERE Citizen=?;",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let row = match rows.first() {
+            Some(row) => row,
+            None => return DatabaseResult::Ok(None),
+        };
+
+        match fetch_world_quota(row) {
+            DatabaseResult::Ok(quota) => DatabaseResult::Ok(Some(quota)),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn world_quota_set(&self, quota: &WorldQuotaQuery) -> DatabaseResult<()> {
+        let existing = match self.world_quota_get(quota.citizen) {
+            DatabaseResult::Ok(existing) => existing,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let r = if existing.is_none() {
+            self.db.exec(
+                r"INSERT INTO awu_world_quota (Citizen, Licenses, RunningWorlds)
+                VALUES(?, ?, ?);",
+                aw_params! {
+                    quota.citizen,
+                    quota.licenses,
+                    quota.running_worlds
+                },
+            )
+        } else {
+            self.db.exec(
+                r"UPDATE awu_world_quota SET Licenses=?, RunningWorlds=?
+                WHERE Citizen=?;",
+                aw_params! {
+                    quota.licenses,
+                    quota.running_worlds,
+                    quota.citizen
+                },
+            )
+        };
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn world_quota_delete(&self, citizen_id: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_world_quota WHERE Citizen=?;",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+}
+
+fn fetch_world_quota(row: &Row) -> DatabaseResult<WorldQuotaQuery> {
+    let citizen = match row.fetch_int("Citizen").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let licenses = match row.fetch_int("Licenses").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let running_worlds = match row.fetch_int("RunningWorlds").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    DatabaseResult::Ok(WorldQuotaQuery {
+        citizen,
+        licenses,
+        running_worlds,
+    })
+}
@@ -7,18 +7,21 @@ pub struct TelegramQuery {
     pub id: u32,
     pub citizen: u32,
     pub from: u32,
-    pub timestamp: u32,
+    pub timestamp: u64,
     pub message: String,
     pub delivered: u32,
 }
 
 pub trait TelegramDB {
     fn init_telegram(&self) -> DatabaseResult<()>;
-    fn telegram_add(&self, to: u32, from: u32, timestamp: u32, message: &str)
+    fn telegram_add(&self, to: u32, from: u32, timestamp: u64, message: &str)
         -> DatabaseResult<()>;
     fn telegram_get_undelivered(&self, citizen_id: u32) -> DatabaseResult<Vec<TelegramQuery>>;
     fn telegram_get_all(&self, citizen_id: u32) -> DatabaseResult<Vec<TelegramQuery>>;
     fn telegram_mark_delivered(&self, telegram_id: u32) -> DatabaseResult<()>;
+    fn telegram_get_sent(&self, citizen_id: u32) -> DatabaseResult<Vec<TelegramQuery>>;
+    fn telegram_delete_to(&self, citizen_id: u32) -> DatabaseResult<()>;
+    fn telegram_delete_from(&self, citizen_id: u32) -> DatabaseResult<()>;
 }
 
 impl TelegramDB for UniverseDatabase {
@@ -30,7 +33,7 @@ impl TelegramDB for UniverseDatabase {
             ID INTEGER PRIMARY KEY {auto_increment_not_null}, 
             Citizen INTEGER {unsigned} NOT NULL default '0', 
             `From` INTEGER {unsigned} NOT NULL default '0', 
-            `Timestamp` INTEGER {unsigned} NOT NULL default '0', 
+            `Timestamp` BIGINT NOT NULL default '0', 
             Message text NOT NULL, 
             Delivered tinyint(1) NOT NULL default '0'
         );"
@@ -48,7 +51,7 @@ impl TelegramDB for UniverseDatabase {
         &self,
         to: u32,
         from: u32,
-        timestamp: u32,
+        timestamp: u64,
         message: &str,
     ) -> DatabaseResult<()> {
         let r = self.db.exec(
@@ -132,6 +135,59 @@ impl TelegramDB for UniverseDatabase {
             DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
         }
     }
+
+    fn telegram_get_sent(&self, citizen_id: u32) -> DatabaseResult<Vec<TelegramQuery>> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_telegram WHERE `From`=? 
+                ORDER BY Timestamp",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut telegrams = Vec::<TelegramQuery>::new();
+        for row in &rows {
+            match fetch_telegram(row) {
+                DatabaseResult::Ok(telegram) => telegrams.push(telegram),
+                DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(telegrams)
+    }
+
+    fn telegram_delete_to(&self, citizen_id: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_telegram WHERE Citizen=?;",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn telegram_delete_from(&self, citizen_id: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_telegram WHERE `From`=?;",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
 }
 
 fn fetch_telegram(row: &Row) -> DatabaseResult<TelegramQuery> {
@@ -150,7 +206,7 @@ fn fetch_telegram(row: &Row) -> DatabaseResult<TelegramQuery> {
         _ => return DatabaseResult::DatabaseError,
     };
 
-    let timestamp = match row.fetch_int("Timestamp").map(u32::try_from) {
+    let timestamp = match row.fetch_int("Timestamp").map(u64::try_from) {
         Some(Ok(x)) => x,
         _ => return DatabaseResult::DatabaseError,
     };
@@ -0,0 +1,154 @@
+use aw_db::{aw_params, DatabaseResult};
+
+use super::UniverseDatabase;
+
+pub trait WorldAccessDB {
+    fn init_world_access(&self) -> DatabaseResult<()>;
+    fn world_access_list(&self, license_id: u32) -> DatabaseResult<Vec<u32>>;
+    fn world_access_by_citizen(&self, citizen_id: u32) -> DatabaseResult<Vec<String>>;
+    fn world_access_add(&self, license_id: u32, citizen_id: u32) -> DatabaseResult<()>;
+    fn world_access_remove(&self, license_id: u32, citizen_id: u32) -> DatabaseResult<()>;
+    fn world_access_delete_license(&self, license_id: u32) -> DatabaseResult<()>;
+    fn world_access_delete_citizen(&self, citizen_id: u32) -> DatabaseResult<()>;
+}
+
+impl WorldAccessDB for UniverseDatabase {
+    fn init_world_access(&self) -> DatabaseResult<()> {
+        let unsigned = self.db.unsigned_str();
+        let auto_increment_not_null = self.db.auto_increment_not_null();
+        let r = self.db.exec(
+            format!(
+                r"CREATE TABLE IF NOT EXISTS awu_world_access (
+                ID INTEGER PRIMARY KEY {auto_increment_not_null},
+                License INTEGER {unsigned} NOT NULL default '0',
+                Citizen INTEGER {unsigned} NOT NULL default '0'
+            );"
+            ),
+            vec![],
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    /// Citizens on the access list of a license.
+    fn world_access_list(&self, license_id: u32) -> DatabaseResult<Vec<u32>> {
+        let r = self.db.exec(
+            r"SELECT Citizen FROM awu_world_access WHERE License=? ORDER BY Citizen",
+            aw_params! {
+                license_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut citizens = Vec::<u32>::new();
+        for row in &rows {
+            match row.fetch_int("Citizen").map(u32::try_from) {
+                Some(Ok(x)) => citizens.push(x),
+                _ => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(citizens)
+    }
+
+    /// Names of the worlds whose access list a citizen is on.
+    fn world_access_by_citizen(&self, citizen_id: u32) -> DatabaseResult<Vec<String>> {
+        let r = self.db.exec(
+            r"SELECT awu_license.Name FROM awu_world_access
+            INNER JOIN awu_license ON awu_license.ID=awu_world_access.License
+            WHERE awu_world_access.Citizen=? ORDER BY awu_license.Name",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut worlds = Vec::<String>::new();
+        for row in &rows {
+            match row.fetch_string("Name") {
+                Some(name) => worlds.push(name),
+                None => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(worlds)
+    }
+
+    fn world_access_add(&self, license_id: u32, citizen_id: u32) -> DatabaseResult<()> {
+        let citizens = match self.world_access_list(license_id) {
+            DatabaseResult::Ok(citizens) => citizens,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        if citizens.contains(&citizen_id) {
+            return DatabaseResult::Ok(());
+        }
+
+        let r = self.db.exec(
+            r"INSERT INTO awu_world_access (License, Citizen) VALUES(?, ?);",
+            aw_params! {
+                license_id,
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn world_access_remove(&self, license_id: u32, citizen_id: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_world_access WHERE License=? AND Citizen=?;",
+            aw_params! {
+                license_id,
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn world_access_delete_license(&self, license_id: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_world_access WHERE License=?;",
+            aw_params! {
+                license_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn world_access_delete_citizen(&self, citizen_id: u32) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_world_access WHERE Citizen=?;",
+            aw_params! {
+                citizen_id
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+}
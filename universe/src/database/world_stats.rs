@@ -0,0 +1,330 @@
+use aw_db::{aw_params, DatabaseResult, Row};
+
+use super::UniverseDatabase;
+
+pub trait WorldStatsDB {
+    fn init_world_sample(&self) -> DatabaseResult<()>;
+    fn init_world_daily(&self) -> DatabaseResult<()>;
+    fn world_sample_add(&self, sample: &WorldSampleQuery) -> DatabaseResult<()>;
+    fn world_samples_between(
+        &self,
+        from: u64,
+        until: u64,
+        world: Option<&str>,
+    ) -> DatabaseResult<Vec<WorldSampleQuery>>;
+    fn world_samples_delete_before(&self, timestamp: u64) -> DatabaseResult<()>;
+    fn world_daily_add(&self, daily: &WorldDailyQuery) -> DatabaseResult<()>;
+    fn world_daily_since(
+        &self,
+        day: u64,
+        world: Option<&str>,
+    ) -> DatabaseResult<Vec<WorldDailyQuery>>;
+}
+
+/// The state of a running world at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldSampleQuery {
+    pub world: String,
+    pub timestamp: u64,
+    pub users: u32,
+    /// Seconds since the world was started
+    pub uptime: u64,
+}
+
+/// Samples of a world from one day, combined once they are old enough.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldDailyQuery {
+    pub world: String,
+    /// Timestamp of midnight UTC at the start of the day
+    pub day: u64,
+    pub samples: u32,
+    pub peak_users: u32,
+    /// Sum of the users of every sample, for averaging
+    pub total_users: u64,
+    pub longest_uptime: u64,
+}
+
+impl WorldStatsDB for UniverseDatabase {
+    fn init_world_sample(&self) -> DatabaseResult<()> {
+        let unsigned = self.db.unsigned_str();
+        let auto_increment_not_null = self.db.auto_increment_not_null();
+        let r = self.db.exec(
+            format!(
+                r"CREATE TABLE IF NOT EXISTS awu_world_sample (
+                ID INTEGER PRIMARY KEY {auto_increment_not_null},
+                World varchar(50) NOT NULL default '',
+                `Timestamp` BIGINT NOT NULL default '0',
+                Users INTEGER {unsigned} NOT NULL default '0',
+                Uptime BIGINT NOT NULL default '0'
+            );"
+            ),
+            vec![],
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn init_world_daily(&self) -> DatabaseResult<()> {
+        let unsigned = self.db.unsigned_str();
+        let auto_increment_not_null = self.db.auto_increment_not_null();
+        let r = self.db.exec(
+            format!(
+                r"CREATE TABLE IF NOT EXISTS awu_world_daily (
+                ID INTEGER PRIMARY KEY {auto_increment_not_null},
+                World varchar(50) NOT NULL default '',
+                Day BIGINT NOT NULL default '0',
+                Samples INTEGER {unsigned} NOT NULL default '0',
+                PeakUsers INTEGER {unsigned} NOT NULL default '0',
+                TotalUsers BIGINT NOT NULL default '0',
+                LongestUptime BIGINT NOT NULL default '0'
+            );"
+            ),
+            vec![],
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn world_sample_add(&self, sample: &WorldSampleQuery) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"INSERT INTO awu_world_sample (World, `Timestamp`, Users, Uptime)
+            VALUES(?, ?, ?, ?);",
+            aw_params! {
+                &sample.world,
+                sample.timestamp,
+                sample.users,
+                sample.uptime
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    /// Samples taken from `from` up to but not including `until`, of every
+    /// world or of a single one.
+    fn world_samples_between(
+        &self,
+        from: u64,
+        until: u64,
+        world: Option<&str>,
+    ) -> DatabaseResult<Vec<WorldSampleQuery>> {
+        let r = match world {
+            Some(world) => self.db.exec(
+                r"SELECT * FROM awu_world_sample
+                WHERE `Timestamp`>=? AND `Timestamp`<? AND World=?
+                ORDER BY World, `Timestamp`",
+                aw_params! {
+                    from,
+                    until,
+                    world
+                },
+            ),
+            None => self.db.exec(
+                r"SELECT * FROM awu_world_sample
+                WHERE `Timestamp`>=? AND `Timestamp`<?
+                ORDER BY World, `Timestamp`",
+                aw_params! {
+                    from,
+                    until
+                },
+            ),
+        };
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut samples = Vec::<WorldSampleQuery>::new();
+        for row in &rows {
+            match fetch_world_sample(row) {
+                DatabaseResult::Ok(sample) => samples.push(sample),
+                DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(samples)
+    }
+
+    fn world_samples_delete_before(&self, timestamp: u64) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"DELETE FROM awu_world_sample WHERE `Timestamp`<?;",
+            aw_params! {
+                timestamp
+            },
+        );
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    /// Add a day of samples to the stored day of the same world, or store it
+    /// if there is none yet.
+    fn world_daily_add(&self, daily: &WorldDailyQuery) -> DatabaseResult<()> {
+        let r = self.db.exec(
+            r"SELECT * FROM awu_world_daily WHERE World=? AND Day=?;",
+            aw_params! {
+                &daily.world,
+                daily.day
+            },
+        );
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let r = match rows.first().map(fetch_world_daily) {
+            None => self.db.exec(
+                r"INSERT INTO awu_world_daily
+                (World, Day, Samples, PeakUsers, TotalUsers, LongestUptime)
+                VALUES(?, ?, ?, ?, ?, ?);",
+                aw_params! {
+                    &daily.world,
+                    daily.day,
+                    daily.samples,
+                    daily.peak_users,
+                    daily.total_users,
+                    daily.longest_uptime
+                },
+            ),
+            Some(DatabaseResult::Ok(existing)) => self.db.exec(
+                r"UPDATE awu_world_daily
+                SET Samples=?, PeakUsers=?, TotalUsers=?, LongestUptime=?
+                WHERE World=? AND Day=?;",
+                aw_params! {
+                    existing.samples.saturating_add(daily.samples),
+                    existing.peak_users.max(daily.peak_users),
+                    existing.total_users.saturating_add(daily.total_users),
+                    existing.longest_uptime.max(daily.longest_uptime),
+                    &daily.world,
+                    daily.day
+                },
+            ),
+            Some(DatabaseResult::DatabaseError) => return DatabaseResult::DatabaseError,
+        };
+
+        match r {
+            DatabaseResult::Ok(_) => DatabaseResult::Ok(()),
+            DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+        }
+    }
+
+    fn world_daily_since(
+        &self,
+        day: u64,
+        world: Option<&str>,
+    ) -> DatabaseResult<Vec<WorldDailyQuery>> {
+        let r = match world {
+            Some(world) => self.db.exec(
+                r"SELECT * FROM awu_world_daily WHERE Day>=? AND World=? ORDER BY World, Day",
+                aw_params! {
+                    day,
+                    world
+                },
+            ),
+            None => self.db.exec(
+                r"SELECT * FROM awu_world_daily WHERE Day>=? ORDER BY World, Day",
+                aw_params! {
+                    day
+                },
+            ),
+        };
+
+        let rows = match r {
+            DatabaseResult::Ok(rows) => rows,
+            DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+        };
+
+        let mut days = Vec::<WorldDailyQuery>::new();
+        for row in &rows {
+            match fetch_world_daily(row) {
+                DatabaseResult::Ok(daily) => days.push(daily),
+                DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+            }
+        }
+
+        DatabaseResult::Ok(days)
+    }
+}
+
+fn fetch_world_sample(row: &Row) -> DatabaseResult<WorldSampleQuery> {
+    let world = match row.fetch_string("World") {
+        Some(x) => x,
+        None => return DatabaseResult::DatabaseError,
+    };
+
+    let timestamp = match row.fetch_int("Timestamp").map(u64::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let users = match row.fetch_int("Users").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let uptime = match row.fetch_int("Uptime").map(u64::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    DatabaseResult::Ok(WorldSampleQuery {
+        world,
+        timestamp,
+        users,
+        uptime,
+    })
+}
+
+fn fetch_world_daily(row: &Row) -> DatabaseResult<WorldDailyQuery> {
+    let world = match row.fetch_string("World") {
+        Some(x) => x,
+        None => return DatabaseResult::DatabaseError,
+    };
+
+    let day = match row.fetch_int("Day").map(u64::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let samples = match row.fetch_int("Samples").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let peak_users = match row.fetch_int("PeakUsers").map(u32::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let total_users = match row.fetch_int("TotalUsers").map(u64::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    let longest_uptime = match row.fetch_int("LongestUptime").map(u64::try_from) {
+        Some(Ok(x)) => x,
+        _ => return DatabaseResult::DatabaseError,
+    };
+
+    DatabaseResult::Ok(WorldDailyQuery {
+        world,
+        day,
+        samples,
+        peak_users,
+        total_users,
+        longest_uptime,
+    })
+}
@@ -5,17 +5,17 @@ use aw_db::DatabaseResult;
 use crate::{
     client::ClientInfo,
     database::{eject::EjectionQuery, EjectDB, UniverseDatabase},
-    timestamp::unix_epoch_timestamp_u32,
+    timestamp::unix_epoch_timestamp_u64,
     UniverseConnection,
 };
 
 pub fn is_ejection_expired(ejection: &EjectionQuery) -> bool {
     log::trace!(
-        "unix_epoch_timestamp_u32() = {}; ejection.expiration = {}",
-        unix_epoch_timestamp_u32(),
+        "unix_epoch_timestamp_u64() = {}; ejection.expiration = {}",
+        unix_epoch_timestamp_u64(),
         ejection.expiration
     );
-    unix_epoch_timestamp_u32() > ejection.expiration
+    unix_epoch_timestamp_u64() > ejection.expiration
 }
 
 pub fn is_connection_ejected(
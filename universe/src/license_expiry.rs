@@ -0,0 +1,121 @@
+//! Warning world owners about licenses that are about to expire, and
+//! renewing licenses.
+use aw_db::DatabaseResult;
+
+use crate::{
+    database::{license::LicenseQuery, LicenseDB, UniverseDatabase},
+    telegram,
+    timestamp::{unix_epoch_timestamp_u64, SECONDS_PER_DAY},
+    UniverseServer,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum RenewError {
+    #[error("There is no license for world {0:?}")]
+    NoSuchLicense(String),
+    #[error("The license for world {0:?} never expires")]
+    NeverExpires(String),
+    #[error("The license could not be renewed due to a database error")]
+    DatabaseError,
+}
+
+/// A warning about a license that has expired or expires within
+/// `warning_days`, or nothing if it does not expire that soon.
+pub fn expiry_warning(lic: &LicenseQuery, warning_days: u64, now: u64) -> Option<String> {
+    // Licenses without an expiration never expire
+    if lic.expiration == 0 || warning_days == 0 {
+        return None;
+    }
+
+    if lic.expiration <= now {
+        return Some(format!("The license for world {} has expired.", lic.name));
+    }
+
+    let remaining = lic.expiration - now;
+    if remaining > warning_days.saturating_mul(SECONDS_PER_DAY) {
+        return None;
+    }
+
+    let when = match remaining / SECONDS_PER_DAY {
+        0 => "in less than a day".to_string(),
+        1 => "in 1 day".to_string(),
+        days => format!("in {days} days"),
+    };
+    Some(format!(
+        "The license for world {} expires {when}.",
+        lic.name
+    ))
+}
+
+/// Send a telegram to the owner of every license that is about to expire,
+/// once per expiration.
+pub fn notify_expiring_licenses(server: &UniverseServer) {
+    let warning_days = server.config.license_warning_days;
+    if warning_days == 0 {
+        return;
+    }
+
+    let now = unix_epoch_timestamp_u64();
+    let before = now.saturating_add(warning_days.saturating_mul(SECONDS_PER_DAY));
+    let licenses = match server.database.license_expiring(before) {
+        DatabaseResult::Ok(licenses) => licenses,
+        DatabaseResult::DatabaseError => {
+            log::error!("Could not look for expiring licenses due to database error.");
+            return;
+        }
+    };
+
+    for lic in licenses {
+        let Some(warning) = expiry_warning(&lic, warning_days, now) else {
+            continue;
+        };
+
+        if lic.owner == 0 {
+            log::info!("{warning} It has no owner to notify.");
+        } else {
+            let message = format!("{warning} Contact the universe administrators to renew it.");
+            if telegram::send_notice(server, lic.owner, &message).is_err() {
+                log::error!("Could not send expiry notice for world {:?}.", lic.name);
+                continue;
+            }
+            log::info!("{warning} Notified citizen {}.", lic.owner);
+        }
+
+        if server
+            .database
+            .license_set_expiry_notified(lic.id, lic.expiration)
+            .is_err()
+        {
+            log::error!("Could not record expiry notice for world {:?}.", lic.name);
+        }
+    }
+}
+
+/// Extend a license by `term_days`, counting from its expiration, or from
+/// now if it has already expired. Returns the new expiration.
+pub fn renew_license(
+    database: &UniverseDatabase,
+    name: &str,
+    term_days: u64,
+) -> Result<u64, RenewError> {
+    let lic = match database.license_by_name(name) {
+        DatabaseResult::Ok(Some(lic)) => lic,
+        DatabaseResult::Ok(None) => return Err(RenewError::NoSuchLicense(name.to_string())),
+        DatabaseResult::DatabaseError => return Err(RenewError::DatabaseError),
+    };
+
+    if lic.expiration == 0 {
+        return Err(RenewError::NeverExpires(lic.name));
+    }
+
+    let now = unix_epoch_timestamp_u64();
+    let new_expiration = lic
+        .expiration
+        .max(now)
+        .saturating_add(term_days.saturating_mul(SECONDS_PER_DAY));
+
+    match database.license_renew(&lic, new_expiration, now) {
+        DatabaseResult::Ok(()) => Ok(new_expiration),
+        DatabaseResult::DatabaseError => Err(RenewError::DatabaseError),
+    }
+}
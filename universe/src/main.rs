@@ -3,11 +3,18 @@ mod debug_alloc;
 
 use aw_core::*;
 
+mod backup;
+mod citizen_data;
 mod client;
 mod universe_server;
 pub use universe_server::UniverseServer;
 pub mod attributes;
+pub mod license_expiry;
 pub mod universe_license;
+pub mod world_access;
+pub mod world_auth;
+pub mod world_quota;
+pub mod world_stats;
 pub use attributes::send_attributes;
 mod database;
 pub mod packet_handler;
@@ -25,7 +32,8 @@ mod configuration;
 use env_logger::Builder;
 pub use log::{debug, error, info, trace, warn};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -36,6 +44,115 @@ struct Args {
     #[clap(long, default_value = "universe.toml")]
     /// Path to the TOML configuration file for the universe server
     config_file: String,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write a snapshot of the internal database to the backup directory, even while the universe is running
+    Backup,
+    /// Replace the internal database with a snapshot. Refuses to run while a universe has the database open.
+    Restore {
+        /// Path to the snapshot to restore
+        snapshot: PathBuf,
+    },
+    /// Write everything stored about a citizen as a JSON document
+    ExportCitizen {
+        /// Number of the citizen to export
+        citizen_id: u32,
+
+        #[clap(long)]
+        /// File to write the document to instead of standard output
+        output: Option<PathBuf>,
+    },
+    /// Extend a world license and record it in the license history. Refuses to
+    /// run while a universe has the database open.
+    RenewLicense {
+        /// Name of the world whose license to renew
+        world: String,
+
+        #[clap(long)]
+        /// Days to extend the license by instead of license_renewal_days
+        days: Option<u64>,
+    },
+    /// Give a world license to a citizen, who can then change parts of it themselves
+    SetLicenseOwner {
+        /// Name of the world whose license to give away
+        world: String,
+
+        /// Number of the citizen who will own the license, or 0 for nobody
+        citizen_id: u32,
+    },
+    /// Give a citizen a different world quota than the universe default
+    SetWorldQuota {
+        /// Number of the citizen whose quota to set
+        citizen_id: u32,
+
+        /// Licenses the citizen may hold and still add another one themselves
+        licenses: u32,
+
+        /// Worlds of the citizen's licenses that may run at once, or 0 for no limit
+        running_worlds: u32,
+    },
+    /// Return a citizen to the universe's default world quota
+    ClearWorldQuota {
+        /// Number of the citizen whose quota to clear
+        citizen_id: u32,
+    },
+    /// Restrict which world servers may start a world. Without options, any
+    /// world server that knows the world's password may start it again.
+    RestrictWorld {
+        /// Name of the world whose license to restrict
+        world: String,
+
+        #[clap(long = "allow", value_name = "ADDRESS_OR_CIDR")]
+        /// Only let world servers at this IPv4 address or CIDR block start the world; may be given more than once
+        allowed_addresses: Vec<String>,
+
+        #[clap(long, value_name = "PUBLIC_KEY_FILE")]
+        /// Only let a world server that proves it holds the private half of this public key start the world
+        server_key: Option<PathBuf>,
+    },
+    /// Change who can see and enter a world. Changes apply once the universe
+    /// is started again.
+    WorldAccess {
+        /// Name of the world whose license to change
+        world: String,
+
+        #[clap(long, value_enum)]
+        /// Whether the world is listed for everyone, only for its access list, or also only enterable by it
+        visibility: Option<world::WorldVisibility>,
+
+        #[clap(long = "allow", value_name = "CITIZEN_ID")]
+        /// Add this citizen to the world's access list; may be given more than once
+        allow: Vec<u32>,
+
+        #[clap(long = "deny", value_name = "CITIZEN_ID")]
+        /// Remove this citizen from the world's access list; may be given more than once
+        deny: Vec<u32>,
+    },
+    /// Report the peak and average users of every world per day
+    WorldStats {
+        #[clap(long)]
+        /// Only report on this world
+        world: Option<String>,
+
+        #[clap(long, default_value_t = 30)]
+        /// Number of days to report on, including today
+        days: u64,
+
+        #[clap(long)]
+        /// Print the report as JSON
+        json: bool,
+    },
+    /// List the world licenses of every citizen, or of one, with their quotas
+    ListLicenses {
+        #[clap(long)]
+        /// Only list the licenses of this citizen
+        citizen: Option<u32>,
+    },
 }
 
 fn init_logging(level: log::LevelFilter) {
@@ -49,7 +166,41 @@ fn main() {
     init_logging(args.log_level);
 
     match configuration::Config::get_interactive(&args.config_file) {
-        Ok(config) => start_universe(config),
+        Ok(config) => match args.command {
+            None => start_universe(config),
+            Some(Command::Backup) => backup_database(config),
+            Some(Command::Restore { snapshot }) => restore_database(config, snapshot),
+            Some(Command::ExportCitizen { citizen_id, output }) => {
+                export_citizen(config, citizen_id, output)
+            }
+            Some(Command::RenewLicense { world, days }) => renew_license(config, &world, days),
+            Some(Command::SetLicenseOwner { world, citizen_id }) => {
+                set_license_owner(config, &world, citizen_id)
+            }
+            Some(Command::SetWorldQuota {
+                citizen_id,
+                licenses,
+                running_worlds,
+            }) => set_world_quota(config, citizen_id, Some((licenses, running_worlds))),
+            Some(Command::ClearWorldQuota { citizen_id }) => {
+                set_world_quota(config, citizen_id, None)
+            }
+            Some(Command::RestrictWorld {
+                world,
+                allowed_addresses,
+                server_key,
+            }) => restrict_world(config, &world, &allowed_addresses, server_key),
+            Some(Command::WorldAccess {
+                world,
+                visibility,
+                allow,
+                deny,
+            }) => world_access(config, &world, visibility, &allow, &deny),
+            Some(Command::WorldStats { world, days, json }) => {
+                world_stats_report(config, world.as_deref(), days, json)
+            }
+            Some(Command::ListLicenses { citizen }) => list_licenses(config, citizen),
+        },
         Err(err) => log::error!("Could not get universe configuration: {err}"),
     }
 }
@@ -60,3 +211,479 @@ fn start_universe(config: configuration::Config) {
         Err(err) => log::error!("Could not create universe: {err}"),
     }
 }
+
+fn backup_database(config: configuration::Config) {
+    if !matches!(config.sql.database_type, aw_db::DatabaseType::Internal) {
+        log::error!("Backups are only supported for the internal database.");
+        return;
+    }
+
+    let source = &config.sql.sqlite_config.path;
+    match backup::take_snapshot(&config.backup, |dest| aw_db::backup_sqlite(source, dest)) {
+        Ok(path) => log::info!("Wrote database snapshot {}", path.display()),
+        Err(err) => log::error!("Could not back up the database: {err}"),
+    }
+}
+
+fn restore_database(config: configuration::Config, snapshot: PathBuf) {
+    if !matches!(config.sql.database_type, aw_db::DatabaseType::Internal) {
+        log::error!("Restoring is only supported for the internal database.");
+        return;
+    }
+
+    let target = &config.sql.sqlite_config.path;
+    match aw_db::restore_sqlite(&snapshot, target) {
+        Ok(()) => log::info!("Restored {} from {}", target, snapshot.display()),
+        Err(err) => log::error!("Could not restore the database: {err}"),
+    }
+}
+
+fn export_citizen(config: configuration::Config, citizen_id: u32, output: Option<PathBuf>) {
+    let database = match database::UniverseDatabase::open_read_only(config.sql) {
+        Ok(database) => database,
+        Err(err) => {
+            log::error!("Could not open the database: {err}");
+            return;
+        }
+    };
+
+    let export = match citizen_data::export_citizen(&database, citizen_id) {
+        aw_db::DatabaseResult::Ok(Some(export)) => export,
+        aw_db::DatabaseResult::Ok(None) => {
+            log::error!("There is no citizen {citizen_id}");
+            return;
+        }
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read citizen {citizen_id} from the database");
+            return;
+        }
+    };
+
+    let json = match serde_json::to_string_pretty(&export) {
+        Ok(json) => json,
+        Err(err) => {
+            log::error!("Could not serialize citizen {citizen_id}: {err}");
+            return;
+        }
+    };
+
+    match output {
+        Some(path) => match std::fs::write(&path, json) {
+            Ok(()) => log::info!("Wrote citizen {citizen_id} to {}", path.display()),
+            Err(err) => log::error!("Could not write {}: {err}", path.display()),
+        },
+        None => println!("{json}"),
+    }
+}
+
+/// Open the database to change it from the command line. The database is
+/// locked while the universe is running, so this refuses to run until it has
+/// been stopped.
+fn open_database_for_changes(config: configuration::Config) -> Option<database::UniverseDatabase> {
+    match database::UniverseDatabase::new(config.sql, &config.universe) {
+        Ok(database) => Some(database),
+        Err(aw_db::DatabaseOpenError::SqliteInUse(_) | aw_db::DatabaseOpenError::MysqlInUse(_)) => {
+            log::error!(
+                "The database is in use, most likely by the running universe. Stop the universe before running this command."
+            );
+            None
+        }
+        Err(err) => {
+            log::error!("Could not open the database: {err}");
+            None
+        }
+    }
+}
+
+fn renew_license(config: configuration::Config, world: &str, days: Option<u64>) {
+    use database::LicenseDB;
+
+    let term_days = days.unwrap_or(config.universe.license_renewal_days);
+    let Some(database) = open_database_for_changes(config) else {
+        return;
+    };
+
+    match license_expiry::renew_license(&database, world, term_days) {
+        Ok(expiration) => {
+            log::info!(
+                "Renewed the license for world {world:?} by {term_days} days, until {expiration}"
+            )
+        }
+        Err(err) => {
+            log::error!("{err}");
+            return;
+        }
+    }
+
+    let history = match database.license_by_name(world) {
+        aw_db::DatabaseResult::Ok(Some(lic)) => database.license_history(lic.id),
+        _ => aw_db::DatabaseResult::DatabaseError,
+    };
+    match history {
+        aw_db::DatabaseResult::Ok(history) => {
+            for entry in history {
+                log::info!(
+                    "At {}: {} from {} to {}",
+                    entry.timestamp,
+                    entry.event,
+                    entry.old_expiration,
+                    entry.new_expiration
+                );
+            }
+        }
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read the history of the license for world {world:?}")
+        }
+    }
+}
+
+fn set_license_owner(config: configuration::Config, world: &str, citizen_id: u32) {
+    use database::{CitizenDB, LicenseDB};
+
+    let Some(database) = open_database_for_changes(config) else {
+        return;
+    };
+
+    if citizen_id != 0 {
+        match database.citizen_by_number(citizen_id) {
+            aw_db::DatabaseResult::Ok(Some(_)) => {}
+            aw_db::DatabaseResult::Ok(None) => {
+                log::error!("There is no citizen {citizen_id}");
+                return;
+            }
+            aw_db::DatabaseResult::DatabaseError => {
+                log::error!("Could not read citizen {citizen_id} from the database");
+                return;
+            }
+        }
+    }
+
+    let lic = match database.license_by_name(world) {
+        aw_db::DatabaseResult::Ok(Some(lic)) => lic,
+        aw_db::DatabaseResult::Ok(None) => {
+            log::error!("There is no license for world {world:?}");
+            return;
+        }
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read the license for world {world:?}");
+            return;
+        }
+    };
+
+    match database.license_set_owner(lic.id, citizen_id) {
+        aw_db::DatabaseResult::Ok(()) if citizen_id == 0 => {
+            log::info!("The license for world {world:?} no longer has an owner")
+        }
+        aw_db::DatabaseResult::Ok(()) => {
+            log::info!("Citizen {citizen_id} now owns the license for world {world:?}")
+        }
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not change the owner of the license for world {world:?}")
+        }
+    }
+}
+
+/// Set a citizen's quota of licenses and running worlds, or clear it to use
+/// the universe default.
+fn set_world_quota(config: configuration::Config, citizen_id: u32, quota: Option<(u32, u32)>) {
+    use database::{quota::WorldQuotaQuery, CitizenDB, QuotaDB};
+
+    let Some(database) = open_database_for_changes(config) else {
+        return;
+    };
+
+    match database.citizen_by_number(citizen_id) {
+        aw_db::DatabaseResult::Ok(Some(_)) => {}
+        aw_db::DatabaseResult::Ok(None) => {
+            log::error!("There is no citizen {citizen_id}");
+            return;
+        }
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read citizen {citizen_id} from the database");
+            return;
+        }
+    }
+
+    let r = match quota {
+        Some((licenses, running_worlds)) => database.world_quota_set(&WorldQuotaQuery {
+            citizen: citizen_id,
+            licenses,
+            running_worlds,
+        }),
+        None => database.world_quota_delete(citizen_id),
+    };
+
+    match (r, quota) {
+        (aw_db::DatabaseResult::Ok(()), Some((licenses, running_worlds))) => log::info!(
+            "Citizen {citizen_id} may now hold {licenses} licenses and run {running_worlds} worlds at once"
+        ),
+        (aw_db::DatabaseResult::Ok(()), None) => {
+            log::info!("Citizen {citizen_id} now has the default world quota")
+        }
+        (aw_db::DatabaseResult::DatabaseError, _) => {
+            log::error!("Could not change the world quota of citizen {citizen_id}")
+        }
+    }
+}
+
+fn list_licenses(config: configuration::Config, citizen: Option<u32>) {
+    use database::{CitizenDB, LicenseDB};
+
+    let database = match database::UniverseDatabase::open_read_only(config.sql) {
+        Ok(database) => database,
+        Err(err) => {
+            log::error!("Could not open the database: {err}");
+            return;
+        }
+    };
+
+    let licenses = match citizen {
+        Some(citizen_id) => database.license_by_owner(citizen_id),
+        None => database.license_all(),
+    };
+    let licenses = match licenses {
+        aw_db::DatabaseResult::Ok(licenses) => licenses,
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read licenses from the database");
+            return;
+        }
+    };
+
+    let mut owners: Vec<u32> = licenses.iter().map(|lic| lic.owner).collect();
+    owners.dedup();
+    if let Some(citizen_id) = citizen {
+        owners = vec![citizen_id];
+    }
+
+    for owner in owners {
+        let held: Vec<_> = licenses.iter().filter(|lic| lic.owner == owner).collect();
+        if owner == 0 {
+            println!("No owner: {} licenses", held.len());
+        } else {
+            let name = match database.citizen_by_number(owner) {
+                aw_db::DatabaseResult::Ok(Some(cit)) => cit.name,
+                _ => "<unknown>".to_string(),
+            };
+            match world_quota::citizen_quota(&database, &config.world_quota, owner) {
+                aw_db::DatabaseResult::Ok(quota) => println!(
+                    "Citizen {owner} ({name}): {} of {} licenses, {} running worlds allowed",
+                    held.len(),
+                    quota.licenses,
+                    match quota.running_worlds {
+                        0 => "unlimited".to_string(),
+                        running_worlds => running_worlds.to_string(),
+                    }
+                ),
+                aw_db::DatabaseResult::DatabaseError => {
+                    println!("Citizen {owner} ({name}): {} licenses", held.len())
+                }
+            }
+        }
+
+        for lic in held {
+            println!(
+                "    {}: {} users, world size {}, expires {}",
+                lic.name,
+                lic.users,
+                lic.world_size,
+                match lic.expiration {
+                    0 => "never".to_string(),
+                    expiration => expiration.to_string(),
+                }
+            );
+        }
+    }
+}
+
+fn restrict_world(
+    config: configuration::Config,
+    world: &str,
+    allowed_addresses: &[String],
+    server_key: Option<PathBuf>,
+) {
+    use database::{license::ServerRestrictionQuery, LicenseDB};
+
+    for entry in allowed_addresses {
+        if let Err(err) = world_auth::parse_block(entry) {
+            log::error!("{err}");
+            return;
+        }
+    }
+
+    let server_key = match server_key {
+        Some(path) => match std::fs::read(&path) {
+            Ok(key) if AWCryptRSA::default().decode_public_key(&key).is_ok() => {
+                world_auth::encode_key(&key)
+            }
+            Ok(_) => {
+                log::error!("{} is not a public key", path.display());
+                return;
+            }
+            Err(err) => {
+                log::error!("Could not read {}: {err}", path.display());
+                return;
+            }
+        },
+        None => String::new(),
+    };
+
+    let Some(database) = open_database_for_changes(config) else {
+        return;
+    };
+
+    let lic = match database.license_by_name(world) {
+        aw_db::DatabaseResult::Ok(Some(lic)) => lic,
+        aw_db::DatabaseResult::Ok(None) => {
+            log::error!("There is no license for world {world:?}");
+            return;
+        }
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read the license for world {world:?}");
+            return;
+        }
+    };
+
+    let restrictions = ServerRestrictionQuery {
+        allowed_addresses: allowed_addresses.join(","),
+        server_key,
+    };
+    if database
+        .license_set_server_restrictions(lic.id, &restrictions)
+        .is_err()
+    {
+        log::error!("Could not restrict the license for world {world:?}");
+        return;
+    }
+
+    if restrictions == ServerRestrictionQuery::default() {
+        log::info!("Any world server with the password of world {world:?} may start it");
+    } else {
+        log::info!("Restricted which world servers may start world {world:?}");
+    }
+}
+
+fn world_access(
+    config: configuration::Config,
+    world: &str,
+    visibility: Option<world::WorldVisibility>,
+    allow: &[u32],
+    deny: &[u32],
+) {
+    use database::{LicenseDB, WorldAccessDB};
+
+    let Some(database) = open_database_for_changes(config) else {
+        return;
+    };
+
+    let lic = match database.license_by_name(world) {
+        aw_db::DatabaseResult::Ok(Some(lic)) => lic,
+        aw_db::DatabaseResult::Ok(None) => {
+            log::error!("There is no license for world {world:?}");
+            return;
+        }
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read the license for world {world:?}");
+            return;
+        }
+    };
+
+    if let Some(visibility) = visibility {
+        if database
+            .license_set_visibility(lic.id, visibility as u32)
+            .is_err()
+        {
+            log::error!("Could not change the visibility of world {world:?}");
+            return;
+        }
+    }
+
+    for citizen_id in allow {
+        if database.world_access_add(lic.id, *citizen_id).is_err() {
+            log::error!("Could not give citizen {citizen_id} access to world {world:?}");
+            return;
+        }
+    }
+
+    for citizen_id in deny {
+        if database.world_access_remove(lic.id, *citizen_id).is_err() {
+            log::error!("Could not take access to world {world:?} from citizen {citizen_id}");
+            return;
+        }
+    }
+
+    let visibility = match database.license_visibility(lic.id) {
+        aw_db::DatabaseResult::Ok(visibility) => visibility
+            .and_then(world::WorldVisibility::from_u32)
+            .unwrap_or_default(),
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read the visibility of world {world:?}");
+            return;
+        }
+    };
+
+    let access_list = match database.world_access_list(lic.id) {
+        aw_db::DatabaseResult::Ok(access_list) => access_list,
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read the access list of world {world:?}");
+            return;
+        }
+    };
+
+    let access_list = access_list
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("World:       {}", lic.name);
+    println!("Visibility:  {visibility:?}");
+    println!("Access list: {access_list}");
+    if lic.hidden != 0 && visibility == world::WorldVisibility::Public {
+        println!(
+            "The world is hidden, so it is only listed for its owner, admins and access list."
+        );
+    }
+    println!("Changes apply once the universe is started again.");
+}
+
+fn world_stats_report(config: configuration::Config, world: Option<&str>, days: u64, json: bool) {
+    let database = match database::UniverseDatabase::open_read_only(config.sql) {
+        Ok(database) => database,
+        Err(err) => {
+            log::error!("Could not open the database: {err}");
+            return;
+        }
+    };
+
+    let now = timestamp::unix_epoch_timestamp_u64();
+    let reports = match world_stats::report(&database, world, days, now) {
+        aw_db::DatabaseResult::Ok(reports) => reports,
+        aw_db::DatabaseResult::DatabaseError => {
+            log::error!("Could not read world statistics from the database");
+            return;
+        }
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&reports) {
+            Ok(json) => println!("{json}"),
+            Err(err) => log::error!("Could not serialize world statistics: {err}"),
+        }
+        return;
+    }
+
+    for report in reports {
+        println!(
+            "{}: peak {} users, average {:.1} users",
+            report.world, report.peak_users, report.average_users
+        );
+        for day in report.days {
+            println!(
+                "    {}: peak {} users, average {:.1} users, longest uptime {:.1} hours",
+                day.date,
+                day.peak_users,
+                day.average_users,
+                day.longest_uptime as f64 / 3600.0
+            );
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{attributes, get_conn_mut, universe_connection::UniverseConnectionID, UniverseServer};
+use aw_core::{AWPacket, HandshakeAction};
+
+/// Handle a client's part of the key exchange: a request for our public RSA
+/// key, their public RSA key, or their stream key. Once we can decrypt what
+/// they send, they are sent the universe attributes.
+pub fn handshake(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
+    let conn = get_conn_mut!(server, cid, "handshake");
+
+    let actions = match conn.handshake.handle(packet) {
+        Ok(actions) => actions,
+        Err(why) => {
+            log::debug!("Could not continue handshake with {}: {why}", conn.addr());
+            return;
+        }
+    };
+
+    let received_stream_key = actions
+        .iter()
+        .any(|action| matches!(action, HandshakeAction::SetRecvKey(_)));
+
+    conn.apply_handshake(actions);
+
+    if received_stream_key {
+        log::trace!("handshake send_attributes");
+        attributes::send_attributes(conn, &server.database);
+    }
+}
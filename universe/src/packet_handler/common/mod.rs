@@ -1,8 +1,2 @@
-mod public_key_response;
-pub use public_key_response::public_key_response;
-
-mod public_key_request;
-pub use public_key_request::public_key_request;
-
-mod stream_key_response;
-pub use stream_key_response::stream_key_response;
+mod handshake;
+pub use handshake::handshake;
@@ -3,7 +3,7 @@ use crate::{
     database::{citizen::CitizenQuery, CitizenDB, UniverseDatabase},
     get_conn,
     player::Player,
-    timestamp::unix_epoch_timestamp_u32,
+    timestamp::unix_epoch_timestamp_u64,
     universe_connection::UniverseConnectionID,
     UniverseConnection, UniverseServer,
 };
@@ -50,6 +50,7 @@ fn try_add_citizen(
         .ok_or(ReasonCode::Unauthorized)?;
     let expiration = packet
         .get_uint(VarID::CitizenExpiration)
+        .map(u64::from)
         .ok_or(ReasonCode::Unauthorized)?;
     let beta = packet
         .get_uint(VarID::BetaUser)
@@ -73,7 +74,7 @@ fn try_add_citizen(
         priv_pass: String::default(),
         comment: String::default(),
         url: String::default(),
-        immigration: unix_epoch_timestamp_u32(),
+        immigration: unix_epoch_timestamp_u64(),
         expiration,
         last_login: 0,
         last_address: 0,
@@ -114,8 +115,14 @@ fn try_add_citizen(
     // Unimplemented: email filter
 
     if let Some(ClientInfo::Player(Player::Bot(_))) = conn.client {
-        new_info.immigration = packet.get_uint(VarID::CitizenImmigration).unwrap_or(0);
-        new_info.last_login = packet.get_uint(VarID::CitizenLastLogin).unwrap_or(0);
+        new_info.immigration = packet
+            .get_uint(VarID::CitizenImmigration)
+            .map(u64::from)
+            .unwrap_or(0);
+        new_info.last_login = packet
+            .get_uint(VarID::CitizenLastLogin)
+            .map(u64::from)
+            .unwrap_or(0);
         new_info.total_time = packet.get_uint(VarID::CitizenTotalTime).unwrap_or(0);
     }
 
@@ -68,6 +68,7 @@ fn citizen_from_packet(packet: &AWPacket) -> Result<CitizenQuery, String> {
         .ok_or_else(|| "No citizen privilege password".to_string())?;
     let expiration = packet
         .get_uint(VarID::CitizenExpiration)
+        .map(u64::from)
         .ok_or_else(|| "No citizen expiration".to_string())?;
     let bot_limit = packet
         .get_uint(VarID::CitizenBotLimit)
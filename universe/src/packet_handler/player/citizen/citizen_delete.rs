@@ -1,11 +1,11 @@
 use aw_core::{AWPacket, PacketType, ReasonCode, VarID};
 
 use crate::{
-    database::CitizenDB, get_conn, universe_connection::UniverseConnectionID, UniverseServer,
+    citizen_data, get_conn, universe_connection::UniverseConnectionID, world_access, UniverseServer,
 };
 
-pub fn citizen_delete(server: &UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
-    let conn = get_conn!(server, cid, "citizen_lookup_by_name");
+pub fn citizen_delete(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
+    let conn = get_conn!(server, cid, "citizen_delete");
 
     let Some(citizen_id) = packet.get_uint(VarID::CitizenNumber) else {
         return;
@@ -13,12 +13,26 @@ pub fn citizen_delete(server: &UniverseServer, cid: UniverseConnectionID, packet
 
     let mut response = AWPacket::new(PacketType::CitizenChangeResult);
 
-    let rc = match server.database.citizen_delete(citizen_id) {
-        aw_db::DatabaseResult::Ok(()) => ReasonCode::Success,
-        aw_db::DatabaseResult::DatabaseError => ReasonCode::UnableToDeleteCitizen,
+    let rc = if !conn.has_admin_permissions() {
+        ReasonCode::Unauthorized
+    } else {
+        match citizen_data::delete_citizen(
+            &server.database,
+            citizen_id,
+            server.config.citizen_deletion,
+            server.config.deletion_heir,
+        ) {
+            aw_db::DatabaseResult::Ok(()) => ReasonCode::Success,
+            aw_db::DatabaseResult::DatabaseError => ReasonCode::UnableToDeleteCitizen,
+        }
     };
 
     response.add_int(VarID::ReasonCode, rc.into());
 
     conn.send(response);
+
+    // The citizen may have owned running worlds or been on their access lists
+    if rc == ReasonCode::Success {
+        world_access::refresh_all_worlds(server);
+    }
 }
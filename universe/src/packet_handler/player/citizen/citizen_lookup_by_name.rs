@@ -5,33 +5,19 @@ use aw_core::*;
 
 use super::try_citizen_lookup;
 
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::CitizenLookupByName)]
 struct CitizenLookupByNameParams {
+    #[aw(var = VarID::CitizenName)]
     citizen_name: String,
 }
 
-#[derive(Debug)]
-enum CitizenLookupByNameParamsError {
-    CitizenName,
-}
-
-impl TryFrom<&AWPacket> for CitizenLookupByNameParams {
-    type Error = CitizenLookupByNameParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let citizen_name = value
-            .get_string(VarID::CitizenName)
-            .ok_or(CitizenLookupByNameParamsError::CitizenName)?;
-
-        Ok(Self { citizen_name })
-    }
-}
-
 pub fn citizen_lookup_by_name(
     server: &UniverseServer,
     cid: UniverseConnectionID,
     packet: &AWPacket,
 ) {
-    let params = match CitizenLookupByNameParams::try_from(packet) {
+    let params = match CitizenLookupByNameParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete citizen lookup by name: {why:?}");
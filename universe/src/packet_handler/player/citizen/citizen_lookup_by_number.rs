@@ -5,33 +5,19 @@ use aw_core::*;
 
 use super::try_citizen_lookup;
 
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::CitizenLookupByNumber)]
 struct CitizenLookupByNumberParams {
+    #[aw(var = VarID::CitizenNumber)]
     citizen_number: u32,
 }
 
-#[derive(Debug)]
-enum CitizenLookupByNumberParamsError {
-    CitizenNumber,
-}
-
-impl TryFrom<&AWPacket> for CitizenLookupByNumberParams {
-    type Error = CitizenLookupByNumberParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let citizen_number = value
-            .get_uint(VarID::CitizenNumber)
-            .ok_or(CitizenLookupByNumberParamsError::CitizenNumber)?;
-
-        Ok(Self { citizen_number })
-    }
-}
-
 pub fn citizen_lookup_by_number(
     server: &UniverseServer,
     cid: UniverseConnectionID,
     packet: &AWPacket,
 ) {
-    let params = match CitizenLookupByNumberParams::try_from(packet) {
+    let params = match CitizenLookupByNumberParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete citizen lookup by number: {why:?}");
@@ -5,29 +5,15 @@ use aw_core::*;
 
 use super::try_citizen_lookup;
 
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::CitizenNext)]
 struct CitizenNextParams {
+    #[aw(var = VarID::CitizenNumber)]
     citizen_id: u32,
 }
 
-#[derive(Debug)]
-enum CitizenNextParamsError {
-    CitizenID,
-}
-
-impl TryFrom<&AWPacket> for CitizenNextParams {
-    type Error = CitizenNextParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let citizen_id = value
-            .get_uint(VarID::CitizenNumber)
-            .ok_or(CitizenNextParamsError::CitizenID)?;
-
-        Ok(Self { citizen_id })
-    }
-}
-
 pub fn citizen_next(server: &UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
-    let params = match CitizenNextParams::try_from(packet) {
+    let params = match CitizenNextParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete citizen next: {why:?}");
@@ -5,29 +5,15 @@ use aw_core::*;
 
 use super::try_citizen_lookup;
 
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::CitizenPrev)]
 struct CitizenPrevParams {
+    #[aw(var = VarID::CitizenNumber)]
     citizen_id: u32,
 }
 
-#[derive(Debug)]
-enum CitizenPrevParamsError {
-    CitizenID,
-}
-
-impl TryFrom<&AWPacket> for CitizenPrevParams {
-    type Error = CitizenPrevParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let citizen_id = value
-            .get_uint(VarID::CitizenNumber)
-            .ok_or(CitizenPrevParamsError::CitizenID)?;
-
-        Ok(Self { citizen_id })
-    }
-}
-
 pub fn citizen_prev(server: &UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
-    let params = match CitizenPrevParams::try_from(packet) {
+    let params = match CitizenPrevParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete citizen prev: {why:?}");
@@ -19,7 +19,7 @@ pub use citizen_add::citizen_add;
 mod citizen_delete;
 pub use citizen_delete::citizen_delete;
 
-use crate::{database::citizen::CitizenQuery, UniverseConnection};
+use crate::{database::citizen::CitizenQuery, timestamp::wire_timestamp, UniverseConnection};
 use aw_core::*;
 use aw_db::DatabaseResult;
 
@@ -73,9 +73,12 @@ fn citizen_info_vars(
 
     if self_vars || admin_vars {
         vars.extend(vec![
-            AWPacketVar::uint(VarID::CitizenImmigration, citizen.immigration),
-            AWPacketVar::uint(VarID::CitizenExpiration, citizen.expiration),
-            AWPacketVar::uint(VarID::CitizenLastLogin, citizen.last_login),
+            AWPacketVar::uint(
+                VarID::CitizenImmigration,
+                wire_timestamp(citizen.immigration),
+            ),
+            AWPacketVar::uint(VarID::CitizenExpiration, wire_timestamp(citizen.expiration)),
+            AWPacketVar::uint(VarID::CitizenLastLogin, wire_timestamp(citizen.last_login)),
             AWPacketVar::uint(VarID::CitizenTotalTime, citizen.total_time),
             AWPacketVar::uint(VarID::CitizenBotLimit, citizen.bot_limit),
             AWPacketVar::byte(VarID::BetaUser, citizen.beta as u8),
@@ -84,7 +87,10 @@ fn citizen_info_vars(
             AWPacketVar::string(VarID::CitizenPassword, citizen.password.clone()),
             AWPacketVar::string(VarID::CitizenEmail, citizen.email.clone()),
             AWPacketVar::string(VarID::CitizenPrivilegePassword, citizen.priv_pass.clone()),
-            AWPacketVar::uint(VarID::CitizenImmigration, citizen.immigration),
+            AWPacketVar::uint(
+                VarID::CitizenImmigration,
+                wire_timestamp(citizen.immigration),
+            ),
         ]);
     }
 
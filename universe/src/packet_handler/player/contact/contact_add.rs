@@ -3,7 +3,7 @@ use crate::{
     get_conn,
     tabs::regenerate_contact_list,
     telegram,
-    timestamp::unix_epoch_timestamp_u32,
+    timestamp::unix_epoch_timestamp_u64,
     universe_connection::UniverseConnectionID,
     UniverseConnection, UniverseServer,
 };
@@ -67,7 +67,7 @@ pub fn contact_add(server: &mut UniverseServer, cid: UniverseConnectionID, packe
         Err(x) => x,
     };
 
-    log::info!("Contact add: {rc:?}");
+    log::info!("Contact add: {rc}");
     response.add_int(VarID::ReasonCode, rc as i32);
 
     conn.send(response);
@@ -75,7 +75,7 @@ pub fn contact_add(server: &mut UniverseServer, cid: UniverseConnectionID, packe
 }
 
 fn alert_friend_request(from: u32, to: u32, server: &UniverseServer) {
-    let now = unix_epoch_timestamp_u32();
+    let now = unix_epoch_timestamp_u64();
 
     let citizen = match server.database.citizen_by_number(from) {
         DatabaseResult::Ok(Some(citizen)) => citizen,
@@ -1,45 +1,21 @@
-use aw_core::{AWPacket, PacketType, ReasonCode, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, ReasonCode, TypedPacket, VarID};
 
 use crate::{
     database::EjectDB, ejection::is_connection_ejected, get_conn,
-    timestamp::unix_epoch_timestamp_u32, universe_connection::UniverseConnectionID, UniverseServer,
+    timestamp::unix_epoch_timestamp_u64, universe_connection::UniverseConnectionID, UniverseServer,
 };
 
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::EjectAdd)]
 struct EjectAddParams {
+    #[aw(var = VarID::EjectionAddress)]
     address: u32,
+    #[aw(var = VarID::EjectionExpiration)]
     expiration: u32,
+    #[aw(var = VarID::EjectionComment)]
     comment: String,
 }
 
-#[derive(Debug)]
-enum EjectAddParamsError {
-    Address,
-    Expiration,
-    Comment,
-}
-
-impl TryFrom<&AWPacket> for EjectAddParams {
-    type Error = EjectAddParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let address = value
-            .get_uint(VarID::EjectionAddress)
-            .ok_or(EjectAddParamsError::Address)?;
-        let expiration = value
-            .get_uint(VarID::EjectionExpiration)
-            .ok_or(EjectAddParamsError::Expiration)?;
-        let comment = value
-            .get_string(VarID::EjectionComment)
-            .ok_or(EjectAddParamsError::Comment)?;
-
-        Ok(Self {
-            address,
-            expiration,
-            comment,
-        })
-    }
-}
-
 pub fn eject_add(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
     let conn = get_conn!(server, cid, "eject_add");
     if !conn.has_admin_permissions() {
@@ -47,7 +23,7 @@ pub fn eject_add(server: &mut UniverseServer, cid: UniverseConnectionID, packet:
         return;
     }
 
-    let params = match EjectAddParams::try_from(packet) {
+    let params = match EjectAddParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete eject add: {why:?}");
@@ -55,11 +31,11 @@ pub fn eject_add(server: &mut UniverseServer, cid: UniverseConnectionID, packet:
         }
     };
 
-    let creation = unix_epoch_timestamp_u32();
+    let creation = unix_epoch_timestamp_u64();
 
     let rc = match server.database.ejection_set(
         params.address,
-        params.expiration,
+        u64::from(params.expiration),
         creation,
         &params.comment,
     ) {
@@ -16,7 +16,8 @@ mod eject_delete;
 pub use eject_delete::eject_delete;
 
 use crate::{
-    database::EjectDB, get_conn, universe_connection::UniverseConnectionID, UniverseServer,
+    database::EjectDB, get_conn, timestamp::wire_timestamp,
+    universe_connection::UniverseConnectionID, UniverseServer,
 };
 
 enum EjectionLookupMethod {
@@ -49,8 +50,11 @@ fn eject_lookup_by_method(
     let rc = match db_result {
         DatabaseResult::Ok(Some(ejection)) => {
             response.add_uint(VarID::EjectionAddress, ejection.address);
-            response.add_uint(VarID::EjectionExpiration, ejection.expiration);
-            response.add_uint(VarID::EjectionCreation, ejection.creation);
+            response.add_uint(
+                VarID::EjectionExpiration,
+                wire_timestamp(ejection.expiration),
+            );
+            response.add_uint(VarID::EjectionCreation, wire_timestamp(ejection.creation));
             response.add_string(VarID::EjectionComment, ejection.comment);
 
             ReasonCode::Success
@@ -2,56 +2,31 @@ use super::check_valid_name;
 use crate::{
     database::{citizen::CitizenQuery, CitizenDB},
     get_conn,
-    timestamp::unix_epoch_timestamp_u32,
+    timestamp::unix_epoch_timestamp_u64,
     universe_connection::UniverseConnectionID,
     UniverseServer,
 };
-use aw_core::{AWPacket, PacketType, ReasonCode, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, ReasonCode, TypedPacket, VarID};
 use aw_db::DatabaseResult;
 
-#[derive(Debug)]
+#[derive(Debug, TypedPacket)]
+#[aw(packet = PacketType::Immigrate)]
 struct ImmigrateParams {
+    #[aw(var = VarID::CitizenName)]
     name: String,
+    #[aw(var = VarID::CitizenPassword)]
     password: String,
+    #[aw(var = VarID::CitizenEmail)]
     email: String,
 }
 
-#[derive(Debug)]
-enum ImmigrateParamsError {
-    Name,
-    Password,
-    Email,
-}
-
-impl TryFrom<&AWPacket> for ImmigrateParams {
-    type Error = ImmigrateParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let name = value
-            .get_string(VarID::CitizenName)
-            .ok_or(ImmigrateParamsError::Name)?;
-        let password = value
-            .get_string(VarID::CitizenPassword)
-            .ok_or(ImmigrateParamsError::Password)?;
-        let email = value
-            .get_string(VarID::CitizenEmail)
-            .ok_or(ImmigrateParamsError::Email)?;
-
-        Ok(Self {
-            name,
-            password,
-            email,
-        })
-    }
-}
-
 pub fn immigrate(server: &UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
     let conn = get_conn!(server, cid, "immigrate");
     let mut response = AWPacket::new(PacketType::ImmigrateResponse);
 
     log::trace!("immigrate");
 
-    let params = match ImmigrateParams::try_from(packet) {
+    let params = match ImmigrateParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete immigrate: {why:?}");
@@ -84,7 +59,7 @@ fn try_immigrate(server: &UniverseServer, params: ImmigrateParams) -> Result<(),
         DatabaseResult::DatabaseError => return Err(ReasonCode::DatabaseError),
     }
 
-    let now = unix_epoch_timestamp_u32();
+    let now = unix_epoch_timestamp_u64();
 
     let r = server.database.citizen_add_next(CitizenQuery {
         id: 0,
@@ -1,10 +1,14 @@
 use crate::{
-    database::LicenseDB, get_conn, universe_connection::UniverseConnectionID, UniverseServer,
+    database::{license::LicenseQuery, LicenseDB},
+    get_conn,
+    timestamp::{unix_epoch_timestamp_u64, SECONDS_PER_DAY},
+    universe_connection::UniverseConnectionID,
+    world_quota, UniverseServer,
 };
 use aw_core::*;
 use aw_db::DatabaseResult;
 
-use super::{check_valid_world_name, license_from_packet};
+use super::{check_valid_world_name, license_from_packet, LicenseAccess};
 
 pub fn license_add(server: &UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
     let mut p = AWPacket::new(PacketType::LicenseChangeResult);
@@ -20,12 +24,13 @@ pub fn license_add(server: &UniverseServer, cid: UniverseConnectionID, packet: &
         return;
     };
 
-    if !conn.has_admin_permissions() {
-        log::trace!("Failed to add license due to lack of admin permissions");
+    // Admins can add any license, and citizens can add their own within their quota
+    let Some(access) = LicenseAccess::of(conn) else {
+        log::trace!("Failed to add license due to lack of permissions");
         p.add_int(VarID::ReasonCode, ReasonCode::Unauthorized as i32);
         conn.send(p);
         return;
-    }
+    };
 
     if world_name.contains(' ') || world_name.is_empty() {
         log::trace!("Failed to add license due to invalid name");
@@ -34,7 +39,7 @@ pub fn license_add(server: &UniverseServer, cid: UniverseConnectionID, packet: &
         return;
     }
 
-    let lic = match license_from_packet(packet) {
+    let mut lic = match license_from_packet(packet) {
         Ok(x) => x,
         Err(why) => {
             log::info!("Couldn't get license from packet: {why}");
@@ -42,6 +47,17 @@ pub fn license_add(server: &UniverseServer, cid: UniverseConnectionID, packet: &
         }
     };
 
+    if let LicenseAccess::Owner(citizen_id) = access {
+        lic = match own_license(server, citizen_id, lic) {
+            Ok(lic) => lic,
+            Err(rc) => {
+                p.add_int(VarID::ReasonCode, rc as i32);
+                conn.send(p);
+                return;
+            }
+        };
+    }
+
     match server.database.license_by_name(&lic.name) {
         DatabaseResult::Ok(Some(_)) => {
             p.add_int(VarID::ReasonCode, ReasonCode::WorldAlreadyExists.into());
@@ -71,3 +87,42 @@ pub fn license_add(server: &UniverseServer, cid: UniverseConnectionID, packet: &
     p.add_int(VarID::ReasonCode, ReasonCode::Success as i32);
     conn.send(p);
 }
+
+/// A license a citizen adds for themselves, if their quota allows another.
+/// Its caps come from the universe's world quota rather than the request.
+fn own_license(
+    server: &UniverseServer,
+    citizen_id: u32,
+    requested: LicenseQuery,
+) -> Result<LicenseQuery, ReasonCode> {
+    let quota = match world_quota::citizen_quota(&server.database, &server.world_quota, citizen_id)
+    {
+        DatabaseResult::Ok(quota) => quota,
+        DatabaseResult::DatabaseError => return Err(ReasonCode::DatabaseError),
+    };
+
+    let held = match server.database.license_by_owner(citizen_id) {
+        DatabaseResult::Ok(licenses) => licenses.len(),
+        DatabaseResult::DatabaseError => return Err(ReasonCode::DatabaseError),
+    };
+
+    if !world_quota::can_add_license(Some(&quota), held) {
+        log::info!("Citizen {citizen_id} cannot add another license with {held} already held");
+        return Err(ReasonCode::TooManyWorlds);
+    }
+
+    let expiration = match server.config.license_renewal_days {
+        0 => 0,
+        days => unix_epoch_timestamp_u64().saturating_add(days.saturating_mul(SECONDS_PER_DAY)),
+    };
+
+    Ok(LicenseQuery {
+        expiration,
+        users: requested.users.min(server.world_quota.users),
+        world_size: requested.world_size.min(server.world_quota.world_size),
+        voip: 0,
+        plugins: 0,
+        owner: citizen_id,
+        ..requested
+    })
+}
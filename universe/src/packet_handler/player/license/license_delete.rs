@@ -1,10 +1,14 @@
 use aw_core::{AWPacket, PacketType, ReasonCode, VarID};
 
 use crate::{
-    database::LicenseDB, get_conn, universe_connection::UniverseConnectionID, UniverseServer,
+    database::{LicenseDB, WorldAccessDB},
+    get_conn,
+    tabs::regenerate_world_list,
+    universe_connection::UniverseConnectionID,
+    world_access, UniverseServer,
 };
 
-pub fn license_delete(server: &UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
+pub fn license_delete(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
     let conn = get_conn!(server, cid, "license_delete");
 
     if !conn.has_admin_permissions() {
@@ -20,11 +24,38 @@ pub fn license_delete(server: &UniverseServer, cid: UniverseConnectionID, packet
 
     let mut response = AWPacket::new(PacketType::LicenseChangeResult);
 
-    let rc = match server.database.license_delete(&lic_name) {
+    let rc = match delete_license(server, &lic_name) {
         aw_db::DatabaseResult::Ok(()) => ReasonCode::Success,
         aw_db::DatabaseResult::DatabaseError => ReasonCode::DatabaseError,
     };
 
     response.add_uint(VarID::ReasonCode, rc.into());
     conn.send(response);
+
+    // A running world loses its access list along with the license
+    if rc == ReasonCode::Success && server.connections.get_world_by_name(&lic_name).is_some() {
+        if world_access::refresh_world(server, &lic_name).is_err() {
+            log::error!("Could not refresh who can see and enter world {lic_name:?}");
+        }
+        for cid in server.connections.cids() {
+            regenerate_world_list(server, cid)
+        }
+    }
+}
+
+/// Delete a license along with its access list.
+fn delete_license(server: &UniverseServer, name: &str) -> aw_db::DatabaseResult<()> {
+    server.database.transaction(|| {
+        match server.database.license_by_name(name) {
+            aw_db::DatabaseResult::Ok(Some(lic)) => {
+                if server.database.world_access_delete_license(lic.id).is_err() {
+                    return aw_db::DatabaseResult::DatabaseError;
+                }
+            }
+            aw_db::DatabaseResult::Ok(None) => {}
+            aw_db::DatabaseResult::DatabaseError => return aw_db::DatabaseResult::DatabaseError,
+        }
+
+        server.database.license_delete(name)
+    })
 }
@@ -14,10 +14,16 @@ mod license_delete;
 pub use license_delete::license_delete;
 
 use crate::{
-    database::{license::LicenseQuery, LicenseDB, UniverseDatabase},
+    configuration::OwnerLicenseChanges,
+    database::{
+        license::{LicenseHistoryQuery, LicenseQuery},
+        LicenseDB, UniverseDatabase,
+    },
     get_conn,
+    tabs::regenerate_world_list,
+    timestamp::{unix_epoch_timestamp_u64, wire_timestamp},
     universe_connection::UniverseConnectionID,
-    UniverseConnection, UniverseServer,
+    world_access, UniverseConnection, UniverseServer,
 };
 use aw_core::*;
 use aw_db::DatabaseResult;
@@ -28,6 +34,32 @@ enum WorldLicenseLookupMethod {
     Next,
 }
 
+/// Whose world licenses a connection may look up and change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LicenseAccess {
+    /// Admins manage every license
+    Admin,
+    /// Other citizens manage the licenses they own
+    Owner(u32),
+}
+
+impl LicenseAccess {
+    fn of(conn: &UniverseConnection) -> Option<Self> {
+        if conn.has_admin_permissions() {
+            Some(Self::Admin)
+        } else {
+            conn.citizen_id().map(Self::Owner)
+        }
+    }
+
+    fn can_manage(&self, lic: &LicenseQuery) -> bool {
+        match self {
+            Self::Admin => true,
+            Self::Owner(citizen_id) => lic.owner == *citizen_id,
+        }
+    }
+}
+
 fn send_license_lookup(
     conn: &UniverseConnection,
     packet: &AWPacket,
@@ -36,12 +68,12 @@ fn send_license_lookup(
 ) {
     let mut p = AWPacket::new(PacketType::LicenseResult);
 
-    // Only admins should be able to query for world licenses
-    if !conn.has_admin_permissions() {
+    // Only admins and world owners should be able to query for world licenses
+    let Some(access) = LicenseAccess::of(conn) else {
         p.add_int(VarID::ReasonCode, ReasonCode::Unauthorized as i32);
         conn.send(p);
         return;
-    }
+    };
 
     // World name to iterate from should be included
     let world_name = match packet.get_string(VarID::WorldName) {
@@ -49,17 +81,29 @@ fn send_license_lookup(
         None => return,
     };
 
-    // Get the previous/same/next world license starting from the included world name
-    let license_result = match method {
-        WorldLicenseLookupMethod::Previous => database.license_prev(&world_name),
-        WorldLicenseLookupMethod::Exact => database.license_by_name(&world_name),
-        WorldLicenseLookupMethod::Next => database.license_next(&world_name),
+    // Get the previous/same/next world license starting from the included world name.
+    // Owners only iterate through their own licenses.
+    let license_result = match (method, access) {
+        (WorldLicenseLookupMethod::Previous, LicenseAccess::Admin) => {
+            database.license_prev(&world_name)
+        }
+        (WorldLicenseLookupMethod::Previous, LicenseAccess::Owner(owner)) => {
+            database.license_prev_owned(&world_name, owner)
+        }
+        (WorldLicenseLookupMethod::Exact, _) => database.license_by_name(&world_name),
+        (WorldLicenseLookupMethod::Next, LicenseAccess::Admin) => {
+            database.license_next(&world_name)
+        }
+        (WorldLicenseLookupMethod::Next, LicenseAccess::Owner(owner)) => {
+            database.license_next_owned(&world_name, owner)
+        }
     };
 
     let rc = match license_result {
+        DatabaseResult::Ok(Some(lic)) if !access.can_manage(&lic) => ReasonCode::Unauthorized,
         DatabaseResult::Ok(Some(lic)) => {
             // Attach world license info to packet
-            let vars = license_to_vars(&lic, conn.has_admin_permissions());
+            let vars = license_to_vars(&lic);
             for v in vars {
                 p.add_var(v);
             }
@@ -77,16 +121,16 @@ fn send_license_lookup(
     conn.send(p);
 }
 
-pub fn license_change(server: &UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
+pub fn license_change(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
     let mut p = AWPacket::new(PacketType::LicenseResult);
     let conn = get_conn!(server, cid, "license_change");
 
-    // Only admins should be able change world licenses
-    if !conn.has_admin_permissions() {
+    // Only admins and world owners should be able change world licenses
+    let Some(access) = LicenseAccess::of(conn) else {
         p.add_int(VarID::ReasonCode, ReasonCode::Unauthorized as i32);
         conn.send(p);
         return;
-    }
+    };
 
     // Altered license should be included
     let changed_lic = match license_from_packet(packet) {
@@ -116,26 +160,69 @@ pub fn license_change(server: &UniverseServer, cid: UniverseConnectionID, packet
         }
     };
 
+    if !access.can_manage(&original_lic) {
+        p.add_int(VarID::ReasonCode, ReasonCode::Unauthorized.into());
+        conn.send(p);
+        return;
+    }
+
     // Change license
-    let new_lic = LicenseQuery {
-        id: original_lic.id,
-        name: original_lic.name.clone(),
-        password: changed_lic.password.clone(),
-        email: changed_lic.email.clone(),
-        comment: changed_lic.comment.clone(),
-        creation: original_lic.creation,
-        expiration: changed_lic.expiration,
-        last_start: original_lic.last_start,
-        last_address: original_lic.last_address,
-        users: changed_lic.users,
-        world_size: changed_lic.world_size,
-        hidden: changed_lic.hidden,
-        changed: 0,
-        tourists: changed_lic.tourists,
-        voip: changed_lic.voip,
-        plugins: changed_lic.plugins,
+    let new_lic = match access {
+        LicenseAccess::Admin => LicenseQuery {
+            id: original_lic.id,
+            name: original_lic.name.clone(),
+            password: changed_lic.password.clone(),
+            email: changed_lic.email.clone(),
+            comment: changed_lic.comment.clone(),
+            creation: original_lic.creation,
+            expiration: admin_expiration(original_lic.expiration, changed_lic.expiration),
+            last_start: original_lic.last_start,
+            last_address: original_lic.last_address,
+            users: changed_lic.users,
+            world_size: changed_lic.world_size,
+            hidden: changed_lic.hidden,
+            changed: 0,
+            tourists: changed_lic.tourists,
+            voip: changed_lic.voip,
+            plugins: changed_lic.plugins,
+            owner: original_lic.owner,
+        },
+        LicenseAccess::Owner(_) => {
+            match apply_owner_changes(&original_lic, &changed_lic, &server.owner_license_changes) {
+                Ok(lic) => lic,
+                Err(rc) => {
+                    log::info!(
+                        "Refused a change to the license for world {:?} by its owner",
+                        original_lic.name
+                    );
+                    p.add_int(VarID::ReasonCode, rc.into());
+                    conn.send(p);
+                    return;
+                }
+            }
+        }
     };
-    if server.database.license_change(&new_lic).is_err() {
+    let r = server.database.transaction(|| {
+        if server.database.license_change(&new_lic).is_err() {
+            return DatabaseResult::DatabaseError;
+        }
+        if new_lic.expiration == original_lic.expiration {
+            return DatabaseResult::Ok(());
+        }
+
+        // Only admins can move the expiration, which is kept in the license
+        // history like a renewal
+        server.database.license_add_history(
+            original_lic.id,
+            &LicenseHistoryQuery {
+                timestamp: unix_epoch_timestamp_u64(),
+                event: "change".to_string(),
+                old_expiration: original_lic.expiration,
+                new_expiration: new_lic.expiration,
+            },
+        )
+    });
+    if r.is_err() {
         p.add_int(VarID::ReasonCode, ReasonCode::UnableToChangeLicense as i32);
         conn.send(p);
         return;
@@ -143,7 +230,7 @@ pub fn license_change(server: &UniverseServer, cid: UniverseConnectionID, packet
 
     match server.database.license_by_name(&changed_lic.name) {
         DatabaseResult::Ok(Some(lic)) => {
-            let vars = license_to_vars(&lic, conn.has_admin_permissions());
+            let vars = license_to_vars(&lic);
             for v in vars {
                 p.add_var(v);
             }
@@ -159,33 +246,101 @@ pub fn license_change(server: &UniverseServer, cid: UniverseConnectionID, packet
     // TODO: Kill existing world if it is now invalid/expired
     p.add_int(VarID::ReasonCode, ReasonCode::Success.into());
     conn.send(p);
+
+    // A running world is hidden or shown right away
+    if server
+        .connections
+        .get_world_by_name(&new_lic.name)
+        .is_some()
+    {
+        if world_access::refresh_world(server, &new_lic.name).is_err() {
+            log::error!(
+                "Could not refresh who can see and enter world {:?}",
+                new_lic.name
+            );
+        }
+        for cid in server.connections.cids() {
+            regenerate_world_list(server, cid)
+        }
+    }
+}
+
+/// The expiration an admin asked for. The browser only gets the expiration
+/// as a 32-bit wire value, so a stored one past that is kept unless the admin
+/// actually changed it.
+fn admin_expiration(original: u64, changed: u64) -> u64 {
+    if changed == u64::from(wire_timestamp(original)) {
+        original
+    } else {
+        changed
+    }
+}
+
+/// The license an owner asked for, keeping everything owners may not change
+/// as it was. Changing a field they are not allowed to is refused outright,
+/// rather than silently ignored.
+fn apply_owner_changes(
+    original: &LicenseQuery,
+    changed: &LicenseQuery,
+    allowed: &OwnerLicenseChanges,
+) -> Result<LicenseQuery, ReasonCode> {
+    // The browser sends every field back, so only those that differ count as changes
+    let admin_only_changed = changed.expiration != u64::from(wire_timestamp(original.expiration))
+        || changed.users != original.users
+        || changed.world_size != original.world_size
+        || changed.voip != original.voip
+        || changed.plugins != original.plugins;
+    let refused_change = (!allowed.password && changed.password != original.password)
+        || (!allowed.email && changed.email != original.email)
+        || (!allowed.comment && changed.comment != original.comment)
+        || (!allowed.hidden && changed.hidden != original.hidden)
+        || (!allowed.tourists && changed.tourists != original.tourists);
+    if admin_only_changed || refused_change {
+        return Err(ReasonCode::Unauthorized);
+    }
+
+    Ok(LicenseQuery {
+        id: original.id,
+        name: original.name.clone(),
+        password: changed.password.clone(),
+        email: changed.email.clone(),
+        comment: changed.comment.clone(),
+        creation: original.creation,
+        expiration: original.expiration,
+        last_start: original.last_start,
+        last_address: original.last_address,
+        users: original.users,
+        world_size: original.world_size,
+        hidden: changed.hidden,
+        changed: 0,
+        tourists: changed.tourists,
+        voip: original.voip,
+        plugins: original.plugins,
+        owner: original.owner,
+    })
 }
 
-fn license_to_vars(lic: &LicenseQuery, admin: bool) -> Vec<AWPacketVar> {
-    let mut result = vec![
+fn license_to_vars(lic: &LicenseQuery) -> Vec<AWPacketVar> {
+    vec![
         AWPacketVar::string(VarID::WorldName, lic.name.clone()),
         AWPacketVar::uint(VarID::WorldLicenseID, lic.id),
         AWPacketVar::uint(VarID::WorldLicenseUsers, lic.users),
         AWPacketVar::uint(VarID::WorldLicenseRange, lic.world_size),
-    ];
-
-    if admin {
-        result.extend(vec![
-            AWPacketVar::string(VarID::WorldLicensePassword, lic.password.clone()),
-            AWPacketVar::string(VarID::WorldLicenseEmail, lic.email.clone()),
-            AWPacketVar::string(VarID::WorldLicenseComment, lic.comment.clone()),
-            AWPacketVar::uint(VarID::WorldLicenseCreation, lic.creation),
-            AWPacketVar::uint(VarID::WorldLicenseExpiration, lic.expiration),
-            AWPacketVar::uint(VarID::WorldLicenseLastStart, lic.last_start),
-            AWPacketVar::uint(VarID::WorldLicenseLastAddress, lic.last_address),
-            AWPacketVar::uint(VarID::WorldLicenseTourists, lic.tourists),
-            AWPacketVar::uint(VarID::WorldLicenseHidden, lic.hidden),
-            AWPacketVar::uint(VarID::WorldLicenseVoip, lic.voip),
-            AWPacketVar::uint(VarID::WorldLicensePlugins, lic.plugins),
-        ]);
-    }
-
-    result
+        AWPacketVar::string(VarID::WorldLicensePassword, lic.password.clone()),
+        AWPacketVar::string(VarID::WorldLicenseEmail, lic.email.clone()),
+        AWPacketVar::string(VarID::WorldLicenseComment, lic.comment.clone()),
+        AWPacketVar::uint(VarID::WorldLicenseCreation, wire_timestamp(lic.creation)),
+        AWPacketVar::uint(
+            VarID::WorldLicenseExpiration,
+            wire_timestamp(lic.expiration),
+        ),
+        AWPacketVar::uint(VarID::WorldLicenseLastStart, wire_timestamp(lic.last_start)),
+        AWPacketVar::uint(VarID::WorldLicenseLastAddress, lic.last_address),
+        AWPacketVar::uint(VarID::WorldLicenseTourists, lic.tourists),
+        AWPacketVar::uint(VarID::WorldLicenseHidden, lic.hidden),
+        AWPacketVar::uint(VarID::WorldLicenseVoip, lic.voip),
+        AWPacketVar::uint(VarID::WorldLicensePlugins, lic.plugins),
+    ]
 }
 
 fn check_valid_world_name(name: &str) -> Result<(), ReasonCode> {
@@ -228,6 +383,7 @@ fn license_from_packet(packet: &AWPacket) -> Result<LicenseQuery, String> {
         .ok_or_else(|| "No license comment".to_string())?;
     let expiration = packet
         .get_uint(VarID::WorldLicenseExpiration)
+        .map(u64::from)
         .ok_or_else(|| "No license expiration".to_string())?;
     let hidden = packet
         .get_uint(VarID::WorldLicenseHidden)
@@ -265,5 +421,73 @@ fn license_from_packet(packet: &AWPacket) -> Result<LicenseQuery, String> {
         voip,
         plugins,
         creation: 0,
+        owner: 0,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license() -> LicenseQuery {
+        LicenseQuery {
+            id: 3,
+            name: "owned".to_string(),
+            password: "secret".to_string(),
+            email: "owner@example.com".to_string(),
+            comment: String::new(),
+            creation: 1_000,
+            expiration: 2_000,
+            last_start: 1_500,
+            last_address: 0,
+            users: 20,
+            world_size: 100,
+            hidden: 0,
+            changed: 0,
+            tourists: 1,
+            voip: 0,
+            plugins: 0,
+            owner: 2,
+        }
+    }
+
+    #[test]
+    fn owners_change_only_allowed_fields() {
+        let allowed = OwnerLicenseChanges {
+            tourists: false,
+            ..Default::default()
+        };
+
+        let changed = LicenseQuery {
+            password: "hunter2".to_string(),
+            hidden: 1,
+            ..license()
+        };
+        let new_lic = apply_owner_changes(&license(), &changed, &allowed).unwrap();
+        assert_eq!(new_lic.password, "hunter2");
+        assert_eq!(new_lic.hidden, 1);
+        assert_eq!(new_lic.owner, 2);
+
+        let no_tourists = LicenseQuery {
+            tourists: 0,
+            ..license()
+        };
+        assert!(apply_owner_changes(&license(), &no_tourists, &allowed).is_err());
+
+        let more_users = LicenseQuery {
+            users: 200,
+            ..license()
+        };
+        assert!(apply_owner_changes(&license(), &more_users, &allowed).is_err());
+    }
+
+    #[test]
+    fn admins_keep_expirations_past_the_wire_range() {
+        let far = u64::from(u32::MAX) + 1_000;
+
+        assert_eq!(admin_expiration(far, u64::from(u32::MAX)), far);
+        assert_eq!(admin_expiration(far, 3_000), 3_000);
+        assert_eq!(admin_expiration(2_000, 2_000), 2_000);
+        assert_eq!(admin_expiration(2_000, 3_000), 3_000);
+    }
+}
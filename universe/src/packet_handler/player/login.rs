@@ -9,7 +9,7 @@ use crate::{
     player::{Bot, Citizen, GenericPlayer, Player},
     tabs::{regenerate_contact_list_and_mutuals, regenerate_player_list, regenerate_world_list},
     telegram::send_telegram_update_available,
-    timestamp::unix_epoch_timestamp_u32,
+    timestamp::unix_epoch_timestamp_u64,
     universe_connection::UniverseConnectionID,
     UniverseServer,
 };
@@ -33,6 +33,19 @@ pub fn login(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AW
     let _client_version = packet.get_int(VarID::BrowserVersion);
     let browser_build = packet.get_int(VarID::BrowserBuild);
 
+    // The login packet was decoded before the client's build was known
+    let mut packet = packet.clone();
+    let encoding = browser_build.map(|build| server.config.encoding_for_build(build));
+    let conn = get_conn_mut!(server, cid, "login");
+    let encoding = encoding.unwrap_or(conn.encoding());
+    if encoding != conn.encoding() {
+        packet.transcode_strings(conn.encoding(), encoding);
+    }
+    // The connection holds back the packets after this one until it is told
+    // which encoding to decode them with, even if it stays the same.
+    conn.set_encoding(encoding);
+    let packet = &packet;
+
     let mut response = AWPacket::new(PacketType::Login);
 
     let mut new_clientinfo: Option<ClientInfo> = None;
@@ -562,7 +575,7 @@ fn update_last_login_info(database: &UniverseDatabase, address: &IpAddr, cit_id:
     };
 
     cit_query.last_address = ip_u32;
-    cit_query.last_login = unix_epoch_timestamp_u32();
+    cit_query.last_login = unix_epoch_timestamp_u64();
 
     if let DatabaseResult::DatabaseError = database.citizen_change(&cit_query) {
         log::debug!("Can't update last login info due to database failure");
@@ -2,7 +2,7 @@ use crate::{
     client::ClientInfo,
     database::{telegram::TelegramQuery, CitizenDB, TelegramDB, UniverseDatabase},
     get_conn,
-    timestamp::unix_epoch_timestamp_u32,
+    timestamp::{unix_epoch_timestamp_u64, wire_timestamp},
     universe_connection::UniverseConnectionID,
     UniverseConnection, UniverseServer,
 };
@@ -20,10 +20,13 @@ pub fn telegram_get(server: &UniverseServer, cid: UniverseConnectionID, packet:
                     Some(cit) => cit.name,
                     None => "<unknown>".to_string(),
                 };
-                let now = unix_epoch_timestamp_u32();
+                let now = unix_epoch_timestamp_u64();
 
                 response.add_string(VarID::TelegramCitizenName, from_name);
-                response.add_uint(VarID::TelegramAge, now.saturating_sub(telegram.timestamp));
+                response.add_uint(
+                    VarID::TelegramAge,
+                    wire_timestamp(now.saturating_sub(telegram.timestamp)),
+                );
                 response.add_string(VarID::TelegramMessage, telegram.message);
                 response.add_byte(VarID::TelegramsMoreRemain, more_remain as u8);
 
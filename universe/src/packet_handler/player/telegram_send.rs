@@ -3,7 +3,7 @@ use crate::{
     database::{CitizenDB, ContactDB, TelegramDB, UniverseDatabase},
     get_conn,
     telegram::send_telegram_update_available,
-    timestamp::unix_epoch_timestamp_u32,
+    timestamp::unix_epoch_timestamp_u64,
     universe_connection::UniverseConnectionID,
     UniverseConnection, UniverseServer,
 };
@@ -77,7 +77,7 @@ fn try_send_telegram_from_packet(
         return Err(ReasonCode::TelegramBlocked);
     }
 
-    let now = unix_epoch_timestamp_u32();
+    let now = unix_epoch_timestamp_u64();
 
     match database.telegram_add(target_citizen.id, citizen_id, now, &message) {
         DatabaseResult::Ok(_) => Ok(target_citizen.id),
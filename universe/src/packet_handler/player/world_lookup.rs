@@ -1,6 +1,6 @@
 use std::net::IpAddr;
 
-use crate::{get_conn_mut, universe_connection::UniverseConnectionID, UniverseServer};
+use crate::{get_conn, get_conn_mut, universe_connection::UniverseConnectionID, UniverseServer};
 use aw_core::*;
 
 use rand::Rng;
@@ -26,7 +26,15 @@ pub fn world_lookup(server: &mut UniverseServer, cid: UniverseConnectionID, pack
 
     p.add_string(VarID::WorldName, world_name.clone());
 
-    match server.connections.get_world_entry_by_name(&world_name) {
+    // Worlds someone may not enter do not exist as far as they are concerned
+    let viewer = get_conn!(server, cid, "world_lookup").world_viewer();
+    let entry = server
+        .connections
+        .get_world_by_name(&world_name)
+        .filter(|world| world.can_look_up(viewer))
+        .and_then(|world| server.connections.get_world_entry_by_name(&world.name));
+
+    match entry {
         Some(world) => {
             let max_users = world.max_users;
             let world_size = world.world_size;
@@ -5,62 +5,28 @@ use crate::{
     universe_connection::UniverseConnectionID,
     UniverseServer,
 };
-use aw_core::{AWPacket, PacketType, ReasonCode, VarID};
-
-#[derive(Debug)]
-enum IdentifyParamsError {
-    WorldName,
-    Nonce,
-    SessionID,
-    PlayerIP,
-    PlayerPort,
-}
+use aw_core::{AWPacket, PacketFields, PacketType, ReasonCode, TypedPacket, VarID};
 
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::Identify)]
 struct IdentifyParams {
+    #[aw(var = VarID::WorldName)]
     world_name: String,
+    #[aw(var = VarID::WorldUserNonce)]
     nonce: Vec<u8>,
+    #[aw(var = VarID::SessionID)]
     session_id: u16,
+    #[aw(var = VarID::IdentifyUserIP)]
     player_ip: u32,
+    #[aw(var = VarID::PlayerPort)]
     player_port: u16,
 }
 
-impl TryFrom<&AWPacket> for IdentifyParams {
-    type Error = IdentifyParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let world_name = value
-            .get_string(VarID::WorldName)
-            .ok_or(IdentifyParamsError::WorldName)?;
-        let nonce = value
-            .get_data(VarID::WorldUserNonce)
-            .ok_or(IdentifyParamsError::Nonce)?;
-        let session_id = value
-            .get_uint(VarID::SessionID)
-            .and_then(|sid| u16::try_from(sid).ok())
-            .ok_or(IdentifyParamsError::SessionID)?;
-        let player_ip = value
-            .get_uint(VarID::IdentifyUserIP)
-            .ok_or(IdentifyParamsError::PlayerIP)?;
-        let player_port = value
-            .get_uint(VarID::PlayerPort)
-            .and_then(|port| u16::try_from(port).ok())
-            .ok_or(IdentifyParamsError::PlayerPort)?;
-
-        Ok(Self {
-            world_name,
-            nonce,
-            session_id,
-            player_ip,
-            player_port,
-        })
-    }
-}
-
 /// A connection (supposed to be a world server) wants to know information about a player.
 pub fn identify(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
     let mut p = AWPacket::new(PacketType::Identify);
 
-    let mut params = match IdentifyParams::try_from(packet) {
+    let mut params = match IdentifyParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete identify: {why:?}");
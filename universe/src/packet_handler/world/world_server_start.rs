@@ -2,44 +2,20 @@ use crate::{
     client::ClientInfo, get_conn_mut, universe_connection::UniverseConnectionID,
     world::WorldServer, UniverseServer,
 };
-use aw_core::{AWPacket, VarID};
-
-#[derive(Debug)]
-enum WorldServerStartParamsError {
-    Version,
-    Build,
-    Port,
-}
+use aw_core::{AWPacket, PacketFields, PacketType, TypedPacket, VarID};
+use std::collections::HashMap;
 
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::WorldServerStart)]
 struct WorldServerStartParams {
+    #[aw(var = VarID::BrowserVersion)]
     version: u32,
+    #[aw(var = VarID::WorldBuild)]
     build: u32,
+    #[aw(var = VarID::WorldPort)]
     port: u16,
 }
 
-impl TryFrom<&AWPacket> for WorldServerStartParams {
-    type Error = WorldServerStartParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let version = value
-            .get_uint(VarID::BrowserVersion)
-            .ok_or(WorldServerStartParamsError::Version)?;
-        let build = value
-            .get_uint(VarID::WorldBuild)
-            .ok_or(WorldServerStartParamsError::Build)?;
-        let port = value
-            .get_uint(VarID::WorldPort)
-            .and_then(|port| u16::try_from(port).ok())
-            .ok_or(WorldServerStartParamsError::Port)?;
-
-        Ok(Self {
-            version,
-            build,
-            port,
-        })
-    }
-}
-
 pub fn world_server_start(
     server: &mut UniverseServer,
     cid: UniverseConnectionID,
@@ -54,7 +30,7 @@ pub fn world_server_start(
         return;
     }
 
-    let params = match WorldServerStartParams::try_from(packet) {
+    let params = match WorldServerStartParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete world server start: {why:?}");
@@ -66,6 +42,7 @@ pub fn world_server_start(
         build: params.build,
         server_port: params.port,
         worlds: Vec::new(),
+        key_challenges: HashMap::new(),
     }));
 
     log::info!(
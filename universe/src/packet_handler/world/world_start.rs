@@ -1,57 +1,36 @@
-use aw_core::{AWPacket, PacketType, ReasonCode, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, ReasonCode, TypedPacket, VarID};
 use aw_db::DatabaseResult;
 
 use crate::{
-    database::{attrib::Attribute, license::LicenseQuery, AttribDB, LicenseDB},
-    get_conn, get_conn_mut,
+    database::{
+        attrib::Attribute,
+        license::{LicenseQuery, ServerRestrictionQuery},
+        AttribDB, LicenseDB,
+    },
+    get_conn, get_conn_mut, license_expiry,
     tabs::regenerate_world_list,
-    timestamp::unix_epoch_timestamp_u32,
-    universe_connection::UniverseConnectionID,
+    timestamp::{unix_epoch_timestamp_u64, wire_timestamp},
+    universe_connection::{UniverseConnection, UniverseConnectionID},
     world::{World, WorldRating},
-    UniverseServer,
+    world_access,
+    world_auth::{self, KeyChallenge, StartRefusal},
+    world_quota, UniverseServer,
 };
+use std::{net::IpAddr, time::Instant};
 
-#[derive(Debug)]
-enum WorldStartParamsError {
-    Name,
-    Password,
-    Rating,
-    FreeEntry,
-}
-
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::WorldStart)]
 struct WorldStartParams {
+    #[aw(var = VarID::WorldName)]
     world_name: String,
+    #[aw(var = VarID::WorldLicensePassword)]
     world_password: String,
+    #[aw(var = VarID::WorldRating)]
     world_rating: WorldRating,
+    #[aw(var = VarID::WorldFreeEntry)]
     world_free_entry: bool,
-}
-
-impl TryFrom<&AWPacket> for WorldStartParams {
-    type Error = WorldStartParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let world_name = value
-            .get_string(VarID::WorldName)
-            .ok_or(WorldStartParamsError::Name)?;
-        let world_password = value
-            .get_string(VarID::WorldLicensePassword)
-            .ok_or(WorldStartParamsError::Password)?;
-        let world_rating = value
-            .get_byte(VarID::WorldRating)
-            .and_then(WorldRating::from_u8)
-            .ok_or(WorldStartParamsError::Rating)?;
-        let world_free_entry = value
-            .get_byte(VarID::WorldFreeEntry)
-            .map(|free_entry| free_entry != 0)
-            .ok_or(WorldStartParamsError::FreeEntry)?;
-
-        Ok(WorldStartParams {
-            world_name,
-            world_password,
-            world_rating,
-            world_free_entry,
-        })
-    }
+    #[aw(var = VarID::WorldServerChallenge)]
+    challenge_answer: Option<Vec<u8>>,
 }
 
 pub fn world_start(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
@@ -63,7 +42,7 @@ pub fn world_start(server: &mut UniverseServer, cid: UniverseConnectionID, packe
 
     let world_build = world_server.build;
 
-    let params = match WorldStartParams::try_from(packet) {
+    let params = match WorldStartParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete world start: {why:?}");
@@ -83,13 +62,44 @@ pub fn world_start(server: &mut UniverseServer, cid: UniverseConnectionID, packe
     ) {
         Ok(x) => x,
         Err(rc) => {
-            log::info!("Unable to start world: {rc:?}");
+            log::info!("Unable to start world: {rc}");
             p.add_int(VarID::ReasonCode, rc as i32);
             conn.send(p);
             return;
         }
     };
 
+    let ip = conn.addr().ip();
+    let challenge = match authenticate_world_server(
+        server,
+        cid,
+        &lic,
+        ip,
+        params.challenge_answer.as_deref(),
+    ) {
+        Ok(challenge) => challenge,
+        Err(rc) => {
+            let conn = get_conn!(server, cid, "world_start");
+            log::info!("Unable to start world: {rc}");
+            p.add_int(VarID::ReasonCode, rc as i32);
+            conn.send(p);
+            return;
+        }
+    };
+    let conn = get_conn!(server, cid, "world_start");
+
+    // World servers that cannot answer the challenge take this as a refusal
+    if let Some(challenge) = challenge {
+        log::info!(
+            "Asking the world server at {ip} to prove it holds the key registered for world {:?}",
+            lic.name
+        );
+        p.add_data(VarID::WorldServerChallenge, challenge);
+        p.add_int(VarID::ReasonCode, ReasonCode::Imposter as i32);
+        conn.send(p);
+        return;
+    }
+
     // Don't let clients start a world twice
     if server.connections.get_world_by_name(&lic.name).is_some() {
         log::info!(
@@ -102,6 +112,21 @@ pub fn world_start(server: &mut UniverseServer, cid: UniverseConnectionID, packe
         return;
     }
 
+    if let Err(rc) = check_running_world_quota(server, &lic) {
+        p.add_int(VarID::ReasonCode, rc as i32);
+        conn.send(p);
+        return;
+    }
+
+    let (visibility, access_list) = match world_access::license_access(&server.database, &lic) {
+        DatabaseResult::Ok(access) => access,
+        DatabaseResult::DatabaseError => {
+            p.add_int(VarID::ReasonCode, ReasonCode::DatabaseError.into());
+            conn.send(p);
+            return;
+        }
+    };
+
     let new_world = World {
         name: lic.name.clone(),
         free_entry: params.world_free_entry,
@@ -109,9 +134,16 @@ pub fn world_start(server: &mut UniverseServer, cid: UniverseConnectionID, packe
         max_users: lic.users,
         rating: params.world_rating,
         user_count: 0,
+        owner: lic.owner,
+        started_at: unix_epoch_timestamp_u64(),
+        visibility,
+        access_list,
     };
 
-    p.add_uint(VarID::WorldLicenseExpiration, lic.expiration);
+    p.add_uint(
+        VarID::WorldLicenseExpiration,
+        wire_timestamp(lic.expiration),
+    );
     p.add_uint(VarID::WorldLicenseUsers, lic.users);
     p.add_uint(VarID::WorldLicenseRange, lic.world_size);
     p.add_uint(VarID::WorldLicenseVoip, lic.voip);
@@ -121,6 +153,13 @@ pub fn world_start(server: &mut UniverseServer, cid: UniverseConnectionID, packe
 
     conn.send(p);
 
+    let now = unix_epoch_timestamp_u64();
+    if let Some(warning) =
+        license_expiry::expiry_warning(&lic, server.config.license_warning_days, now)
+    {
+        conn.send(expiry_console_message(warning));
+    }
+
     add_world_to_world_server(server, cid, new_world);
 
     // Add information about the new world to everyone's world list
@@ -129,6 +168,18 @@ pub fn world_start(server: &mut UniverseServer, cid: UniverseConnectionID, packe
     }
 }
 
+/// A red console message for the world server's operator.
+fn expiry_console_message(warning: String) -> AWPacket {
+    let mut p = AWPacket::new(PacketType::ConsoleMessage);
+    p.add_string(VarID::ChatMessage, warning);
+    p.add_byte(VarID::ConsoleBold, 1);
+    p.add_byte(VarID::ConsoleItalics, 0);
+    p.add_uint(VarID::ConsoleRed, 255);
+    p.add_uint(VarID::ConsoleGreen, 0);
+    p.add_uint(VarID::ConsoleBlue, 0);
+    p
+}
+
 fn add_world_to_world_server(server: &mut UniverseServer, cid: UniverseConnectionID, world: World) {
     let conn = get_conn_mut!(server, cid, "add_world_to_world_server");
 
@@ -139,6 +190,119 @@ fn add_world_to_world_server(server: &mut UniverseServer, cid: UniverseConnectio
     world_server.worlds.push(world);
 }
 
+/// Check a world server against the restrictions of the license it is
+/// starting, reporting refusals and concurrent attempts to the owner. If the
+/// license has a registered key, this returns a challenge for the world
+/// server to decrypt and send back with its next start of the world.
+fn authenticate_world_server(
+    server: &mut UniverseServer,
+    cid: UniverseConnectionID,
+    lic: &LicenseQuery,
+    ip: IpAddr,
+    challenge_answer: Option<&[u8]>,
+) -> Result<Option<Vec<u8>>, ReasonCode> {
+    let restrictions = match server.database.license_server_restrictions(lic.id) {
+        DatabaseResult::Ok(restrictions) => restrictions.unwrap_or_default(),
+        DatabaseResult::DatabaseError => return Err(ReasonCode::DatabaseError),
+    };
+
+    if let Err(refusal) = world_auth::check_restrictions(&restrictions, ip) {
+        world_auth::report(server, lic, &format!("Refused to start it: {refusal}."));
+        return Err(refusal.reason_code());
+    }
+
+    if let Some(other) = server.start_attempts.record(&lic.name, ip, Instant::now()) {
+        world_auth::report(
+            server,
+            lic,
+            &format!("Both {other} and {ip} tried to start it within a minute."),
+        );
+    }
+
+    if let Some(running) = server.connections.get_world_entry_by_name(&lic.name) {
+        if running.ip != ip {
+            let refusal = StartRefusal::RunningElsewhere {
+                running: running.ip,
+                attempt: ip,
+            };
+            world_auth::report(server, lic, &format!("Refused to start it: {refusal}."));
+            return Err(refusal.reason_code());
+        }
+    }
+
+    check_server_key(server, cid, lic, ip, &restrictions, challenge_answer)
+}
+
+/// Check that a world server holds the key registered for a license, by
+/// challenging it first and then checking its answer. Each challenge can only
+/// be answered once.
+fn check_server_key(
+    server: &mut UniverseServer,
+    cid: UniverseConnectionID,
+    lic: &LicenseQuery,
+    ip: IpAddr,
+    restrictions: &ServerRestrictionQuery,
+    challenge_answer: Option<&[u8]>,
+) -> Result<Option<Vec<u8>>, ReasonCode> {
+    if restrictions.server_key.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let Some(world_server) = server
+        .connections
+        .get_connection_mut(cid)
+        .and_then(UniverseConnection::world_server_mut)
+    else {
+        return Err(ReasonCode::Imposter);
+    };
+    let key = lic.name.to_lowercase();
+    let expected = world_server.key_challenges.remove(&key);
+
+    match (challenge_answer, expected) {
+        (Some(answer), Some(expected)) if answer == expected.as_slice() => Ok(None),
+        (Some(_), _) => {
+            let refusal = StartRefusal::WrongServerKey(ip);
+            world_auth::report(server, lic, &format!("Refused to start it: {refusal}."));
+            Err(refusal.reason_code())
+        }
+        (None, _) => {
+            let Some(challenge) = KeyChallenge::new(&restrictions.server_key) else {
+                log::error!(
+                    "The key registered for world {:?} is not a public key",
+                    lic.name
+                );
+                return Err(ReasonCode::Imposter);
+            };
+            world_server.key_challenges.insert(key, challenge.expected);
+            Ok(Some(challenge.encrypted))
+        }
+    }
+}
+
+/// Refuse to start a world whose owner already has as many worlds up as
+/// their quota allows.
+fn check_running_world_quota(
+    server: &UniverseServer,
+    lic: &LicenseQuery,
+) -> Result<(), ReasonCode> {
+    let quota = match world_quota::owner_quota(&server.database, &server.world_quota, lic.owner) {
+        DatabaseResult::Ok(quota) => quota,
+        DatabaseResult::DatabaseError => return Err(ReasonCode::DatabaseError),
+    };
+
+    let running = server.connections.count_worlds_by_owner(lic.owner);
+    if !world_quota::can_start_world(quota.as_ref(), running) {
+        log::info!(
+            "Not starting world {:?} because citizen {} already has {running} worlds running",
+            lic.name,
+            lic.owner
+        );
+        return Err(ReasonCode::TooManyWorlds);
+    }
+
+    Ok(())
+}
+
 fn validate_world(
     server: &UniverseServer,
     world_build: u32,
@@ -184,7 +348,7 @@ fn validate_world(
         return Err(ReasonCode::InvalidPassword);
     }
 
-    let now = unix_epoch_timestamp_u32();
+    let now = unix_epoch_timestamp_u64();
 
     // Check if world is expired
     if world_lic.expiration != 0 && world_lic.expiration < now {
@@ -1,59 +1,29 @@
-use aw_core::{AWPacket, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, TypedPacket, VarID};
 
 use crate::{
     get_conn_mut, tabs::regenerate_world_list, universe_connection::UniverseConnectionID,
     world::WorldRating, UniverseServer,
 };
 
-#[derive(Debug)]
-enum WorldStatsUpdateParamsError {
-    Rating,
-    FreeEntry,
-    UserCount,
-    Name,
-}
-
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::WorldStatsUpdate)]
 struct WorldStatsUpdateParams {
+    #[aw(var = VarID::WorldRating)]
     world_rating: WorldRating,
+    #[aw(var = VarID::WorldFreeEntry)]
     world_free_entry: bool,
+    #[aw(var = VarID::WorldUsers)]
     world_user_count: u32,
+    #[aw(var = VarID::WorldName)]
     world_name: String,
 }
 
-impl TryFrom<&AWPacket> for WorldStatsUpdateParams {
-    type Error = WorldStatsUpdateParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let world_rating = value
-            .get_byte(VarID::WorldRating)
-            .and_then(WorldRating::from_u8)
-            .ok_or(WorldStatsUpdateParamsError::Rating)?;
-        let world_free_entry = value
-            .get_byte(VarID::WorldFreeEntry)
-            .map(|free_entry| free_entry != 0)
-            .ok_or(WorldStatsUpdateParamsError::FreeEntry)?;
-        let world_user_count = value
-            .get_uint(VarID::WorldUsers)
-            .ok_or(WorldStatsUpdateParamsError::UserCount)?;
-        let world_name = value
-            .get_string(VarID::WorldName)
-            .ok_or(WorldStatsUpdateParamsError::Name)?;
-
-        Ok(Self {
-            world_rating,
-            world_free_entry,
-            world_user_count,
-            world_name,
-        })
-    }
-}
-
 pub fn world_stats_update(
     server: &mut UniverseServer,
     cid: UniverseConnectionID,
     packet: &AWPacket,
 ) {
-    let params = match WorldStatsUpdateParams::try_from(packet) {
+    let params = match WorldStatsUpdateParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete world stats update: {why:?}");
@@ -1,33 +1,19 @@
-use aw_core::{AWPacket, PacketType, ReasonCode, VarID};
+use aw_core::{AWPacket, PacketFields, PacketType, ReasonCode, TypedPacket, VarID};
 
 use crate::{
     get_conn_mut, tabs::regenerate_world_list, universe_connection::UniverseConnectionID,
     UniverseServer,
 };
 
-#[derive(Debug)]
-enum WorldStopParamsError {
-    NoName,
-}
-
+#[derive(TypedPacket)]
+#[aw(packet = PacketType::WorldStop)]
 struct WorldStopParams {
+    #[aw(var = VarID::WorldName)]
     world_name: String,
 }
 
-impl TryFrom<&AWPacket> for WorldStopParams {
-    type Error = WorldStopParamsError;
-
-    fn try_from(value: &AWPacket) -> Result<Self, Self::Error> {
-        let world_name = value
-            .get_string(VarID::WorldName)
-            .ok_or(WorldStopParamsError::NoName)?;
-
-        Ok(WorldStopParams { world_name })
-    }
-}
-
 pub fn world_stop(server: &mut UniverseServer, cid: UniverseConnectionID, packet: &AWPacket) {
-    let params = match WorldStopParams::try_from(packet) {
+    let params = match WorldStopParams::decode(packet) {
         Ok(params) => params,
         Err(why) => {
             log::debug!("Could not complete world stop: {why:?}");
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use aw_core::{AWPacket, AWPacketGroup, PacketType, VarID};
+use aw_core::{encoding::StringEncoding, AWPacket, AWPacketGroup, PacketType, VarID};
 use aw_db::DatabaseResult;
 
 use crate::{
@@ -131,8 +131,8 @@ impl ContactList {
     //     groups
     // }
 
-    fn make_packet_group(&self) -> AWPacketGroup {
-        let mut group = AWPacketGroup::new();
+    fn make_packet_group(&self, encoding: StringEncoding) -> AWPacketGroup {
+        let mut group = AWPacketGroup::with_encoding(encoding);
 
         let ordered_ids = {
             let mut k = self
@@ -210,7 +210,7 @@ impl ContactList {
     /// This is "limited" because it only makes as many packets as it can before the
     /// length gets over 0x1000. The client must request another starting from a new citizen ID.
     pub fn send_limited_list(&self, target: &UniverseConnection) {
-        let group = self.make_packet_group();
+        let group = self.make_packet_group(target.encoding());
         target.send_group(group);
     }
 }
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, net::IpAddr};
 
-use aw_core::{AWPacket, AWPacketGroup, PacketType, VarID};
+use aw_core::{encoding::StringEncoding, AWPacket, AWPacketGroup, PacketType, VarID};
 
 use crate::{
     client::ClientInfo, get_conn_mut, player::Player, universe_connection::UniverseConnectionID,
@@ -211,6 +211,7 @@ impl PlayerList {
         &self,
         continuation_id: u32,
         to_admin: bool,
+        encoding: StringEncoding,
     ) -> AWPacketGroup {
         let ids_in_order = {
             let mut k = self
@@ -224,7 +225,7 @@ impl PlayerList {
         };
 
         // Group packets into larger transmissions for efficiency
-        let mut group = AWPacketGroup::new();
+        let mut group = AWPacketGroup::with_encoding(encoding);
         let mut next_continuation_id: Option<PlayerListID> = None;
 
         for list_id in ids_in_order {
@@ -276,13 +277,13 @@ impl PlayerList {
     }
 
     pub fn send_full_list(&self, target: &UniverseConnection) {
-        let mut group = AWPacketGroup::new();
+        let mut group = AWPacketGroup::with_encoding(target.encoding());
 
         for (id, player) in &self.players {
             match group.serialize_len() {
                 Ok(len) if len > 0x4000 => {
                     target.send_group(group);
-                    group = AWPacketGroup::new();
+                    group = AWPacketGroup::with_encoding(target.encoding());
                 }
                 Err(why) => {
                     log::error!("group.serialize_len() failed: {why:?}");
@@ -306,8 +307,11 @@ impl PlayerList {
     }
 
     pub fn send_list_starting_from(&self, target: &UniverseConnection, continuation_id: u32) {
-        let group =
-            self.make_packet_group_starting_from(continuation_id, target.has_admin_permissions());
+        let group = self.make_packet_group_starting_from(
+            continuation_id,
+            target.has_admin_permissions(),
+            target.encoding(),
+        );
 
         target.send_group(group);
     }
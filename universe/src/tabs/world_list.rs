@@ -1,9 +1,9 @@
 use std::{collections::HashMap, net::IpAddr};
 
-use aw_core::{AWPacket, AWPacketGroup, PacketType, VarID};
+use aw_core::{encoding::StringEncoding, AWPacket, AWPacketGroup, PacketType, VarID};
 
 use crate::{
-    client::ClientInfo, get_conn_mut, timestamp::unix_epoch_timestamp_u32,
+    client::ClientInfo, get_conn, get_conn_mut, timestamp::unix_epoch_timestamp_u32,
     universe_connection::UniverseConnectionID, world::WorldRating, UniverseConnection,
     UniverseServer,
 };
@@ -72,7 +72,7 @@ impl WorldList {
         self.entries.values().find(|&entry| entry.name == name)
     }
 
-    pub fn make_packet_groups(&self) -> Vec<AWPacketGroup> {
+    pub fn make_packet_groups(&self, encoding: StringEncoding) -> Vec<AWPacketGroup> {
         let now = unix_epoch_timestamp_u32();
 
         let world_packets = self
@@ -83,12 +83,12 @@ impl WorldList {
 
         // Group packets into larger transmissions for efficiency
         let mut groups: Vec<AWPacketGroup> = Vec::new();
-        let mut group = AWPacketGroup::new();
+        let mut group = AWPacketGroup::with_encoding(encoding);
 
         for world_packet in world_packets {
             if let Err(p) = group.push(world_packet) {
                 groups.push(group);
-                group = AWPacketGroup::new();
+                group = AWPacketGroup::with_encoding(encoding);
 
                 let mut more = AWPacket::new(PacketType::WorldListResult);
                 // Yes, expect another WorldList packet from the server
@@ -106,7 +106,7 @@ impl WorldList {
 
         if let Err(p) = group.push(p) {
             groups.push(group);
-            group = AWPacketGroup::new();
+            group = AWPacketGroup::with_encoding(encoding);
             group.push(p).ok();
         }
 
@@ -116,7 +116,7 @@ impl WorldList {
     }
 
     pub fn send_list(&self, target: &UniverseConnection) {
-        let groups = self.make_packet_groups();
+        let groups = self.make_packet_groups(target.encoding());
 
         for group in groups {
             target.send_group(group.clone());
@@ -198,7 +198,8 @@ impl Default for UpdatingWorldList {
 }
 
 pub fn regenerate_world_list(server: &mut UniverseServer, cid: UniverseConnectionID) {
-    let entries = server.connections.get_all_world_entries();
+    let viewer = get_conn!(server, cid, "regenerate_world_list").world_viewer();
+    let entries = server.connections.get_all_world_entries(viewer);
     let conn = get_conn_mut!(server, cid, "regenerate_world_list");
     let Some(ClientInfo::Player(p)) = &mut conn.client else {
         return;
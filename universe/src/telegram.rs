@@ -3,9 +3,32 @@ use aw_db::DatabaseResult;
 
 use crate::{
     client::ClientInfo, database::TelegramDB, get_conn, player::Player,
-    universe_connection::UniverseConnectionID, UniverseServer,
+    timestamp::unix_epoch_timestamp_u64, universe_connection::UniverseConnectionID, UniverseServer,
 };
 
+/// Citizen that notices from the universe are sent from, which is the
+/// Administrator account created with the database.
+const NOTICE_SENDER: u32 = 1;
+
+/// Send a telegram from the universe to a citizen, and tell them about it
+/// if they are online.
+pub fn send_notice(server: &UniverseServer, citizen_id: u32, message: &str) -> DatabaseResult<()> {
+    let now = unix_epoch_timestamp_u64();
+    if server
+        .database
+        .telegram_add(citizen_id, NOTICE_SENDER, now, message)
+        .is_err()
+    {
+        return DatabaseResult::DatabaseError;
+    }
+
+    if let Some(cid) = server.connections.get_by_citizen_id(citizen_id) {
+        send_telegram_update_available(server, cid);
+    }
+
+    DatabaseResult::Ok(())
+}
+
 pub fn send_telegram_update_available(server: &UniverseServer, cid: UniverseConnectionID) {
     let conn = get_conn!(server, cid, "send_telegram_update_available");
 
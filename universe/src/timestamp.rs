@@ -1,9 +1,9 @@
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
 pub fn unix_epoch_timestamp_u32() -> u32 {
-    u32::try_from(unix_epoch_timestamp_u64())
-        // If we are past the max u32, the best we can do is report the latest possible time
-        .unwrap_or(u32::MAX)
+    wire_timestamp(unix_epoch_timestamp_u64())
 }
 
 pub fn unix_epoch_timestamp_u64() -> u64 {
@@ -20,3 +20,11 @@ pub fn unix_epoch_timestamp_u64() -> u64 {
 pub fn unix_epoch_timestamp_str() -> String {
     unix_epoch_timestamp_u64().to_string()
 }
+
+/// Convert a timestamp to the 32-bit value sent in packets. Times are kept as
+/// 64-bit everywhere else, and only narrowed here at the protocol boundary.
+pub fn wire_timestamp(timestamp: u64) -> u32 {
+    u32::try_from(timestamp)
+        // If we are past the max u32, the best we can do is report the latest possible time
+        .unwrap_or(u32::MAX)
+}
@@ -1,18 +1,25 @@
-use std::{collections::HashMap, net::SocketAddr, time::Instant};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 
-use aw_core::{AWConnection, AWCryptRSA, AWPacket, AWPacketGroup, PacketType, ProtocolMessage};
+use aw_core::{
+    encoding::StringEncoding, AWConnection, AWPacket, AWPacketGroup, HandshakeAction, PacketType,
+    ProtocolMessage, SendQueue, ServerHandshake, TrafficStats,
+};
 
 use crate::{
     client::ClientInfo,
     player::{GenericPlayer, Player},
     tabs::{WorldListEntry, WorldStatus},
-    world::{World, WorldServer},
+    world::{World, WorldServer, WorldViewer},
 };
 
 #[derive(Debug)]
 pub struct UniverseConnection {
     connection: AWConnection,
-    pub rsa: AWCryptRSA,
+    pub handshake: ServerHandshake,
     pub last_heartbeat_sent: Instant,
     pub last_heartbeat_received: Instant,
     /// A connection may not have one of these yet if they just connected.
@@ -21,9 +28,11 @@ pub struct UniverseConnection {
 
 impl UniverseConnection {
     pub fn new(connection: AWConnection) -> Self {
+        // A new RSA key pair is made for every client.
+        let handshake = ServerHandshake::new(connection.get_send_key());
         Self {
             connection,
-            rsa: AWCryptRSA::new(),
+            handshake,
             last_heartbeat_sent: Instant::now(),
             last_heartbeat_received: Instant::now(),
             client: None,
@@ -52,20 +61,46 @@ impl UniverseConnection {
         self.connection.addr()
     }
 
+    /// Packets waiting to be sent to this connection.
+    pub fn send_queue(&self) -> &SendQueue {
+        self.connection.send_queue()
+    }
+
+    /// Traffic sent and received on this connection so far.
+    pub fn traffic_stats(&self) -> TrafficStats {
+        self.connection.traffic_stats()
+    }
+
+    /// How long ago this connection was established.
+    pub fn age(&self) -> Duration {
+        self.connection.age()
+    }
+
+    /// Short description of who is on the other end, for logs.
+    pub fn describe(&self) -> String {
+        match &self.client {
+            Some(ClientInfo::Player(player)) => format!("player {:?}", player.username()),
+            Some(ClientInfo::WorldServer(_)) => "world server".to_string(),
+            None => "unidentified client".to_string(),
+        }
+    }
+
     pub fn disconnect(&mut self) {
         self.connection.disconnect()
     }
 
-    pub fn set_recv_key(&self, key: &[u8]) {
-        self.connection.set_recv_key(key)
+    /// Encode strings sent to and from this connection differently from now on.
+    pub fn set_encoding(&mut self, encoding: StringEncoding) {
+        self.connection.set_encoding(encoding)
     }
 
-    pub fn get_send_key(&self) -> Vec<u8> {
-        self.connection.get_send_key()
+    pub fn encoding(&self) -> StringEncoding {
+        self.connection.encoding()
     }
 
-    pub fn encrypt_data(&self, should: bool) {
-        self.connection.encrypt_data(should)
+    pub fn apply_handshake(&self, actions: Vec<HandshakeAction>) {
+        log::trace!("Applying handshake with {}: {actions:?}", self.addr());
+        self.connection.apply_handshake(actions)
     }
 
     pub fn has_admin_permissions(&self) -> bool {
@@ -76,6 +111,18 @@ impl UniverseConnection {
         }
     }
 
+    pub fn citizen_id(&self) -> Option<u32> {
+        self.client.as_ref().and_then(ClientInfo::citizen_id)
+    }
+
+    /// Who this connection is when deciding which worlds it can see.
+    pub fn world_viewer(&self) -> WorldViewer {
+        WorldViewer {
+            citizen_id: self.citizen_id(),
+            admin: self.has_admin_permissions(),
+        }
+    }
+
     pub fn player_info(&self) -> Option<&GenericPlayer> {
         if let Some(info) = &self.client {
             info.player_info()
@@ -141,6 +188,8 @@ macro_rules! get_conn_mut {
 pub struct UniverseConnections {
     connections: HashMap<UniverseConnectionID, UniverseConnection>,
     next_id: UniverseConnectionID,
+    /// Traffic of connections that have already been removed
+    closed_traffic: TrafficStats,
 }
 
 impl Default for UniverseConnections {
@@ -148,6 +197,7 @@ impl Default for UniverseConnections {
         Self {
             connections: HashMap::new(),
             next_id: UniverseConnectionID(0),
+            closed_traffic: TrafficStats::default(),
         }
     }
 }
@@ -248,7 +298,13 @@ impl UniverseConnections {
 
             // 30 seconds between each heartbeat
             if time_since_heartbeat.as_secs() >= 30 {
-                log::debug!("Sending heartbeat to {}", conn.connection.addr().ip());
+                let queue = conn.send_queue();
+                log::debug!(
+                    "Sending heartbeat to {} ({} packets, {} bytes queued)",
+                    conn.addr().ip(),
+                    queue.packets(),
+                    queue.bytes()
+                );
                 let packet = AWPacket::new(PacketType::Heartbeat);
                 conn.connection.send(packet);
                 conn.last_heartbeat_sent = now;
@@ -256,6 +312,30 @@ impl UniverseConnections {
         }
     }
 
+    /// Traffic of every connection added together, including the ones that
+    /// have closed since the universe started.
+    pub fn traffic_totals(&self) -> TrafficStats {
+        let mut totals = self.closed_traffic.clone();
+        for conn in self.connections.values() {
+            totals.merge(&conn.traffic_stats());
+        }
+        totals
+    }
+
+    /// The connections that have moved the most bytes on the wire, most first.
+    pub fn busiest_connections(&self, count: usize) -> Vec<(&UniverseConnection, TrafficStats)> {
+        let mut result = self
+            .connections
+            .values()
+            .map(|conn| (conn, conn.traffic_stats()))
+            .collect::<Vec<_>>();
+        result.sort_by_key(|(_, stats)| {
+            std::cmp::Reverse(stats.sent.wire_bytes + stats.received.wire_bytes)
+        });
+        result.truncate(count);
+        result
+    }
+
     pub fn disconnected_cids(&self) -> Vec<UniverseConnectionID> {
         self.connections
             .iter()
@@ -266,7 +346,12 @@ impl UniverseConnections {
 
     pub fn remove_disconnected(&mut self) {
         for cid in self.disconnected_cids() {
-            self.connections.remove(&cid);
+            if let Some(conn) = self.connections.remove(&cid) {
+                // Round trip times and queued packets only mean anything while connected
+                let stats = conn.traffic_stats();
+                self.closed_traffic.sent.merge(&stats.sent);
+                self.closed_traffic.received.merge(&stats.received);
+            }
         }
     }
 
@@ -344,6 +429,28 @@ impl UniverseConnections {
         None
     }
 
+    pub fn get_world_by_name_mut(&mut self, name: &str) -> Option<&mut World> {
+        self.connections
+            .values_mut()
+            .filter_map(UniverseConnection::world_server_mut)
+            .find_map(|server| server.get_world_mut(name))
+    }
+
+    /// Every running world.
+    pub fn worlds(&self) -> impl Iterator<Item = &World> {
+        self.connections
+            .values()
+            .filter_map(UniverseConnection::world_server)
+            .flat_map(|server| &server.worlds)
+    }
+
+    /// Number of running worlds whose license belongs to a citizen.
+    pub fn count_worlds_by_owner(&self, citizen_id: u32) -> usize {
+        self.worlds()
+            .filter(|world| world.owner == citizen_id)
+            .count()
+    }
+
     pub fn get_world_entry_by_name(&self, name: &str) -> Option<WorldListEntry> {
         for conn in self.connections.values() {
             let Some(user_info) = conn.client.as_ref() else {
@@ -372,7 +479,8 @@ impl UniverseConnections {
         None
     }
 
-    pub fn get_all_world_entries(&self) -> Vec<WorldListEntry> {
+    /// Entries of the running worlds that are listed for a viewer.
+    pub fn get_all_world_entries(&self, viewer: WorldViewer) -> Vec<WorldListEntry> {
         let mut entries = Vec::<WorldListEntry>::new();
         for conn in self.connections.values() {
             let Some(user_info) = conn.client.as_ref() else {
@@ -384,6 +492,10 @@ impl UniverseConnections {
             };
 
             for world in &server.worlds {
+                if !world.is_listed_for(viewer) {
+                    continue;
+                }
+
                 entries.push(WorldListEntry {
                     name: world.name.clone(),
                     status: WorldStatus::from_free_entry(world.free_entry),
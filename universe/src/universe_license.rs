@@ -1,6 +1,22 @@
+use crate::configuration::{LicenseConfig, LicenseRule, BUNDLED_AW_KEY, BUNDLED_VORTEX_KEY};
 use aw_core::*;
 use std::net::SocketAddrV4;
 
+#[derive(thiserror::Error, Debug)]
+pub enum LicenseConfigError {
+    #[error("Could not read license key {path}: {source}")]
+    ReadKey {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Could not decode license key {0}")]
+    DecodeKey(String),
+    #[error("Could not encrypt a license with key {0}")]
+    Encrypt(String),
+    #[error("Invalid license for key {key}: {reason}")]
+    InvalidFields { key: String, reason: String },
+}
+
 /// Generates licenses for sending to clients.
 ///
 /// ActiveWorlds relies on providing Universe owners with a license containing
@@ -17,36 +33,73 @@ use std::net::SocketAddrV4;
 /// to connect.
 ///
 /// This also provides compatibility with the Vortex ActiveWorlds 5.1 client.
+///
+/// Which key and license fields a client build gets is configured with
+/// [`LicenseRule`]s. Licenses never change while the universe runs, so they
+/// are all encrypted up front.
 pub struct LicenseGenerator {
-    ip: SocketAddrV4,
+    licenses: Vec<(LicenseRule, Vec<u8>)>,
+    fallback: Vec<u8>,
 }
 
 impl LicenseGenerator {
-    pub fn new(ip: &SocketAddrV4) -> Self {
-        Self { ip: *ip }
+    pub fn new(ip: &SocketAddrV4, config: &LicenseConfig) -> Result<Self, LicenseConfigError> {
+        let mut licenses = Vec::new();
+        for rule in &config.rules {
+            licenses.push((rule.clone(), generate_license(ip, rule)?));
+        }
+
+        let fallback = generate_license(ip, &LicenseRule::with_key(BUNDLED_AW_KEY))?;
+
+        Ok(Self { licenses, fallback })
     }
 
     pub fn create_license_data(&self, browser_build: i32) -> Vec<u8> {
-        let key = match browser_build {
-            /* Vortex 5.1 */ 1217 => include_bytes!("keys/vortex.priv"),
-            /* Vortex 5.1 SDK */ 85 => include_bytes!("keys/vortex.priv"),
-            /* Miuchiz R7 */ 2007 => include_bytes!("keys/vortex.priv"),
-            /* Regular AW */ _ => include_bytes!("keys/aw.priv"),
-        };
-
-        let mut rsa = AWCryptRSA::default();
-        rsa.decode_private_key(key)
-            .expect("Couldn't decode RSA key.");
-
-        let mut reg_lic = AWRegLic::new(rsa);
-        let reg_lic_data = AWRegLicData::default()
-            .set_ip_address(self.ip.ip())
-            .set_port(self.ip.port() as u32)
-            .set_name("aw")
-            .set_expiration_time(i32::MAX);
-
-        reg_lic
-            .code_generate_binary(&reg_lic_data, RSAKey::Private)
-            .expect("Could not generate license")
+        self.licenses
+            .iter()
+            .find(|(rule, _)| rule.matches(browser_build))
+            .map_or(&self.fallback, |(_, license)| license)
+            .clone()
     }
 }
+
+fn read_key(key: &str) -> Result<Vec<u8>, LicenseConfigError> {
+    match key {
+        BUNDLED_AW_KEY => Ok(include_bytes!("keys/aw.priv").to_vec()),
+        BUNDLED_VORTEX_KEY => Ok(include_bytes!("keys/vortex.priv").to_vec()),
+        path => std::fs::read(path).map_err(|source| LicenseConfigError::ReadKey {
+            path: path.to_string(),
+            source,
+        }),
+    }
+}
+
+fn generate_license(ip: &SocketAddrV4, rule: &LicenseRule) -> Result<Vec<u8>, LicenseConfigError> {
+    let mut rsa = AWCryptRSA::default();
+    rsa.decode_private_key(&read_key(&rule.key)?)
+        .map_err(|_| LicenseConfigError::DecodeKey(rule.key.clone()))?;
+
+    let reg_lic_data = AWRegLicFields {
+        license_version: rule.license_version,
+        ip_address: Some(*ip.ip()),
+        port: Some(ip.port() as u32),
+        land_limit: rule.land_limit,
+        max_users: rule.max_users,
+        world_limit: rule.world_limit,
+        expiration_time: rule.expiration_time,
+        major_version: rule.major_version,
+        minor_version: rule.minor_version,
+        name: rule.name.clone(),
+        can_have_bots: rule.can_have_bots,
+    }
+    .build()
+    .map_err(|reason| LicenseConfigError::InvalidFields {
+        key: rule.key.clone(),
+        reason,
+    })?;
+
+    let mut reg_lic = AWRegLic::new(rsa);
+    reg_lic
+        .code_generate_binary(&reg_lic_data, RSAKey::Private)
+        .map_err(|_| LicenseConfigError::Encrypt(rule.key.clone()))
+}
@@ -2,14 +2,17 @@ use aw_core::*;
 use aw_db::DatabaseOpenError;
 
 use crate::{
+    backup::BackupScheduler,
     client::ClientInfo,
-    configuration,
+    configuration::{self, OwnerLicenseChanges, WorldQuotaConfig},
     database::UniverseDatabase,
-    get_conn, packet_handler,
+    get_conn, license_expiry, packet_handler,
     tabs::{regenerate_contact_list, regenerate_player_list, regenerate_world_list},
+    timestamp::unix_epoch_timestamp_u64,
     universe_connection::{UniverseConnectionID, UniverseConnections},
-    universe_license::LicenseGenerator,
-    UniverseConnection,
+    universe_license::{LicenseConfigError, LicenseGenerator},
+    world_auth::StartAttempts,
+    world_stats, UniverseConnection,
 };
 use std::{
     collections::HashMap,
@@ -25,21 +28,35 @@ use std::{sync::Arc, time::Instant};
 pub struct UniverseServer {
     pub config: configuration::UniverseConfig,
     pub license_generator: LicenseGenerator,
+    pub owner_license_changes: OwnerLicenseChanges,
+    pub world_quota: WorldQuotaConfig,
+    pub start_attempts: StartAttempts,
     pub connections: UniverseConnections,
     pub database: UniverseDatabase,
+    backup: BackupScheduler,
     listener: TcpListener,
+    key_log: Option<KeyLog>,
+    last_traffic_report: Instant,
+    last_license_check: Option<Instant>,
+    last_world_sample: Instant,
 }
 
+/// How often licenses are checked for upcoming expirations.
+const LICENSE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(thiserror::Error, Debug)]
 pub enum UniverseStartError {
     #[error("The Universe failed to open its database: {0}")]
     DatabaseOpenError(#[from] DatabaseOpenError),
     #[error("The Universe failed to initialize networking: {0}")]
     IoError(#[from] std::io::Error),
+    #[error("The Universe failed to prepare its licenses: {0}")]
+    LicenseConfig(#[from] LicenseConfigError),
 }
 
 impl UniverseServer {
     pub fn new(config: configuration::Config) -> Result<Self, UniverseStartError> {
+        let backup = BackupScheduler::new(config.backup, &config.sql);
         let database = UniverseDatabase::new(config.sql, &config.universe)?;
 
         // The Universe server provides a license to incoming clients, which must contain information
@@ -50,15 +67,33 @@ impl UniverseServer {
         let license_socket_addr =
             SocketAddrV4::new(config.universe.license_ip, config.universe.port);
 
+        let license_generator = LicenseGenerator::new(&license_socket_addr, &config.license)?;
+
         let listener = TcpListener::bind(bind_socket)?;
         listener.set_nonblocking(true)?;
 
+        let key_log = match &config.universe.key_log_file {
+            Some(path) => {
+                log::warn!("Logging stream keys to {path}. Anyone with this file can read captured traffic.");
+                Some(KeyLog::open(path)?)
+            }
+            None => None,
+        };
+
         Ok(Self {
             config: config.universe,
-            license_generator: LicenseGenerator::new(&license_socket_addr),
+            license_generator,
+            owner_license_changes: config.owner_license_changes,
+            world_quota: config.world_quota,
+            start_attempts: StartAttempts::default(),
             connections: UniverseConnections::new(),
             database,
+            backup,
             listener,
+            key_log,
+            last_traffic_report: Instant::now(),
+            last_license_check: None,
+            last_world_sample: Instant::now(),
         })
     }
 
@@ -87,12 +122,89 @@ impl UniverseServer {
             self.remove_dead_clients();
             self.connections.send_tab_updates();
             self.connections.send_heartbeats();
+            self.backup.run_if_due();
+            self.report_traffic_if_due();
+            self.check_licenses_if_due();
+            self.sample_worlds_if_due();
             sleep(Duration::from_millis(1));
         }
 
+        self.backup.finish();
+        self.report_database_metrics();
+        self.report_traffic();
         log::info!("Shutting down universe.");
     }
 
+    fn report_database_metrics(&self) {
+        log::info!("Database cache: {}", self.database.cache_stats());
+
+        // Only the most expensive statements are interesting
+        for (shape, stats) in self.database.query_metrics().iter().take(10) {
+            log::info!("Query {shape:?}: {stats}");
+        }
+    }
+
+    fn report_traffic_if_due(&mut self) {
+        if self.config.traffic_report_minutes == 0 {
+            return;
+        }
+
+        let interval = Duration::from_secs(self.config.traffic_report_minutes.saturating_mul(60));
+        if self.last_traffic_report.elapsed() < interval {
+            return;
+        }
+        self.last_traffic_report = Instant::now();
+
+        self.report_traffic();
+    }
+
+    fn check_licenses_if_due(&mut self) {
+        if let Some(last_check) = self.last_license_check {
+            if last_check.elapsed() < LICENSE_CHECK_INTERVAL {
+                return;
+            }
+        }
+        self.last_license_check = Some(Instant::now());
+
+        license_expiry::notify_expiring_licenses(self);
+    }
+
+    fn sample_worlds_if_due(&mut self) {
+        if self.config.world_stats_minutes == 0 {
+            return;
+        }
+
+        let interval = Duration::from_secs(self.config.world_stats_minutes.saturating_mul(60));
+        if self.last_world_sample.elapsed() < interval {
+            return;
+        }
+        self.last_world_sample = Instant::now();
+
+        world_stats::record_samples(self);
+
+        let now = unix_epoch_timestamp_u64();
+        if world_stats::downsample(&self.database, self.config.world_stats_raw_days, now).is_err() {
+            log::error!("Could not combine old world samples due to database error.");
+        }
+    }
+
+    fn report_traffic(&self) {
+        log::info!(
+            "Traffic since startup ({} connections open): {}",
+            self.connections.iter().len(),
+            self.connections.traffic_totals()
+        );
+
+        for (conn, stats) in self.connections.busiest_connections(5) {
+            log::info!(
+                "Traffic of {} ({}, connected {:?}): {stats}",
+                conn.addr(),
+                conn.describe(),
+                conn.age()
+            );
+        }
+    }
+
     fn protocol_version() -> &'static str {
         #[cfg(feature = "protocol_v4")]
         return "4";
@@ -110,7 +222,7 @@ impl UniverseServer {
         }
 
         while let Ok((stream, addr)) = self.listener.accept() {
-            let proto = match AWProtocol::new(stream) {
+            let mut proto = match AWProtocol::new(stream) {
                 Ok(proto) => proto,
                 Err(why) => {
                     log::error!(
@@ -119,7 +231,18 @@ impl UniverseServer {
                     continue;
                 }
             };
-            let conn = UniverseConnection::new(AWConnection::new(proto, addr));
+            proto.set_encoding(self.config.string_encoding);
+            proto.wait_for_encoding_after(PacketType::Login);
+            proto.set_limits(self.config.deserialize_limits());
+            if self.config.validate_packets {
+                proto.warn_on_schema_violations(schema::Direction::ClientToServer);
+            }
+            if let Some(key_log) = &self.key_log {
+                proto.set_key_log(key_log.clone());
+            }
+            let mut connection = AWConnection::new(proto, addr);
+            connection.set_send_queue_limit(self.config.send_queue_limit);
+            let conn = UniverseConnection::new(connection);
             self.connections.add_connection(conn);
             log::info!("{} connected.", addr.ip());
         }
@@ -173,6 +296,12 @@ impl UniverseServer {
         for cid in &disconnected_conn_ids {
             let conn = get_conn!(self, *cid, "remove_dead_clients");
             log::info!("Removed client {}", conn.addr().ip());
+            log::debug!(
+                "Traffic of {} over {:?}: {}",
+                conn.addr(),
+                conn.age(),
+                conn.traffic_stats()
+            );
         }
 
         // Figure out whether the player lists need to be remade, and remake them if so.
@@ -230,6 +359,7 @@ impl UniverseServer {
                 }
                 ProtocolMessage::StreamKey(_)
                 | ProtocolMessage::Encrypt(_)
+                | ProtocolMessage::Encoding(_)
                 | ProtocolMessage::PacketGroup(_) => {
                     panic!("Should not receive these message types on this end.");
                 }
@@ -251,8 +381,9 @@ impl UniverseServer {
         };
 
         match packet_type {
-            PacketType::PublicKeyResponse => packet_handler::public_key_response(self, cid, packet),
-            PacketType::StreamKeyResponse => packet_handler::stream_key_response(self, cid, packet),
+            PacketType::PublicKeyRequest
+            | PacketType::PublicKeyResponse
+            | PacketType::StreamKeyResponse => packet_handler::handshake(self, cid, packet),
             PacketType::AttributeChange => packet_handler::attribute_change(self, cid, packet),
             PacketType::Botgram => packet_handler::botgram(self, cid, packet),
             PacketType::CitizenAdd => packet_handler::citizen_add(self, cid, packet),
@@ -270,7 +401,6 @@ impl UniverseServer {
             PacketType::ContactChange => packet_handler::contact_change(self, cid, packet),
             PacketType::ContactDelete => packet_handler::contact_delete(self, cid, packet),
             PacketType::ContactList => packet_handler::contact_list(self, cid, packet),
-            PacketType::PublicKeyRequest => packet_handler::public_key_request(self, cid),
             PacketType::Heartbeat => packet_handler::heartbeat(self, cid),
             PacketType::Identify => packet_handler::identify(self, cid, packet),
             PacketType::LicenseAdd => packet_handler::license_add(self, cid, packet),
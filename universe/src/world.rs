@@ -1,4 +1,6 @@
+use aw_core::{AWPacket, PacketField, VarID};
 use num_derive::FromPrimitive;
+use std::collections::HashMap;
 
 #[derive(FromPrimitive, Debug, Copy, Clone, Default, PartialEq)]
 pub enum WorldRating {
@@ -16,6 +18,16 @@ impl WorldRating {
     }
 }
 
+impl PacketField for WorldRating {
+    fn from_packet(packet: &AWPacket, var: VarID) -> Option<Self> {
+        packet.get_byte(var).and_then(Self::from_u8)
+    }
+
+    fn add_to_packet(&self, packet: &mut AWPacket, var: VarID) {
+        packet.add_byte(var, *self as u8);
+    }
+}
+
 #[derive(Debug)]
 pub struct World {
     pub name: String,
@@ -24,6 +36,76 @@ pub struct World {
     pub max_users: u32,
     pub rating: WorldRating,
     pub user_count: u32,
+    /// Citizen who owns the world's license, or 0 if it has no owner
+    pub owner: u32,
+    /// Unix timestamp of when the world was started
+    pub started_at: u64,
+    pub visibility: WorldVisibility,
+    /// Citizens besides the owner who can see and enter the world when it is
+    /// not public
+    pub access_list: Vec<u32>,
+}
+
+impl World {
+    /// Whether the world is in the viewer's world list.
+    pub fn is_listed_for(&self, viewer: WorldViewer) -> bool {
+        self.visibility == WorldVisibility::Public || self.has_access(viewer)
+    }
+
+    /// Whether the viewer may look up the world's address to enter it.
+    pub fn can_look_up(&self, viewer: WorldViewer) -> bool {
+        self.visibility != WorldVisibility::Private || self.has_access(viewer)
+    }
+
+    fn has_access(&self, viewer: WorldViewer) -> bool {
+        if viewer.admin {
+            return true;
+        }
+
+        match viewer.citizen_id {
+            Some(citizen_id) => {
+                (self.owner != 0 && citizen_id == self.owner)
+                    || self.access_list.contains(&citizen_id)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Who can see a world in their world list and enter it.
+#[derive(FromPrimitive, Debug, Copy, Clone, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WorldVisibility {
+    /// Listed for and enterable by everyone
+    #[default]
+    Public = 0,
+    /// Only listed for the owner, admins and the access list, but enterable
+    /// by anyone who knows its name
+    Unlisted = 1,
+    /// Only listed for and enterable by the owner, admins and the access list
+    Private = 2,
+}
+
+impl WorldVisibility {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        num_traits::FromPrimitive::from_u32(value)
+    }
+
+    /// The visibility of a license, where the hidden flag that browsers can
+    /// set makes a public world unlisted.
+    pub fn of_license(visibility: Self, hidden: bool) -> Self {
+        if hidden && visibility == Self::Public {
+            Self::Unlisted
+        } else {
+            visibility
+        }
+    }
+}
+
+/// Someone who a world is being listed or looked up for.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WorldViewer {
+    pub citizen_id: Option<u32>,
+    pub admin: bool,
 }
 
 #[derive(Debug)]
@@ -31,6 +113,9 @@ pub struct WorldServer {
     pub build: u32,
     pub server_port: u16,
     pub worlds: Vec<World>,
+    /// What this world server must send back to start each world it was
+    /// challenged for, by lowercase world name
+    pub key_challenges: HashMap<String, Vec<u8>>,
 }
 
 impl WorldServer {
@@ -55,3 +140,58 @@ impl WorldServer {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_worlds_are_only_for_the_access_list() {
+        let world = World {
+            name: "secret".to_string(),
+            free_entry: true,
+            world_size: 10,
+            max_users: 10,
+            rating: WorldRating::G,
+            user_count: 0,
+            owner: 2,
+            started_at: 0,
+            visibility: WorldVisibility::Private,
+            access_list: vec![5],
+        };
+
+        let tourist = WorldViewer::default();
+        let owner = WorldViewer {
+            citizen_id: Some(2),
+            admin: false,
+        };
+        let friend = WorldViewer {
+            citizen_id: Some(5),
+            admin: false,
+        };
+        let stranger = WorldViewer {
+            citizen_id: Some(7),
+            admin: false,
+        };
+        let admin = WorldViewer {
+            citizen_id: Some(1),
+            admin: true,
+        };
+
+        for viewer in [owner, friend, admin] {
+            assert!(world.is_listed_for(viewer));
+            assert!(world.can_look_up(viewer));
+        }
+        for viewer in [tourist, stranger] {
+            assert!(!world.is_listed_for(viewer));
+            assert!(!world.can_look_up(viewer));
+        }
+
+        let unlisted = World {
+            visibility: WorldVisibility::Unlisted,
+            ..world
+        };
+        assert!(!unlisted.is_listed_for(stranger));
+        assert!(unlisted.can_look_up(stranger));
+    }
+}
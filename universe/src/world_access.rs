@@ -0,0 +1,82 @@
+//! Who can see and enter a world, read from its license and kept up to date
+//! for worlds that are already running.
+use aw_db::DatabaseResult;
+
+use crate::{
+    database::{license::LicenseQuery, LicenseDB, UniverseDatabase, WorldAccessDB},
+    tabs::regenerate_world_list,
+    world::WorldVisibility,
+    UniverseServer,
+};
+
+/// The visibility and access list of a license's world.
+pub fn license_access(
+    database: &UniverseDatabase,
+    lic: &LicenseQuery,
+) -> DatabaseResult<(WorldVisibility, Vec<u32>)> {
+    let visibility = match database.license_visibility(lic.id) {
+        DatabaseResult::Ok(visibility) => visibility
+            .and_then(WorldVisibility::from_u32)
+            .unwrap_or_default(),
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    };
+
+    let access_list = match database.world_access_list(lic.id) {
+        DatabaseResult::Ok(access_list) => access_list,
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    };
+
+    DatabaseResult::Ok((
+        WorldVisibility::of_license(visibility, lic.hidden != 0),
+        access_list,
+    ))
+}
+
+/// Re-read the owner, visibility and access list of a running world from its
+/// license. A world whose license is gone keeps its visibility until it
+/// stops, but nobody is on its access list anymore.
+pub fn refresh_world(server: &mut UniverseServer, name: &str) -> DatabaseResult<()> {
+    let lic = match server.database.license_by_name(name) {
+        DatabaseResult::Ok(Some(lic)) => lic,
+        DatabaseResult::Ok(None) => {
+            if let Some(world) = server.connections.get_world_by_name_mut(name) {
+                world.access_list.clear();
+            }
+            return DatabaseResult::Ok(());
+        }
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    };
+
+    let (visibility, access_list) = match license_access(&server.database, &lic) {
+        DatabaseResult::Ok(access) => access,
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    };
+
+    if let Some(world) = server.connections.get_world_by_name_mut(name) {
+        world.owner = lic.owner;
+        world.visibility = visibility;
+        world.access_list = access_list;
+    }
+
+    DatabaseResult::Ok(())
+}
+
+/// Refresh every running world, then rebuild everyone's world list so that
+/// license changes show up without restarting the worlds.
+pub fn refresh_all_worlds(server: &mut UniverseServer) {
+    let names = server
+        .connections
+        .worlds()
+        .map(|world| world.name.clone())
+        .collect::<Vec<_>>();
+
+    for name in names {
+        if refresh_world(server, &name).is_err() {
+            log::error!("Could not refresh who can see and enter world {name:?}");
+        }
+    }
+
+    for cid in server.connections.cids() {
+        regenerate_world_list(server, cid)
+    }
+}
@@ -0,0 +1,276 @@
+//! Restrictions on which world servers may start a world, beyond knowing the
+//! password of its license.
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+    time::{Duration, Instant},
+};
+
+use aw_core::{AWCryptRSA, ReasonCode};
+use rand::Rng;
+
+use crate::{
+    database::license::{LicenseQuery, ServerRestrictionQuery},
+    telegram, UniverseServer,
+};
+
+/// Start attempts for a world from different hosts this close together are
+/// reported as concurrent.
+const CONCURRENT_START_WINDOW: Duration = Duration::from_secs(60);
+
+/// Owners are told about refused starts of a world at most this often, so
+/// that a host retrying in a loop does not flood them with telegrams.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Random bytes a world server with a registered key is asked to decrypt.
+const CHALLENGE_LENGTH: usize = 32;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum StartRefusal {
+    #[error("{0} is not one of the addresses allowed to start the world")]
+    AddressNotAllowed(IpAddr),
+    #[error("the world server at {0} did not prove it holds the world's registered key")]
+    WrongServerKey(IpAddr),
+    #[error("the world is already running on {running}, so {attempt} may not start it")]
+    RunningElsewhere { running: IpAddr, attempt: IpAddr },
+}
+
+impl StartRefusal {
+    pub fn reason_code(&self) -> ReasonCode {
+        match self {
+            Self::AddressNotAllowed(_) => ReasonCode::Unauthorized,
+            Self::WrongServerKey(_) => ReasonCode::Imposter,
+            Self::RunningElsewhere { .. } => ReasonCode::WorldAlreadyStarted,
+        }
+    }
+}
+
+/// Check a world server at `ip` against the restrictions of a license.
+pub fn check_restrictions(
+    restrictions: &ServerRestrictionQuery,
+    ip: IpAddr,
+) -> Result<(), StartRefusal> {
+    if !address_allowed(&restrictions.allowed_addresses, ip) {
+        return Err(StartRefusal::AddressNotAllowed(ip));
+    }
+
+    Ok(())
+}
+
+/// Whether `ip` is in a comma separated list of IPv4 addresses and CIDR
+/// blocks. An empty list allows every address, and entries that cannot be
+/// parsed allow none.
+pub fn address_allowed(allowed: &str, ip: IpAddr) -> bool {
+    if allowed.trim().is_empty() {
+        return true;
+    }
+
+    // Dual-stack listeners see IPv4 peers as IPv4-mapped IPv6 addresses
+    let IpAddr::V4(ip) = ip.to_canonical() else {
+        return false;
+    };
+
+    allowed.split(',').any(|entry| match parse_block(entry) {
+        Ok((network, prefix)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        Err(_) => false,
+    })
+}
+
+/// Parse an IPv4 address or CIDR block such as `192.168.0.0/16` into its
+/// address and prefix length.
+pub fn parse_block(entry: &str) -> Result<(Ipv4Addr, u32), String> {
+    let entry = entry.trim();
+    let (address, prefix) = match entry.split_once('/') {
+        Some((address, prefix)) => {
+            let prefix = prefix
+                .parse::<u32>()
+                .ok()
+                .filter(|prefix| *prefix <= 32)
+                .ok_or_else(|| format!("{entry:?} has an invalid prefix length"))?;
+            (address, prefix)
+        }
+        None => (entry, 32),
+    };
+
+    let address = address
+        .parse::<Ipv4Addr>()
+        .map_err(|_| format!("{entry:?} is not an IPv4 address or CIDR block"))?;
+
+    Ok((address, prefix))
+}
+
+/// The form public keys are registered in, which is hex.
+pub fn encode_key(key: &[u8]) -> String {
+    key.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Inverse of [`encode_key`].
+pub fn decode_key(key: &str) -> Option<Vec<u8>> {
+    let key = key.trim();
+    if !key.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..key.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(key.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A challenge for a world server to prove it holds the private half of the
+/// key registered for a world. Knowing the public key is not enough, because
+/// only the private key decrypts the challenge.
+pub struct KeyChallenge {
+    /// What the world server must send back
+    pub expected: Vec<u8>,
+    /// `expected` encrypted with the registered public key
+    pub encrypted: Vec<u8>,
+}
+
+impl KeyChallenge {
+    /// A new challenge for a key registered with [`encode_key`], or `None` if
+    /// it is not a usable public key.
+    pub fn new(server_key: &str) -> Option<Self> {
+        let mut rsa = AWCryptRSA::default();
+        rsa.randomize();
+        rsa.decode_public_key(&decode_key(server_key)?).ok()?;
+
+        let mut expected = vec![0u8; CHALLENGE_LENGTH];
+        rand::thread_rng().fill(&mut expected[..]);
+        let encrypted = rsa.encrypt_public(&expected).ok()?;
+
+        Some(Self {
+            expected,
+            encrypted,
+        })
+    }
+}
+
+/// Recent start attempts of each world, to notice different hosts trying to
+/// start the same world at once.
+#[derive(Default)]
+pub struct StartAttempts {
+    attempts: HashMap<String, (IpAddr, Instant)>,
+    reported: HashMap<String, Instant>,
+}
+
+impl StartAttempts {
+    /// Record an attempt to start a world, returning the other host that
+    /// tried to start it shortly before, if any.
+    pub fn record(&mut self, world: &str, ip: IpAddr, now: Instant) -> Option<IpAddr> {
+        let previous = self.attempts.insert(world.to_lowercase(), (ip, now));
+
+        match previous {
+            Some((previous_ip, at))
+                if previous_ip != ip && now.duration_since(at) < CONCURRENT_START_WINDOW =>
+            {
+                Some(previous_ip)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the owner of a world should be told about another refused
+    /// start, which is not the case if they were told recently.
+    fn should_report(&mut self, world: &str, now: Instant) -> bool {
+        let key = world.to_lowercase();
+        match self.reported.get(&key) {
+            Some(at) if now.duration_since(*at) < REPORT_INTERVAL => false,
+            _ => {
+                self.reported.insert(key, now);
+                true
+            }
+        }
+    }
+}
+
+/// Log something suspicious about a start of a license's world, and tell
+/// the license's owner about it.
+pub fn report(server: &mut UniverseServer, lic: &LicenseQuery, message: &str) {
+    log::warn!("World {:?}: {message}", lic.name);
+
+    if lic.owner == 0
+        || !server
+            .start_attempts
+            .should_report(&lic.name, Instant::now())
+    {
+        return;
+    }
+
+    let notice = format!("World {}: {message}", lic.name);
+    if telegram::send_notice(server, lic.owner, &notice).is_err() {
+        log::error!("Could not tell citizen {} about {:?}", lic.owner, lic.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_match_blocks() {
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+
+        assert!(address_allowed("", ip));
+        assert!(address_allowed("192.168.1.20", ip));
+        assert!(address_allowed("10.0.0.0/8, 192.168.0.0/16", ip));
+        assert!(address_allowed("0.0.0.0/0", ip));
+        assert!(!address_allowed("192.168.2.0/24", ip));
+        assert!(!address_allowed("not an address", ip));
+        assert!(parse_block("10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn mapped_addresses_match_ipv4_blocks() {
+        let mapped = IpAddr::V6(Ipv4Addr::new(192, 168, 1, 20).to_ipv6_mapped());
+
+        assert!(address_allowed("192.168.1.20", mapped));
+        assert!(address_allowed("192.168.0.0/16", mapped));
+        assert!(!address_allowed("10.0.0.0/8", mapped));
+        assert!(!address_allowed(
+            "0.0.0.0/0",
+            "2001:db8::1".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn only_the_private_key_answers_challenges() {
+        let registered = AWCryptRSA::new();
+        let server_key = encode_key(&registered.encode_public_key().unwrap());
+        assert_eq!(decode_key(&server_key), registered.encode_public_key());
+
+        let challenge = KeyChallenge::new(&server_key).unwrap();
+        assert_ne!(challenge.encrypted, challenge.expected);
+        assert_eq!(
+            registered.decrypt_private(&challenge.encrypted).unwrap(),
+            challenge.expected
+        );
+
+        let other = AWCryptRSA::new();
+        assert_ne!(
+            other.decrypt_private(&challenge.encrypted).ok(),
+            Some(challenge.expected)
+        );
+
+        assert!(KeyChallenge::new("not a key").is_none());
+    }
+
+    #[test]
+    fn concurrent_starts_from_other_hosts() {
+        let mut attempts = StartAttempts::default();
+        let first = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let second = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let now = Instant::now();
+
+        assert_eq!(attempts.record("AW", first, now), None);
+        assert_eq!(attempts.record("aw", first, now), None);
+        assert_eq!(attempts.record("aw", second, now), Some(first));
+        assert_eq!(
+            attempts.record("aw", first, now + CONCURRENT_START_WINDOW),
+            None
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Limits on how many world licenses a citizen may hold and how many of
+//! their worlds may run at once.
+use aw_db::DatabaseResult;
+
+use crate::{
+    configuration::WorldQuotaConfig,
+    database::{quota::WorldQuotaQuery, QuotaDB, UniverseDatabase},
+};
+
+/// The quota an admin gave a citizen, or the universe default if they have none.
+pub fn citizen_quota(
+    database: &UniverseDatabase,
+    config: &WorldQuotaConfig,
+    citizen_id: u32,
+) -> DatabaseResult<WorldQuotaQuery> {
+    match database.world_quota_get(citizen_id) {
+        DatabaseResult::Ok(Some(quota)) => DatabaseResult::Ok(quota),
+        DatabaseResult::Ok(None) => DatabaseResult::Ok(WorldQuotaQuery {
+            citizen: citizen_id,
+            licenses: config.licenses,
+            running_worlds: config.running_worlds,
+        }),
+        DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+    }
+}
+
+/// The quota a license's owner is held to, or `None` for a license without
+/// an owner, which does not count against anyone's quota.
+pub fn owner_quota(
+    database: &UniverseDatabase,
+    config: &WorldQuotaConfig,
+    owner: u32,
+) -> DatabaseResult<Option<WorldQuotaQuery>> {
+    if owner == 0 {
+        return DatabaseResult::Ok(None);
+    }
+
+    match citizen_quota(database, config, owner) {
+        DatabaseResult::Ok(quota) => DatabaseResult::Ok(Some(quota)),
+        DatabaseResult::DatabaseError => DatabaseResult::DatabaseError,
+    }
+}
+
+/// Whether a citizen holding `held` licenses may add another one. `quota` is
+/// `None` for admins, who are not limited.
+pub fn can_add_license(quota: Option<&WorldQuotaQuery>, held: usize) -> bool {
+    quota.is_none_or(|quota| held < quota.licenses as usize)
+}
+
+/// Whether the owner of a license with `running` worlds up may start another
+/// one. `quota` is `None` for licenses without an owner, which are not limited.
+pub fn can_start_world(quota: Option<&WorldQuotaQuery>, running: usize) -> bool {
+    quota.is_none_or(|quota| quota.running_worlds == 0 || running < quota.running_worlds as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(licenses: u32, running_worlds: u32) -> WorldQuotaQuery {
+        WorldQuotaQuery {
+            citizen: 2,
+            licenses,
+            running_worlds,
+        }
+    }
+
+    #[test]
+    fn adding_licenses() {
+        let two = quota(2, 1);
+        assert!(can_add_license(Some(&two), 0));
+        assert!(can_add_license(Some(&two), 1));
+        assert!(!can_add_license(Some(&two), 2));
+        assert!(!can_add_license(Some(&two), 3));
+
+        // A quota of 0 leaves adding licenses to admins
+        assert!(!can_add_license(Some(&quota(0, 1)), 0));
+        assert!(can_add_license(None, 100));
+    }
+
+    #[test]
+    fn starting_worlds() {
+        let two = quota(1, 2);
+        assert!(can_start_world(Some(&two), 1));
+        assert!(!can_start_world(Some(&two), 2));
+
+        // A limit of 0 means no limit
+        assert!(can_start_world(Some(&quota(1, 0)), 100));
+        // Licenses without an owner are not limited
+        assert!(can_start_world(None, 100));
+    }
+}
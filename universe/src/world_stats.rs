@@ -0,0 +1,237 @@
+//! Recording how many users are in each world over time, and reporting the
+//! peak and average users of each world per day.
+use std::collections::BTreeMap;
+
+use aw_core::date::civil_from_days;
+use aw_db::DatabaseResult;
+use serde::Serialize;
+
+use crate::{
+    database::{
+        world_stats::{WorldDailyQuery, WorldSampleQuery},
+        UniverseDatabase, WorldStatsDB,
+    },
+    timestamp::{unix_epoch_timestamp_u64, SECONDS_PER_DAY},
+    UniverseServer,
+};
+
+/// Peak and average users of a world on one day.
+#[derive(Debug, Serialize)]
+pub struct DayReport {
+    /// The day as YYYY-MM-DD, in UTC
+    pub date: String,
+    pub peak_users: u32,
+    pub average_users: f64,
+    /// Longest time in seconds the world had been running when it was sampled
+    pub longest_uptime: u64,
+}
+
+/// Peak and average users of a world over the whole report.
+#[derive(Debug, Serialize)]
+pub struct WorldReport {
+    pub world: String,
+    pub peak_users: u32,
+    pub average_users: f64,
+    pub days: Vec<DayReport>,
+}
+
+/// Store a sample of every running world.
+pub fn record_samples(server: &UniverseServer) {
+    let now = unix_epoch_timestamp_u64();
+
+    for world in server.connections.worlds() {
+        let sample = WorldSampleQuery {
+            world: world.name.clone(),
+            timestamp: now,
+            users: world.user_count,
+            uptime: now.saturating_sub(world.started_at),
+        };
+
+        if server.database.world_sample_add(&sample).is_err() {
+            log::error!("Could not record statistics of world {:?}", world.name);
+        }
+    }
+}
+
+/// Combine the samples from before the last `raw_days` days into one entry
+/// per world and day, and delete them.
+pub fn downsample(database: &UniverseDatabase, raw_days: u64, now: u64) -> DatabaseResult<()> {
+    // Only whole days are combined, so that a day never has both an entry and samples
+    let cutoff = start_of_day(now.saturating_sub(raw_days.saturating_mul(SECONDS_PER_DAY)));
+
+    let samples = match database.world_samples_between(0, cutoff, None) {
+        DatabaseResult::Ok(samples) => samples,
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    };
+
+    if samples.is_empty() {
+        return DatabaseResult::Ok(());
+    }
+
+    // Either the days are added and their samples deleted, or neither
+    let r = database.transaction(|| {
+        for daily in combine_days(&samples) {
+            if database.world_daily_add(&daily).is_err() {
+                return DatabaseResult::DatabaseError;
+            }
+        }
+
+        database.world_samples_delete_before(cutoff)
+    });
+
+    if !r.is_err() {
+        log::debug!("Combined {} world samples into days", samples.len());
+    }
+    r
+}
+
+/// Peak and average users of every world, or of a single one, over the last
+/// `days` days including today.
+pub fn report(
+    database: &UniverseDatabase,
+    world: Option<&str>,
+    days: u64,
+    now: u64,
+) -> DatabaseResult<Vec<WorldReport>> {
+    let since = start_of_day(now).saturating_sub(days.saturating_sub(1) * SECONDS_PER_DAY);
+
+    let mut combined = match database.world_daily_since(since, world) {
+        DatabaseResult::Ok(combined) => combined,
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    };
+
+    // Recent days have not been combined yet
+    match database.world_samples_between(since, u64::MAX, world) {
+        DatabaseResult::Ok(samples) => combined.extend(combine_days(&samples)),
+        DatabaseResult::DatabaseError => return DatabaseResult::DatabaseError,
+    }
+
+    DatabaseResult::Ok(build_report(merge_days(combined)))
+}
+
+/// Midnight UTC at the start of the day a timestamp is in.
+fn start_of_day(timestamp: u64) -> u64 {
+    timestamp - timestamp % SECONDS_PER_DAY
+}
+
+/// Combine samples into one entry per world and day.
+fn combine_days(samples: &[WorldSampleQuery]) -> Vec<WorldDailyQuery> {
+    merge_days(samples.iter().map(|sample| WorldDailyQuery {
+        world: sample.world.clone(),
+        day: start_of_day(sample.timestamp),
+        samples: 1,
+        peak_users: sample.users,
+        total_users: u64::from(sample.users),
+        longest_uptime: sample.uptime,
+    }))
+}
+
+/// Merge entries of the same world and day, sorted by world and then day.
+fn merge_days(days: impl IntoIterator<Item = WorldDailyQuery>) -> Vec<WorldDailyQuery> {
+    let mut merged = BTreeMap::<(String, u64), WorldDailyQuery>::new();
+
+    for daily in days {
+        match merged.get_mut(&(daily.world.clone(), daily.day)) {
+            Some(existing) => {
+                existing.samples = existing.samples.saturating_add(daily.samples);
+                existing.peak_users = existing.peak_users.max(daily.peak_users);
+                existing.total_users = existing.total_users.saturating_add(daily.total_users);
+                existing.longest_uptime = existing.longest_uptime.max(daily.longest_uptime);
+            }
+            None => {
+                merged.insert((daily.world.clone(), daily.day), daily);
+            }
+        }
+    }
+
+    merged.into_values().collect()
+}
+
+fn build_report(days: Vec<WorldDailyQuery>) -> Vec<WorldReport> {
+    let mut reports = Vec::<WorldReport>::new();
+    let mut totals = Vec::<(u64, u64)>::new();
+
+    for daily in days {
+        if reports.last().map(|report| &report.world) != Some(&daily.world) {
+            reports.push(WorldReport {
+                world: daily.world.clone(),
+                peak_users: 0,
+                average_users: 0.0,
+                days: Vec::new(),
+            });
+            totals.push((0, 0));
+        }
+
+        let (Some(report), Some((samples, users))) = (reports.last_mut(), totals.last_mut()) else {
+            continue;
+        };
+
+        report.peak_users = report.peak_users.max(daily.peak_users);
+        *samples += u64::from(daily.samples);
+        *users += daily.total_users;
+        report.average_users = average(*users, *samples);
+        report.days.push(DayReport {
+            date: format_date(daily.day),
+            peak_users: daily.peak_users,
+            average_users: average(daily.total_users, u64::from(daily.samples)),
+            longest_uptime: daily.longest_uptime,
+        });
+    }
+
+    reports
+}
+
+fn average(total: u64, count: u64) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        total as f64 / count as f64
+    }
+}
+
+/// Format a timestamp as YYYY-MM-DD in UTC.
+fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(world: &str, timestamp: u64, users: u32) -> WorldSampleQuery {
+        WorldSampleQuery {
+            world: world.to_string(),
+            timestamp,
+            users,
+            uptime: timestamp % SECONDS_PER_DAY,
+        }
+    }
+
+    #[test]
+    fn reports_peak_and_average_per_day() {
+        let day = 19_000 * SECONDS_PER_DAY;
+        let samples = [
+            sample("aw", day + 60, 4),
+            sample("aw", day + 120, 10),
+            sample("aw", day + SECONDS_PER_DAY, 1),
+            sample("beta", day + 60, 3),
+        ];
+
+        let reports = build_report(combine_days(&samples));
+        assert_eq!(reports.len(), 2);
+
+        let aw = &reports[0];
+        assert_eq!(aw.world, "aw");
+        assert_eq!(aw.peak_users, 10);
+        assert_eq!(aw.average_users, 5.0);
+        assert_eq!(aw.days.len(), 2);
+        assert_eq!(aw.days[0].date, "2022-01-08");
+        assert_eq!(aw.days[0].peak_users, 10);
+        assert_eq!(aw.days[0].average_users, 7.0);
+        assert_eq!(aw.days[0].longest_uptime, 120);
+
+        assert_eq!(reports[1].world, "beta");
+        assert_eq!(format_date(0), "1970-01-01");
+    }
+}